@@ -1,12 +1,17 @@
 use std::sync::Arc;
 
 use axum::middleware::from_fn;
-use axum::{routing::post, Router};
+use axum::{
+    routing::{get, post},
+    Router,
+};
 
 pub mod generate_qr_code;
 mod middleware;
+pub mod telegram_status;
 
 use generate_qr_code::*;
+use telegram_status::*;
 
 use tower::ServiceBuilder;
 use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
@@ -39,6 +44,7 @@ pub fn get_router() -> Router<Arc<AppState>> {
             "/whatsapp/generate_qr_code",
             post(generate_whatsapp_qr_code),
         )
+        .route("/telegram/status", get(get_telegram_status))
         .layer(
             ServiceBuilder::new()
                 .layer(from_fn(middleware::jwt_middleware))
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::{debug_handler, Json};
+use telegram_bot::TelegramStatus;
+
+use crate::app::AppState;
+
+#[debug_handler]
+#[utoipa::path(
+    get,
+    tag = "Admin",
+    path = "/admin/telegram/status",
+    responses(
+        (status = 200, description = "Authorization and connection state of the Telegram client", example = json!({ "authorization": "ready", "connection": "ready", "restarts": 0 })),
+    ),
+    security(("admin" = []))
+)]
+pub async fn get_telegram_status(State(state): State<Arc<AppState>>) -> Json<TelegramStatus> {
+    Json(state.telegram.status())
+}
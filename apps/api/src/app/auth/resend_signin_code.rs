@@ -6,7 +6,7 @@ use crate::validation::validate_phone_number;
 use crate::whatsapp::WhatsAppStatus;
 use axum::extract::State;
 use axum::{debug_handler, http::StatusCode, Json};
-use log::info;
+use log::{info, warn};
 use mockall_double::double;
 use serde;
 use serde::{Deserialize, Serialize};
@@ -38,6 +38,7 @@ pub enum ResendSigninCodeStatus {
     MessageSent,
     WhatsAppError,
     TelegramError,
+    TelegramUnavailable,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -82,6 +83,16 @@ async fn send_signin_code_telegram(
     payload: &ResendSigninCodePayload,
     code: String,
 ) -> Result<(), (StatusCode, Json<ResendSigninCodeResponse>)> {
+    if !state.telegram.status().is_authorized() {
+        warn!(target: LOG_TARGET, "Telegram is not logged in, can't send signin code");
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ResendSigninCodeResponse {
+                status: ResendSigninCodeStatus::TelegramUnavailable,
+            }),
+        ));
+    }
+
     info!(target: LOG_TARGET, "Sending new signin code to {}", &payload.phone_number);
 
     let tg_user = state
@@ -186,7 +197,8 @@ async fn send_signin_code_telegram(
         (status = NOT_FOUND, description = "User was not found", body = ResendSigninCodeResponse, example = json!({ "status": "user_not_found" })),
         (status = BAD_REQUEST, description = "Target is not on WhatsApp", body = ResendSigninCodeResponse, example = json!({ "status": "target_not_on_whatsapp" })),
         (status = UNAUTHORIZED, description = "Signin code has expired", body = ResendSigninCodeResponse, example = json!({ "status": "code_expired" })),
-        (status = INTERNAL_SERVER_ERROR, description = "WhatsApp error", body = ResendSigninCodeResponse, example = json!({ "status": "whatsapp_error" }))
+        (status = INTERNAL_SERVER_ERROR, description = "WhatsApp error", body = ResendSigninCodeResponse, example = json!({ "status": "whatsapp_error" })),
+        (status = SERVICE_UNAVAILABLE, description = "Telegram account is logged out", body = ResendSigninCodeResponse, example = json!({ "status": "telegram_unavailable" }))
     )
 )]
 pub async fn resend_signin_code(
@@ -6,7 +6,7 @@ use crate::validation::validate_phone_number;
 use crate::whatsapp::WhatsAppStatus;
 use axum::extract::State;
 use axum::{debug_handler, http::StatusCode, Json};
-use log::info;
+use log::{info, warn};
 use mockall_double::double;
 use serde;
 use serde::{Deserialize, Serialize};
@@ -36,6 +36,7 @@ pub enum SendSigninCodeStatus {
     WhatsAppError,
     TargetNotOnTelegram,
     TelegramError,
+    TelegramUnavailable,
 }
 
 #[derive(Serialize, ToSchema)]
@@ -80,6 +81,16 @@ async fn send_signin_code_telegram(
     payload: &SendSigninCodePayload,
     code: String,
 ) -> Result<(), (StatusCode, Json<SendSigninCodeResponse>)> {
+    if !state.telegram.status().is_authorized() {
+        warn!(target: LOG_TARGET, "Telegram is not logged in, can't send signin code");
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(SendSigninCodeResponse {
+                status: SendSigninCodeStatus::TelegramUnavailable,
+            }),
+        ));
+    }
+
     info!(target: LOG_TARGET, "Sending new signin code to {}", &payload.phone_number);
 
     let tg_user = state
@@ -184,7 +195,8 @@ async fn send_signin_code_telegram(
         (status = BAD_REQUEST, description = "Target is not on Telegram", body = SendSigninCodeResponse, example = json!({ "status": "target_not_on_telegram" })),
         (status = BAD_REQUEST, description = "Target is not on WhatsApp", body = SendSigninCodeResponse, example = json!({ "status": "target_not_on_whatsapp" })),
         (status = INTERNAL_SERVER_ERROR, description = "WhatsApp error", body = SendSigninCodeResponse, example = json!({ "status": "whatsapp_error" })),
-        (status = INTERNAL_SERVER_ERROR, description = "Telegram error", body = SendSigninCodeResponse, example = json!({ "status": "telegram_error" }))
+        (status = INTERNAL_SERVER_ERROR, description = "Telegram error", body = SendSigninCodeResponse, example = json!({ "status": "telegram_error" })),
+        (status = SERVICE_UNAVAILABLE, description = "Telegram account is logged out", body = SendSigninCodeResponse, example = json!({ "status": "telegram_unavailable" }))
     )
 )]
 pub async fn send_signin_code(
@@ -72,4 +72,25 @@ mod tests {
 
         assert_eq!(user.phone_number, "+201096707442");
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn test_send_signin_code_telegram_logged_out() {
+        let (_db, server) = setup().await;
+
+        let payload = SendSigninCodePayload {
+            phone_number: "+201096707442".to_string(),
+            channel: MessagingChannel::Telegram,
+        };
+
+        let response = server.post("/send_signin_code").json(&payload).await;
+
+        response.assert_status(http::StatusCode::SERVICE_UNAVAILABLE);
+
+        response.assert_json(&json!(
+            {
+                "status": to_variant_name(&SendSigninCodeStatus::TelegramUnavailable).unwrap()
+            }
+        ));
+    }
 }
@@ -0,0 +1,203 @@
+use std::sync::Arc;
+
+use crate::app::sync::verify_chunk;
+use crate::app::{validate_payload, AppState};
+use crate::database::LoggedSyncEvent;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::{debug_handler, http::StatusCode, Json};
+use log::{debug, info, warn};
+use serde;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+static LOG_TARGET: &str = "Pull changes";
+
+/// Maximum number of changes returned in a single response
+pub static MAX_PULL_LIMIT: i64 = 500;
+
+#[derive(Serialize, Deserialize, Validate, Debug, ToSchema)]
+pub struct PullChangesPayload {
+    /// Sequence of the last change applied by the caller, 0 to pull from the beginning
+    #[validate(range(min = 0))]
+    pub after: i64,
+
+    #[validate(range(min = 1, max = 500))]
+    pub limit: Option<i64>,
+
+    /// Installation pulling the changes, the changes it uploaded are left out
+    #[serde(default)]
+    pub installation_id: Option<String>,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PullChangesStatus {
+    Ok,
+    CenterNotFound,
+    SignatureInvalid,
+    PayloadInvalid,
+    MissingHeaders,
+    DatabaseQueryError,
+}
+
+#[derive(Serialize, Debug, ToSchema)]
+pub struct PullChangesResponse {
+    status: PullChangesStatus,
+    changes: Vec<LoggedSyncEvent>,
+    /// Sequence to pull after next time
+    cursor: i64,
+    has_more: bool,
+}
+
+impl PullChangesResponse {
+    fn error(status: PullChangesStatus) -> Self {
+        Self {
+            status,
+            changes: Vec::new(),
+            cursor: 0,
+            has_more: false,
+        }
+    }
+}
+
+fn extract_header(header_name: &str, headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(header_name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
+
+#[debug_handler]
+#[utoipa::path(
+    post,
+    tag = "Synchronization",
+    path = "/sync/pull_changes",
+    request_body = PullChangesPayload,
+    params(
+        ("Signature", Header, description = "Signature of raw request body"),
+        ("Center-ID", Header, description = "Center ID"),
+    ),
+    responses(
+        (status = OK, description = "Changes after the requested sequence", body = PullChangesResponse, example = json!({ "status": "ok", "changes": [], "cursor": 0, "has_more": false })),
+        (status = UNAUTHORIZED, description = "Invalid or manipulated signature", body = PullChangesResponse, example = json!({ "status": "signature_invalid", "changes": [], "cursor": 0, "has_more": false })),
+        (status = NOT_FOUND, description = "Center not found", body = PullChangesResponse, example = json!({ "status": "center_not_found", "changes": [], "cursor": 0, "has_more": false }))
+    )
+)]
+pub async fn pull_changes(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Bytes,
+) -> (StatusCode, Json<PullChangesResponse>) {
+    debug!(target: LOG_TARGET, "Checking headers");
+
+    let (center_id, signature) = match (
+        extract_header("Center-ID", &headers),
+        extract_header("Signature", &headers),
+    ) {
+        (Some(center_id), Some(signature)) => (center_id, signature),
+        _ => {
+            warn!(target: LOG_TARGET, "Missing Center-ID or Signature header");
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(PullChangesResponse::error(
+                    PullChangesStatus::MissingHeaders,
+                )),
+            );
+        }
+    };
+
+    debug!(target: LOG_TARGET, "Checking if center {} exists", &center_id);
+
+    let center = match state.db.center.get_center(&center_id).await {
+        Some(center) => center,
+        None => {
+            warn!(target: LOG_TARGET, "Center not found");
+            return (
+                StatusCode::NOT_FOUND,
+                Json(PullChangesResponse::error(
+                    PullChangesStatus::CenterNotFound,
+                )),
+            );
+        }
+    };
+
+    if let Err(error) = verify_chunk(&center_id, &payload, &signature, &center.public_key).await {
+        warn!(target: LOG_TARGET, "Invalid signature for center {}: {:?}", &center_id, error);
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(PullChangesResponse::error(
+                PullChangesStatus::SignatureInvalid,
+            )),
+        );
+    }
+
+    let payload = match serde_json::from_slice::<PullChangesPayload>(&payload) {
+        Ok(payload) => payload,
+        Err(err) => {
+            warn!(target: LOG_TARGET, "Invalid payload {:?}", err.to_string());
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(PullChangesResponse::error(
+                    PullChangesStatus::PayloadInvalid,
+                )),
+            );
+        }
+    };
+
+    validate_payload(&payload);
+
+    let limit = payload.limit.unwrap_or(100).min(MAX_PULL_LIMIT);
+
+    match state
+        .db
+        .sync
+        .pull_sync_events(&center_id, payload.after, limit)
+        .await
+    {
+        Ok(changes) => {
+            info!(
+                target: LOG_TARGET,
+                "Sending {} changes after {} to center {}",
+                changes.len(),
+                payload.after,
+                &center_id
+            );
+
+            let cursor = changes
+                .last()
+                .map(|change| change.sequence)
+                .unwrap_or(payload.after);
+            let has_more = changes.len() as i64 == limit;
+
+            // The cursor still moves past the changes the caller uploaded itself
+            let changes = changes
+                .into_iter()
+                .filter(|change| {
+                    change.origin.is_none() || change.origin != payload.installation_id
+                })
+                .collect();
+
+            (
+                StatusCode::OK,
+                Json(PullChangesResponse {
+                    status: PullChangesStatus::Ok,
+                    changes,
+                    cursor,
+                    has_more,
+                }),
+            )
+        }
+        Err(err) => {
+            warn!(target: LOG_TARGET, "Error pulling changes: {:?}", err);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(PullChangesResponse::error(
+                    PullChangesStatus::DatabaseQueryError,
+                )),
+            )
+        }
+    }
+}
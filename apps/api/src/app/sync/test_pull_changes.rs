@@ -0,0 +1,220 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum_test::TestServer;
+    use serde_json::{json, Value};
+    use surrealdb::sql::{Datetime, Thing};
+    use telegram_bot::TelegramClient;
+
+    use crate::app::sync::PullChangesPayload;
+    use crate::database::{
+        local_structs::{Content, User},
+        SyncEvent,
+    };
+    use crate::{
+        app::{sync::get_router, AppState},
+        database::{Address, Center, Database, Record},
+    };
+
+    async fn setup() -> (Arc<Database>, TestServer) {
+        let db = Arc::new(Database::in_memory().await);
+        let telegram = TelegramClient::for_testing();
+        let state = Arc::new(AppState {
+            db: db.clone(),
+            telegram,
+        });
+        let router = get_router().with_state(state).into_make_service();
+
+        (db.clone(), TestServer::new(router).unwrap())
+    }
+
+    async fn create_center(db: &Database) -> String {
+        let center: Vec<Record> = db
+            .surreal
+            .create("center")
+            .content(Center {
+                name: "name".to_string(),
+                public_key: "public_key".to_string(),
+                owner: Thing::from(("user", "user_id")),
+                address: Address {
+                    city: "city".to_string(),
+                    country: "country".to_string(),
+                    line1: "line1".to_string(),
+                    state: "state".to_string(),
+                    landmark: None,
+                },
+            })
+            .await
+            .unwrap();
+
+        center.first().unwrap().id.to_string()
+    }
+
+    fn user_event(id: &str, name: &str) -> SyncEvent {
+        SyncEvent {
+            record_id: Thing::from(("user", id)),
+            event: "CREATE".to_string(),
+            content: Content::User(User {
+                name: name.to_string(),
+                // Phone numbers are unique across users
+                phone_number: format!("phone_number_{}", id),
+                password: "password".to_string(),
+            }),
+            created_at: Datetime::default(),
+        }
+    }
+
+    async fn pull(
+        db: &Database,
+        server: &TestServer,
+        center_id: &String,
+        after: i64,
+        limit: i64,
+    ) -> Value {
+        pull_as(db, server, center_id, after, limit, None).await
+    }
+
+    async fn pull_as(
+        db: &Database,
+        server: &TestServer,
+        center_id: &String,
+        after: i64,
+        limit: i64,
+        installation_id: Option<&str>,
+    ) -> Value {
+        // Inserting and pulling events switch the shared in-memory connection to the center
+        // database, centers are looked up in the main one
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("magmooty")
+            .await
+            .unwrap();
+
+        let payload = serde_json::to_string(&PullChangesPayload {
+            after,
+            limit: Some(limit),
+            installation_id: installation_id.map(|id| id.to_string()),
+        })
+        .unwrap();
+
+        let response = server
+            .post("/pull_changes")
+            .add_header("Signature".parse().unwrap(), "debug".parse().unwrap())
+            .add_header(
+                "Content-Type".parse().unwrap(),
+                "application/json".parse().unwrap(),
+            )
+            .add_header("Center-ID".parse().unwrap(), center_id.parse().unwrap())
+            .bytes(payload.bytes().collect())
+            .await;
+
+        response.assert_status_success();
+
+        response.json::<Value>()
+    }
+
+    #[tokio::test]
+    async fn test_pull_changes_after_cursor() {
+        let (db, server) = setup().await;
+
+        let center_id = create_center(&db).await;
+
+        db.sync
+            .insert_sync_events(
+                &center_id,
+                vec![user_event("id1", "first"), user_event("id2", "second")],
+                None,
+            )
+            .await
+            .unwrap();
+
+        db.sync
+            .insert_sync_events(&center_id, vec![user_event("id1", "third")], None)
+            .await
+            .unwrap();
+
+        let response = pull(&db, &server, &center_id, 0, 2).await;
+
+        assert_eq!(response["status"], "ok");
+        assert_eq!(response["cursor"], 2);
+        assert_eq!(response["has_more"], true);
+        assert_eq!(response["changes"][0]["sequence"], 1);
+        assert_eq!(response["changes"][0]["content"]["name"], "first");
+        assert_eq!(response["changes"][1]["content"]["name"], "second");
+
+        let response = pull(&db, &server, &center_id, 2, 2).await;
+
+        assert_eq!(response["cursor"], 3);
+        assert_eq!(response["has_more"], false);
+        assert_eq!(response["changes"].as_array().unwrap().len(), 1);
+        assert_eq!(response["changes"][0]["content"]["name"], "third");
+
+        let response = pull(&db, &server, &center_id, 3, 2).await;
+
+        assert_eq!(response["cursor"], 3);
+        assert_eq!(response["changes"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_pull_changes_of_other_installations() {
+        let (db, server) = setup().await;
+
+        let center_id = create_center(&db).await;
+        let first = "first".to_string();
+        let second = "second".to_string();
+
+        db.sync
+            .insert_sync_events(&center_id, vec![user_event("id1", "first")], Some(&first))
+            .await
+            .unwrap();
+
+        db.sync
+            .insert_sync_events(&center_id, vec![user_event("id2", "second")], Some(&second))
+            .await
+            .unwrap();
+
+        db.sync
+            .insert_sync_events(&center_id, vec![user_event("id3", "unknown")], None)
+            .await
+            .unwrap();
+
+        let response = pull_as(&db, &server, &center_id, 0, 10, Some("first")).await;
+
+        assert_eq!(response["cursor"], 3);
+        assert_eq!(response["changes"].as_array().unwrap().len(), 2);
+        assert_eq!(response["changes"][0]["content"]["name"], "second");
+        assert_eq!(response["changes"][1]["content"]["name"], "unknown");
+
+        // A page of the caller's own changes still moves the cursor
+        let response = pull_as(&db, &server, &center_id, 0, 1, Some("first")).await;
+
+        assert_eq!(response["cursor"], 1);
+        assert_eq!(response["has_more"], true);
+        assert_eq!(response["changes"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_pull_changes_center_not_found() {
+        let (_db, server) = setup().await;
+
+        let payload = serde_json::to_string(&PullChangesPayload {
+            after: 0,
+            limit: None,
+            installation_id: None,
+        })
+        .unwrap();
+
+        let response = server
+            .post("/pull_changes")
+            .add_header("Signature".parse().unwrap(), "debug".parse().unwrap())
+            .add_header(
+                "Center-ID".parse().unwrap(),
+                "center:missing".parse().unwrap(),
+            )
+            .bytes(payload.bytes().collect())
+            .await;
+
+        response.assert_status_not_found();
+    }
+}
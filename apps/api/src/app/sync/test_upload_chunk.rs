@@ -5,10 +5,13 @@ mod tests {
     use axum_test::TestServer;
     use base64::Engine;
     use bytes::Bytes;
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
     use rsa::pkcs1v15::SigningKey;
     use rsa::signature::SignatureEncoding;
     use rsa::{pkcs1::DecodeRsaPrivateKey, sha2::Sha256, signature::SignerMut, RsaPrivateKey};
     use serde_json::json;
+    use std::io::Write;
     use surrealdb::sql::{Datetime, Thing};
     use telegram_bot::TelegramClient;
 
@@ -100,6 +103,7 @@ mod tests {
                 }),
                 created_at: Datetime::default(),
             }],
+            installation_id: None,
         };
 
         let payload = serde_json::to_string(&payload).unwrap();
@@ -175,6 +179,7 @@ mod tests {
                 }),
                 created_at: Datetime::default(),
             }],
+            installation_id: None,
         };
 
         let payload = serde_json::to_string(&payload).unwrap();
@@ -252,4 +257,119 @@ mod tests {
             }
         ));
     }
+
+    async fn create_center(db: &Database) -> String {
+        let center: Vec<Record> = db
+            .surreal
+            .create("center")
+            .content(Center {
+                name: "name".to_string(),
+                public_key: "public_key".to_string(),
+                owner: Thing::from(("user", "user_id")),
+                address: Address {
+                    city: "city".to_string(),
+                    country: "country".to_string(),
+                    line1: "line1".to_string(),
+                    state: "state".to_string(),
+                    landmark: None,
+                },
+            })
+            .await
+            .unwrap();
+
+        center.first().unwrap().id.to_string()
+    }
+
+    fn user_chunk() -> String {
+        serde_json::to_string(&UploadChunkPayload {
+            chunk: vec![SyncEvent {
+                record_id: Thing::from(("user", "id1")),
+                event: "CREATE".to_string(),
+                content: Content::User(User {
+                    name: "name".to_string(),
+                    phone_number: "phone_number".to_string(),
+                    password: "password".to_string(),
+                }),
+                created_at: Datetime::default(),
+            }],
+            installation_id: None,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_compressed_sync() {
+        let (db, server) = setup().await;
+        let center_id = create_center(&db).await;
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(user_chunk().as_bytes()).unwrap();
+        let body = encoder.finish().unwrap();
+
+        let response = server
+            .post("/upload_chunk")
+            .add_header("Signature".parse().unwrap(), "debug".parse().unwrap())
+            .add_header(
+                "Content-Type".parse().unwrap(),
+                "application/json".parse().unwrap(),
+            )
+            .add_header("Content-Encoding".parse().unwrap(), "gzip".parse().unwrap())
+            .add_header("Center-ID".parse().unwrap(), center_id.parse().unwrap())
+            .bytes(Bytes::from(body))
+            .await;
+
+        response.assert_status_success();
+
+        response.assert_json(&json!(
+            {
+                "status": "accepted"
+            }
+        ));
+
+        // Inserting events switches the shared in-memory connection to the center database
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("magmooty")
+            .await
+            .unwrap();
+
+        // Uncompressed bytes sent as gzip
+        let response = server
+            .post("/upload_chunk")
+            .add_header("Signature".parse().unwrap(), "debug".parse().unwrap())
+            .add_header("Content-Encoding".parse().unwrap(), "gzip".parse().unwrap())
+            .add_header("Center-ID".parse().unwrap(), center_id.parse().unwrap())
+            .bytes(user_chunk().bytes().collect())
+            .await;
+
+        response.assert_status_bad_request();
+
+        response.assert_json(&json!(
+            {
+                "status": "decompression_error"
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_encoding() {
+        let (db, server) = setup().await;
+        let center_id = create_center(&db).await;
+
+        let response = server
+            .post("/upload_chunk")
+            .add_header("Signature".parse().unwrap(), "debug".parse().unwrap())
+            .add_header("Content-Encoding".parse().unwrap(), "br".parse().unwrap())
+            .add_header("Center-ID".parse().unwrap(), center_id.parse().unwrap())
+            .bytes(user_chunk().bytes().collect())
+            .await;
+
+        response.assert_status(http::StatusCode::UNSUPPORTED_MEDIA_TYPE);
+
+        response.assert_json(&json!(
+            {
+                "status": "unsupported_encoding"
+            }
+        ));
+    }
 }
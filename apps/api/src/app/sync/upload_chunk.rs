@@ -2,27 +2,36 @@ use std::sync::Arc;
 
 use crate::app::{validate_payload, AppState};
 use crate::database::local_structs::{self, Content};
-use crate::database::SyncEvent;
+use crate::database::{LoggedSyncEvent, SyncEvent};
 use axum::body::Bytes;
 use axum::extract::State;
 use axum::http::HeaderMap;
 use axum::{debug_handler, http::StatusCode, Json};
 use base64::Engine;
+use flate2::read::GzDecoder;
 use log::{debug, info, warn};
 use rsa::pkcs1v15::{Signature, VerifyingKey};
 use rsa::signature::Verifier;
 use rsa::{pkcs1::DecodeRsaPublicKey, sha2::Sha256, RsaPublicKey};
 use serde;
 use serde::{Deserialize, Serialize};
+use std::io::Read;
 use utoipa::openapi::{KnownFormat, ObjectBuilder, RefOr, Schema, SchemaFormat, SchemaType};
 use utoipa::{Modify, ToSchema};
 use validator::Validate;
 
 static LOG_TARGET: &str = "Upload chunk";
 
+/// Largest chunk accepted once decompressed
+static MAX_DECOMPRESSED_CHUNK_SIZE: u64 = 32 * 1024 * 1024;
+
 #[derive(Serialize, Deserialize, Validate, Debug, ToSchema)]
 pub struct UploadChunkPayload {
     pub chunk: Vec<SyncEvent>,
+
+    /// Installation uploading the chunk, it isn't sent back its own changes when pulling
+    #[serde(default)]
+    pub installation_id: Option<String>,
 }
 
 pub struct ChunkUploadSchemasAddon;
@@ -66,6 +75,9 @@ impl Modify for ChunkUploadSchemasAddon {
         let (name, schema) = SyncEvent::schema();
         components.schemas.insert(name.to_string(), schema);
 
+        let (name, schema) = LoggedSyncEvent::schema();
+        components.schemas.insert(name.to_string(), schema);
+
         let (name, schema) = local_structs::AcademicYear::schema();
         components.schemas.insert(name.to_string(), schema);
 
@@ -105,6 +117,8 @@ pub enum UploadChunkStatus {
     SignatureInvalid,
     ChunkInvalid,
     MissingHeaders,
+    UnsupportedEncoding,
+    DecompressionError,
     Base64DecodeError,
     PrivateKeyParseError,
     DatabaseUploadError,
@@ -115,7 +129,7 @@ pub struct UploadChunkResponse {
     status: UploadChunkStatus,
 }
 
-async fn verify_chunk(
+pub(super) async fn verify_chunk(
     center_id: &String,
     body: &Bytes,
     signature: &String,
@@ -194,6 +208,60 @@ fn extract_header(
     }
 }
 
+/// Decompress the body according to its `Content-Encoding`, uncompressed bodies are passed through
+fn decode_body(
+    headers: &HeaderMap,
+    payload: Bytes,
+) -> Result<Bytes, (StatusCode, Json<UploadChunkResponse>)> {
+    let encoding = match headers.get("Content-Encoding") {
+        Some(encoding) => encoding.to_str().unwrap_or_default().trim().to_lowercase(),
+        None => return Ok(payload),
+    };
+
+    match encoding.as_str() {
+        "" | "identity" => Ok(payload),
+        "gzip" => {
+            let mut body = Vec::new();
+
+            // Read one byte past the limit to tell a body of exactly the limit from a bigger one
+            let read = GzDecoder::new(payload.as_ref())
+                .take(MAX_DECOMPRESSED_CHUNK_SIZE + 1)
+                .read_to_end(&mut body);
+
+            match read {
+                Ok(size) if size as u64 <= MAX_DECOMPRESSED_CHUNK_SIZE => Ok(Bytes::from(body)),
+                Ok(_) => {
+                    warn!(target: LOG_TARGET, "Decompressed chunk is too large");
+                    Err((
+                        StatusCode::PAYLOAD_TOO_LARGE,
+                        Json(UploadChunkResponse {
+                            status: UploadChunkStatus::ChunkInvalid,
+                        }),
+                    ))
+                }
+                Err(err) => {
+                    warn!(target: LOG_TARGET, "Error decompressing chunk: {:?}", err);
+                    Err((
+                        StatusCode::BAD_REQUEST,
+                        Json(UploadChunkResponse {
+                            status: UploadChunkStatus::DecompressionError,
+                        }),
+                    ))
+                }
+            }
+        }
+        _ => {
+            warn!(target: LOG_TARGET, "Unsupported content encoding {}", encoding);
+            Err((
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                Json(UploadChunkResponse {
+                    status: UploadChunkStatus::UnsupportedEncoding,
+                }),
+            ))
+        }
+    }
+}
+
 fn parse_payload(
     payload: &Bytes,
 ) -> Result<UploadChunkPayload, (StatusCode, Json<UploadChunkResponse>)> {
@@ -218,13 +286,15 @@ fn parse_payload(
     path = "/sync/upload_chunk",
     request_body = UploadChunkPayload,
     params(
-        ("Signature", Header, description = "Signature of raw request body"),
+        ("Signature", Header, description = "Signature of the request body before compression"),
+        ("Content-Encoding", Header, description = "Optional, `gzip` for compressed bodies"),
         ("Center-ID", Header, description = "Center ID"),
     ),
     responses(
         (status = OK, description = "Chunk uploaded", body = UploadChunkResponse, example = json!({ "status": "accepted" })),
         (status = UNAUTHORIZED, description = "Invalid or manipulated signature", body = UploadChunkResponse, example = json!({ "status": "signature_invalid" })),
-        (status = NOT_FOUND, description = "Center not found", body = UploadChunkResponse, example = json!({ "status": "center_not_found" }))
+        (status = NOT_FOUND, description = "Center not found", body = UploadChunkResponse, example = json!({ "status": "center_not_found" })),
+        (status = UNSUPPORTED_MEDIA_TYPE, description = "Unsupported content encoding", body = UploadChunkResponse, example = json!({ "status": "unsupported_encoding" }))
     )
 )]
 pub async fn upload_chunk(
@@ -262,6 +332,12 @@ pub async fn upload_chunk(
         }
     };
 
+    // The signature covers the uncompressed body so it doesn't depend on the compression used
+    let payload = match decode_body(&headers, payload) {
+        Ok(payload) => payload,
+        Err(response) => return response,
+    };
+
     debug!(target: LOG_TARGET, "Checking chunk signature for center {}", &center_id);
 
     match verify_chunk(&center_id, &payload, &signature, &center.public_key).await {
@@ -288,7 +364,7 @@ pub async fn upload_chunk(
         .db
         .clone()
         .sync
-        .insert_sync_events(&center_id, payload.chunk)
+        .insert_sync_events(&center_id, payload.chunk, payload.installation_id.as_ref())
         .await
     {
         Ok(_) => (
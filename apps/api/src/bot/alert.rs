@@ -0,0 +1,48 @@
+use log::{error, info};
+use telegram_bot::{TelegramAlertHandler, TelegramStatus};
+
+use crate::whatsapp::{WhatsAppBot, WhatsAppStatus};
+
+static LOG_TARGET: &str = "Telegram alerts";
+
+/// Tells admins over WhatsApp that the Telegram account has to be logged in again
+pub struct WhatsAppAlertHandler {
+    phone_numbers: Vec<String>,
+}
+
+impl WhatsAppAlertHandler {
+    pub fn new(phone_numbers: Vec<String>) -> Self {
+        Self { phone_numbers }
+    }
+
+    /// Parse a comma separated list of phone numbers, e.g. `+201000000000,+201111111111`
+    pub fn from_setting(phone_numbers: &str) -> Self {
+        Self::new(
+            phone_numbers
+                .split(',')
+                .map(|phone_number| phone_number.trim().to_string())
+                .filter(|phone_number| !phone_number.is_empty())
+                .collect(),
+        )
+    }
+}
+
+#[async_trait::async_trait]
+impl TelegramAlertHandler for WhatsAppAlertHandler {
+    async fn handle_login_required(&self, _: &TelegramStatus) {
+        let message = "Magmooty's Telegram account is logged out, please log in again. Sign in codes can't be sent through Telegram until then".to_string();
+
+        for phone_number in &self.phone_numbers {
+            info!(target: LOG_TARGET, "Alerting {} that Telegram login is required", phone_number);
+
+            let response = WhatsAppBot::send_message(phone_number.clone(), message.clone()).await;
+
+            if !matches!(response.status, WhatsAppStatus::MessageSent) {
+                error!(
+                    target: LOG_TARGET,
+                    "Failed to alert {}: {:?} {}", phone_number, response.status, response.error_message
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use log::error;
+use telegram_bot::functions::{FormattedText, TextEntity, TextEntityType};
+use telegram_bot::{CommandContext, CommandHandler};
+
+use crate::database::{AttendanceRecord, Database};
+
+use super::{find_sender_students, LOG_TARGET};
+
+/// Classes shown for every student
+static ATTENDANCE_LIMIT: i64 = 10;
+
+pub struct AttendanceCommand {
+    db: Arc<Database>,
+}
+
+impl AttendanceCommand {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+fn format_record(record: &AttendanceRecord) -> String {
+    format!(
+        "  {} {} {}: {}",
+        record.attended_at.format("%Y-%m-%d"),
+        record.grade,
+        record.subjects.join(", "),
+        if record.present { "Present" } else { "Absent" }
+    )
+}
+
+/// Render the latest attendance of a single student
+pub fn format_student_attendance(student_name: &str, attendance: &[AttendanceRecord]) -> String {
+    let mut lines = vec![student_name.to_string()];
+
+    if attendance.is_empty() {
+        lines.push("  No attendance recorded".to_string());
+    }
+
+    lines.extend(attendance.iter().map(format_record));
+
+    lines.join("\n")
+}
+
+#[async_trait::async_trait]
+impl CommandHandler for AttendanceCommand {
+    async fn handle(&self, context: &CommandContext) -> Option<FormattedText> {
+        let students = match find_sender_students(&self.db, context).await {
+            Ok(students) => students,
+            Err(error) => return Some(error.reply()),
+        };
+
+        if students.is_empty() {
+            return Some(FormattedText::new(
+                "We couldn't find any students registered with your phone number".to_string(),
+            ));
+        }
+
+        let mut text = String::new();
+        let mut entities = Vec::new();
+
+        for student in students {
+            let attendance = match self
+                .db
+                .parent
+                .find_student_attendance(&student, ATTENDANCE_LIMIT)
+                .await
+            {
+                Ok(attendance) => attendance,
+                Err(err) => {
+                    error!(target: LOG_TARGET, "Failed to find student attendance: {}", err);
+                    return Some(FormattedText::new(
+                        "Something went wrong, please try again later".to_string(),
+                    ));
+                }
+            };
+
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+
+            entities.push(TextEntity::new(
+                text.encode_utf16().count() as i32,
+                student.student_name.encode_utf16().count() as i32,
+                TextEntityType::Bold,
+            ));
+            text.push_str(&format_student_attendance(
+                &student.student_name,
+                &attendance,
+            ));
+        }
+
+        Some(FormattedText::with_entities(text, entities))
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use log::error;
+use telegram_bot::functions::{FormattedText, TextEntity, TextEntityType};
+use telegram_bot::{CommandContext, CommandHandler};
+
+use crate::database::{CourseBalance, Database};
+
+use super::{find_sender_students, LOG_TARGET};
+
+pub struct BalanceCommand {
+    db: Arc<Database>,
+}
+
+impl BalanceCommand {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+/// Amounts are whole most of the time, don't show decimals unless needed
+pub fn format_amount(amount: f64) -> String {
+    if amount.fract() == 0.0 {
+        format!("{}", amount as i64)
+    } else {
+        format!("{:.2}", amount)
+    }
+}
+
+/// Render the balances of a single student
+pub fn format_student_balance(student_name: &str, balances: &[CourseBalance]) -> String {
+    let mut lines = vec![student_name.to_string()];
+
+    if balances.is_empty() {
+        lines.push("  Not enrolled in any course".to_string());
+    }
+
+    for balance in balances {
+        lines.push(format!("{} {}", balance.grade, balance.subjects.join(", ")));
+        lines.push(format!(
+            "  Fees {}, paid {}, remaining {}",
+            format_amount(balance.fees),
+            format_amount(balance.paid),
+            format_amount((balance.fees - balance.paid).max(0.0))
+        ));
+    }
+
+    lines.join("\n")
+}
+
+#[async_trait::async_trait]
+impl CommandHandler for BalanceCommand {
+    async fn handle(&self, context: &CommandContext) -> Option<FormattedText> {
+        let students = match find_sender_students(&self.db, context).await {
+            Ok(students) => students,
+            Err(error) => return Some(error.reply()),
+        };
+
+        if students.is_empty() {
+            return Some(FormattedText::new(
+                "We couldn't find any students registered with your phone number".to_string(),
+            ));
+        }
+
+        let mut text = String::new();
+        let mut entities = Vec::new();
+
+        for student in students {
+            let balances = match self.db.parent.find_student_balance(&student).await {
+                Ok(balances) => balances,
+                Err(err) => {
+                    error!(target: LOG_TARGET, "Failed to find student balance: {}", err);
+                    return Some(FormattedText::new(
+                        "Something went wrong, please try again later".to_string(),
+                    ));
+                }
+            };
+
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+
+            entities.push(TextEntity::new(
+                text.encode_utf16().count() as i32,
+                student.student_name.encode_utf16().count() as i32,
+                TextEntityType::Bold,
+            ));
+            text.push_str(&format_student_balance(&student.student_name, &balances));
+        }
+
+        Some(FormattedText::with_entities(text, entities))
+    }
+}
@@ -0,0 +1,109 @@
+use std::sync::Arc;
+
+use log::error;
+use telegram_bot::functions::{FormattedText, GetUser};
+use telegram_bot::{CommandContext, CommandRouter, TdLibType, TelegramMessageSender, TelegramUser};
+
+use crate::database::{CenterStudent, Database};
+
+mod alert;
+mod attendance;
+mod balance;
+mod schedule;
+mod start;
+
+mod test_attendance;
+mod test_balance;
+mod test_schedule;
+
+pub use alert::*;
+pub use attendance::*;
+pub use balance::*;
+pub use schedule::*;
+pub use start::*;
+
+static LOG_TARGET: &str = "Telegram bot";
+
+/// Commands parents and students can send to the Telegram account
+pub fn create_command_router(db: Arc<Database>) -> CommandRouter {
+    CommandRouter::new()
+        .register(
+            "start",
+            "Link your phone number and list your students",
+            StartCommand::new(db.clone()),
+        )
+        .register(
+            "schedule",
+            "Show the weekly class schedule",
+            ScheduleCommand::new(db.clone()),
+        )
+        .register(
+            "balance",
+            "Show the remaining balance",
+            BalanceCommand::new(db.clone()),
+        )
+        .register(
+            "attendance",
+            "Show the latest attendance record",
+            AttendanceCommand::new(db.clone()),
+        )
+}
+
+/// Reasons a sender can't be matched to students
+pub enum SenderStudentsError {
+    PhoneNumberHidden,
+    TelegramError,
+    DatabaseError,
+}
+
+impl SenderStudentsError {
+    pub fn reply(&self) -> FormattedText {
+        let message = match self {
+            SenderStudentsError::PhoneNumberHidden => {
+                "We can't see your phone number, please share your contact with us and try again"
+            }
+            SenderStudentsError::TelegramError | SenderStudentsError::DatabaseError => {
+                "Something went wrong, please try again later"
+            }
+        };
+
+        FormattedText::new(message.to_string())
+    }
+}
+
+/// Find the students registered with the phone number of the sender of the command
+pub async fn find_sender_students(
+    db: &Database,
+    context: &CommandContext,
+) -> Result<Vec<CenterStudent>, SenderStudentsError> {
+    let user_id = match context.message.sender_id {
+        TelegramMessageSender::User { user_id } => user_id,
+        TelegramMessageSender::Chat { .. } => return Err(SenderStudentsError::PhoneNumberHidden),
+    };
+
+    let client = &context.client;
+
+    let user = client
+        .send(GetUser::new(client, user_id))
+        .await
+        .map_err(|_| SenderStudentsError::TelegramError)?;
+
+    if matches!(user.td_type, TdLibType::Error) {
+        return Err(SenderStudentsError::TelegramError);
+    }
+
+    let user = serde_json::from_value::<TelegramUser>(user.data)
+        .map_err(|_| SenderStudentsError::TelegramError)?;
+
+    if user.phone_number.is_empty() {
+        return Err(SenderStudentsError::PhoneNumberHidden);
+    }
+
+    db.parent
+        .find_students_by_phone_number(&user.phone_number)
+        .await
+        .map_err(|err| {
+            error!(target: LOG_TARGET, "Failed to find students: {}", err);
+            SenderStudentsError::DatabaseError
+        })
+}
@@ -0,0 +1,101 @@
+use std::sync::Arc;
+
+use log::error;
+use telegram_bot::functions::{FormattedText, TextEntity, TextEntityType};
+use telegram_bot::{CommandContext, CommandHandler};
+
+use crate::database::{ClassSchedule, CourseSchedule, Database};
+
+use super::{find_sender_students, LOG_TARGET};
+
+pub struct ScheduleCommand {
+    db: Arc<Database>,
+}
+
+impl ScheduleCommand {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+fn format_time(minutes: i64) -> String {
+    format!("{:02}:{:02}", minutes / 60, minutes % 60)
+}
+
+fn format_class(class: &ClassSchedule) -> String {
+    format!(
+        "  {} {} - {}",
+        class.day,
+        format_time(class.start),
+        format_time(class.end)
+    )
+}
+
+/// Render the schedules of a single student
+pub fn format_student_schedule(student_name: &str, schedules: &[CourseSchedule]) -> String {
+    let mut lines = vec![student_name.to_string()];
+
+    if schedules.is_empty() {
+        lines.push("  No classes scheduled".to_string());
+    }
+
+    for schedule in schedules {
+        lines.push(format!(
+            "{} {}",
+            schedule.grade,
+            schedule.subjects.join(", ")
+        ));
+
+        let mut classes = schedule.schedule.clone();
+        classes.sort_by_key(|class| class.class_order);
+
+        lines.extend(classes.iter().map(format_class));
+    }
+
+    lines.join("\n")
+}
+
+#[async_trait::async_trait]
+impl CommandHandler for ScheduleCommand {
+    async fn handle(&self, context: &CommandContext) -> Option<FormattedText> {
+        let students = match find_sender_students(&self.db, context).await {
+            Ok(students) => students,
+            Err(error) => return Some(error.reply()),
+        };
+
+        if students.is_empty() {
+            return Some(FormattedText::new(
+                "We couldn't find any students registered with your phone number".to_string(),
+            ));
+        }
+
+        let mut text = String::new();
+        let mut entities = Vec::new();
+
+        for student in students {
+            let schedules = match self.db.parent.find_student_schedule(&student).await {
+                Ok(schedules) => schedules,
+                Err(err) => {
+                    error!(target: LOG_TARGET, "Failed to find student schedule: {}", err);
+                    return Some(FormattedText::new(
+                        "Something went wrong, please try again later".to_string(),
+                    ));
+                }
+            };
+
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+
+            // Student names are bolded, offsets are in UTF-16 code units
+            entities.push(TextEntity::new(
+                text.encode_utf16().count() as i32,
+                student.student_name.encode_utf16().count() as i32,
+                TextEntityType::Bold,
+            ));
+            text.push_str(&format_student_schedule(&student.student_name, &schedules));
+        }
+
+        Some(FormattedText::with_entities(text, entities))
+    }
+}
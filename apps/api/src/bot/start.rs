@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use telegram_bot::functions::FormattedText;
+use telegram_bot::{CommandContext, CommandHandler};
+
+use crate::database::Database;
+
+use super::find_sender_students;
+
+pub struct StartCommand {
+    db: Arc<Database>,
+}
+
+impl StartCommand {
+    pub fn new(db: Arc<Database>) -> Self {
+        Self { db }
+    }
+}
+
+#[async_trait::async_trait]
+impl CommandHandler for StartCommand {
+    async fn handle(&self, context: &CommandContext) -> Option<FormattedText> {
+        let students = match find_sender_students(&self.db, context).await {
+            Ok(students) => students,
+            Err(error) => return Some(error.reply()),
+        };
+
+        if students.is_empty() {
+            return Some(FormattedText::new(
+                "Welcome to Magmooty! We couldn't find any students registered with your phone number"
+                    .to_string(),
+            ));
+        }
+
+        let students: Vec<String> = students
+            .iter()
+            .map(|student| format!("- {} ({})", student.student_name, student.center_name))
+            .collect();
+
+        Some(FormattedText::new(format!(
+            "Welcome to Magmooty! Your phone number is registered for:\n{}\n\nSend /schedule, /balance or /attendance to see their schedule, fees and attendance",
+            students.join("\n")
+        )))
+    }
+}
@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use surrealdb::sql::Datetime;
+
+    use crate::bot::format_student_attendance;
+    use crate::database::AttendanceRecord;
+
+    #[test]
+    fn test_format_student_attendance() {
+        let attendance = vec![
+            AttendanceRecord {
+                grade: "Grade 10".to_string(),
+                subjects: vec!["Math".to_string()],
+                attended_at: Datetime::from_str("2026-01-10T16:00:00Z").unwrap(),
+                present: false,
+            },
+            AttendanceRecord {
+                grade: "Grade 10".to_string(),
+                subjects: vec!["Math".to_string()],
+                attended_at: Datetime::from_str("2026-01-03T16:00:00Z").unwrap(),
+                present: true,
+            },
+        ];
+
+        assert_eq!(
+            format_student_attendance("Ahmed", &attendance),
+            "Ahmed\n  2026-01-10 Grade 10 Math: Absent\n  2026-01-03 Grade 10 Math: Present"
+        );
+    }
+
+    #[test]
+    fn test_format_empty_student_attendance() {
+        assert_eq!(
+            format_student_attendance("Ahmed", &[]),
+            "Ahmed\n  No attendance recorded"
+        );
+    }
+}
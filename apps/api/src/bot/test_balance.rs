@@ -0,0 +1,42 @@
+#[cfg(test)]
+mod tests {
+    use crate::bot::{format_amount, format_student_balance};
+    use crate::database::CourseBalance;
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(1200.0), "1200");
+        assert_eq!(format_amount(99.5), "99.50");
+    }
+
+    #[test]
+    fn test_format_student_balance() {
+        let balances = vec![
+            CourseBalance {
+                grade: "Grade 10".to_string(),
+                subjects: vec!["Math".to_string(), "Physics".to_string()],
+                fees: 1200.0,
+                paid: 800.0,
+            },
+            CourseBalance {
+                grade: "Grade 10".to_string(),
+                subjects: vec!["Chemistry".to_string()],
+                fees: 0.0,
+                paid: 150.0,
+            },
+        ];
+
+        assert_eq!(
+            format_student_balance("Ahmed", &balances),
+            "Ahmed\nGrade 10 Math, Physics\n  Fees 1200, paid 800, remaining 400\nGrade 10 Chemistry\n  Fees 0, paid 150, remaining 0"
+        );
+    }
+
+    #[test]
+    fn test_format_empty_student_balance() {
+        assert_eq!(
+            format_student_balance("Ahmed", &[]),
+            "Ahmed\n  Not enrolled in any course"
+        );
+    }
+}
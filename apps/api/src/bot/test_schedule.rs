@@ -0,0 +1,40 @@
+#[cfg(test)]
+mod tests {
+    use crate::bot::format_student_schedule;
+    use crate::database::{ClassSchedule, CourseSchedule};
+
+    #[test]
+    fn test_format_student_schedule() {
+        let schedules = vec![CourseSchedule {
+            grade: "Grade 10".to_string(),
+            subjects: vec!["Math".to_string(), "Physics".to_string()],
+            schedule: vec![
+                ClassSchedule {
+                    class_order: 2,
+                    day: "Tuesday".to_string(),
+                    start: 1020,
+                    end: 1110,
+                },
+                ClassSchedule {
+                    class_order: 1,
+                    day: "Saturday".to_string(),
+                    start: 540,
+                    end: 605,
+                },
+            ],
+        }];
+
+        assert_eq!(
+            format_student_schedule("Ahmed", &schedules),
+            "Ahmed\nGrade 10 Math, Physics\n  Saturday 09:00 - 10:05\n  Tuesday 17:00 - 18:30"
+        );
+    }
+
+    #[test]
+    fn test_format_empty_student_schedule() {
+        assert_eq!(
+            format_student_schedule("Ahmed", &[]),
+            "Ahmed\n  No classes scheduled"
+        );
+    }
+}
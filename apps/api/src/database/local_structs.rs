@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use surrealdb::sql::Thing;
+use surrealdb::sql::{Datetime, Thing};
 use utoipa::ToSchema;
 
 #[derive(Serialize, Deserialize, Debug, ToSchema)]
@@ -59,6 +59,27 @@ pub struct Enrollment {
     default_group: Thing,
     academic_year: Thing,
     course: Thing,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    fees: Option<f64>,
+    space: Thing,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Attendance {
+    student: Thing,
+    enrollment: Thing,
+    group: Thing,
+    attended_at: Datetime,
+    present: bool,
+    space: Thing,
+}
+
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct Payment {
+    student: Thing,
+    enrollment: Thing,
+    amount: f64,
+    paid_at: Datetime,
     space: Thing,
 }
 
@@ -73,4 +94,6 @@ pub enum Content {
     Group(Group),
     Student(Student),
     Enrollment(Enrollment),
+    Attendance(Attendance),
+    Payment(Payment),
 }
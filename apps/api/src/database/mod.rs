@@ -5,6 +5,7 @@ use surrealdb::opt::auth::Root;
 use surrealdb::sql::Thing;
 
 mod center;
+mod parent;
 mod signin_code;
 mod sync;
 mod user;
@@ -13,12 +14,14 @@ mod schema;
 pub mod local_structs;
 
 mod test_center;
+mod test_parent;
 mod test_signin_code;
 mod test_sync;
 mod test_user;
 mod test_schema;
 
 pub use center::*;
+pub use parent::*;
 pub use signin_code::*;
 pub use sync::*;
 pub use user::*;
@@ -43,6 +46,7 @@ pub struct Database {
     pub user: UserRepository,
     pub center: CenterRepository,
     pub sync: SyncRepository,
+    pub parent: ParentRepository,
 
     endpoint: &'static str,
     credentials: Option<Root<'static>>,
@@ -55,6 +59,7 @@ impl Database {
         let user = UserRepository::new(surreal.clone());
         let center = CenterRepository::new(surreal.clone());
         let sync = SyncRepository::new(surreal.clone(), endpoint, credentials);
+        let parent = ParentRepository::new(surreal.clone(), endpoint, credentials);
 
         Self {
             surreal,
@@ -62,6 +67,7 @@ impl Database {
             user,
             center,
             sync,
+            parent,
             endpoint,
             credentials,
         }
@@ -0,0 +1,269 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use surrealdb::{
+    engine::any::Any,
+    opt::auth::Root,
+    sql::{Datetime, Thing},
+    Surreal,
+};
+use tokio::sync::Mutex;
+
+/// How long the students of a phone number are reused before the centers are searched again
+static STUDENTS_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Students of a phone number and when they were found
+type CachedStudents = (Instant, Vec<CenterStudent>);
+
+#[derive(Deserialize, Debug, Clone)]
+struct NamedRecord {
+    id: Thing,
+    name: String,
+}
+
+/// Student found in one of the synced center databases
+#[derive(Debug, Clone)]
+pub struct CenterStudent {
+    pub center_id: Thing,
+    pub center_name: String,
+    pub student_id: Thing,
+    pub student_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ClassSchedule {
+    pub class_order: i64,
+    pub day: String,
+    /// Minutes from the start of the day
+    pub start: i64,
+    /// Minutes from the start of the day
+    pub end: i64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct CourseSchedule {
+    pub grade: String,
+    pub subjects: Vec<String>,
+    pub schedule: Vec<ClassSchedule>,
+}
+
+/// Fees of an enrollment and how much of them was paid
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CourseBalance {
+    pub grade: String,
+    pub subjects: Vec<String>,
+    pub fees: f64,
+    pub paid: f64,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct AttendanceRecord {
+    pub grade: String,
+    pub subjects: Vec<String>,
+    pub attended_at: Datetime,
+    pub present: bool,
+}
+
+/// Read-only access to synced center data for parents and students
+#[derive(Clone)]
+pub struct ParentRepository {
+    db: Arc<Surreal<Any>>,
+    endpoint: &'static str,
+    credentials: Option<Root<'static>>,
+    /// Connections to center databases, opened the first time a center is queried
+    center_databases: Arc<Mutex<HashMap<String, Arc<Surreal<Any>>>>>,
+    /// Students found for a phone number, commands from the same parent reuse them
+    students: Arc<Mutex<HashMap<String, CachedStudents>>>,
+}
+
+impl ParentRepository {
+    pub fn new(
+        db: Arc<Surreal<Any>>,
+        endpoint: &'static str,
+        credentials: Option<Root<'static>>,
+    ) -> Self {
+        Self {
+            db,
+            endpoint,
+            credentials,
+            center_databases: Arc::new(Mutex::new(HashMap::new())),
+            students: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    async fn use_center_database(
+        &self,
+        center_id: &Thing,
+    ) -> Result<Arc<Surreal<Any>>, surrealdb::Error> {
+        let db: Arc<Surreal<Any>>;
+
+        #[cfg(test)]
+        {
+            db = self.db.clone();
+
+            db.use_ns("magmooty")
+                .use_db(center_id.id.to_string())
+                .await?;
+        }
+
+        #[cfg(not(test))]
+        {
+            let mut center_databases = self.center_databases.lock().await;
+
+            if let Some(center_db) = center_databases.get(&center_id.to_string()) {
+                return Ok(center_db.clone());
+            }
+
+            db = Arc::new(Surreal::init());
+
+            db.connect(self.endpoint).await?;
+
+            if let Some(credentials) = self.credentials {
+                db.signin(credentials).await?;
+            }
+
+            db.use_ns("magmooty")
+                .use_db(center_id.id.to_string())
+                .await?;
+
+            center_databases.insert(center_id.to_string(), db.clone());
+        }
+
+        Ok(db)
+    }
+
+    #[cfg(test)]
+    async fn restore_main_database(&self) {
+        self.db.use_ns("magmooty").use_db("magmooty").await.unwrap();
+    }
+
+    /// Find students whose phone numbers include `phone_number` in every synced center
+    ///
+    /// Centers are only searched again once the previous result is older than [`STUDENTS_CACHE_TTL`].
+    pub async fn find_students_by_phone_number(
+        &self,
+        phone_number: &String,
+    ) -> Result<Vec<CenterStudent>, surrealdb::Error> {
+        if let Some((found_at, students)) = self.students.lock().await.get(phone_number) {
+            if found_at.elapsed() < STUDENTS_CACHE_TTL {
+                return Ok(students.clone());
+            }
+        }
+
+        let students = self.search_students_by_phone_number(phone_number).await?;
+
+        self.students
+            .lock()
+            .await
+            .insert(phone_number.clone(), (Instant::now(), students.clone()));
+
+        Ok(students)
+    }
+
+    async fn search_students_by_phone_number(
+        &self,
+        phone_number: &String,
+    ) -> Result<Vec<CenterStudent>, surrealdb::Error> {
+        let digits: String = phone_number.chars().filter(char::is_ascii_digit).collect();
+        let phone_numbers = vec![phone_number.clone(), format!("+{}", digits), digits];
+
+        let centers: Vec<NamedRecord> = self
+            .db
+            .query("SELECT id, name FROM center")
+            .await?
+            .take(0)?;
+
+        let mut students = Vec::new();
+
+        for center in centers {
+            let db = self.use_center_database(&center.id).await?;
+
+            let center_students: Vec<NamedRecord> = db
+                .query(
+                    "SELECT id, name FROM student WHERE phone_numbers.number CONTAINSANY $phone_numbers",
+                )
+                .bind(("phone_numbers", &phone_numbers))
+                .await?
+                .take(0)?;
+
+            students.extend(center_students.into_iter().map(|student| CenterStudent {
+                center_id: center.id.clone(),
+                center_name: center.name.clone(),
+                student_id: student.id,
+                student_name: student.name,
+            }));
+        }
+
+        #[cfg(test)]
+        self.restore_main_database().await;
+
+        Ok(students)
+    }
+
+    /// Weekly schedule of the default group of every enrollment of the student
+    pub async fn find_student_schedule(
+        &self,
+        student: &CenterStudent,
+    ) -> Result<Vec<CourseSchedule>, surrealdb::Error> {
+        let db = self.use_center_database(&student.center_id).await?;
+
+        let schedules: Vec<CourseSchedule> = db
+            .query(
+                "SELECT course.grade AS grade, course.subjects AS subjects, default_group.schedule AS schedule FROM enrollment WHERE student = $student",
+            )
+            .bind(("student", &student.student_id))
+            .await?
+            .take(0)?;
+
+        #[cfg(test)]
+        self.restore_main_database().await;
+
+        Ok(schedules)
+    }
+
+    /// Fees and payments of every enrollment of the student
+    pub async fn find_student_balance(
+        &self,
+        student: &CenterStudent,
+    ) -> Result<Vec<CourseBalance>, surrealdb::Error> {
+        let db = self.use_center_database(&student.center_id).await?;
+
+        let balances: Vec<CourseBalance> = db
+            .query(
+                "SELECT course.grade AS grade, course.subjects AS subjects, fees ?? 0 AS fees, math::sum((SELECT VALUE amount FROM payment WHERE enrollment = $parent.id)) AS paid FROM enrollment WHERE student = $student",
+            )
+            .bind(("student", &student.student_id))
+            .await?
+            .take(0)?;
+
+        #[cfg(test)]
+        self.restore_main_database().await;
+
+        Ok(balances)
+    }
+
+    /// Latest `limit` classes the student was expected to attend, newest first
+    pub async fn find_student_attendance(
+        &self,
+        student: &CenterStudent,
+        limit: i64,
+    ) -> Result<Vec<AttendanceRecord>, surrealdb::Error> {
+        let db = self.use_center_database(&student.center_id).await?;
+
+        let attendance: Vec<AttendanceRecord> = db
+            .query(
+                "SELECT enrollment.course.grade AS grade, enrollment.course.subjects AS subjects, attended_at, present FROM attendance WHERE student = $student ORDER BY attended_at DESC LIMIT $limit",
+            )
+            .bind(("student", &student.student_id))
+            .bind(("limit", limit))
+            .await?
+            .take(0)?;
+
+        #[cfg(test)]
+        self.restore_main_database().await;
+
+        Ok(attendance)
+    }
+}
@@ -79,7 +79,33 @@ pub static LOCAL_SCHEMA: &str = "
 			DEFINE FIELD default_group ON TABLE enrollment TYPE record<group>;
 			DEFINE FIELD academic_year ON TABLE enrollment TYPE record<academic_year>;
 			DEFINE FIELD course ON TABLE enrollment TYPE record<academic_year_course>;
+			DEFINE FIELD fees ON TABLE enrollment TYPE option<number>;
 			DEFINE FIELD space ON TABLE enrollment TYPE record<space>;
 
 			DEFINE INDEX enrollment_student_name_index ON enrollment FIELDS _name SEARCH ANALYZER name_analyzer BM25;
+
+			DEFINE TABLE attendance SCHEMAFULL
+				PERMISSIONS
+					FOR SELECT WHERE (SELECT * FROM scope WHERE user = $auth.id AND scope_name = 'manage_center') OR space IN (SELECT space FROM scope WHERE user = $auth.id GROUP BY space).space,
+					FOR CREATE, UPDATE, DELETE WHERE (SELECT * FROM scope WHERE user = $auth.id AND scope_name = 'manage_center') OR space IN (SELECT space FROM scope WHERE user = $auth.id AND scope_name = 'manage_space' GROUP BY space).space OR space IN (SELECT space FROM scope WHERE user = $auth.id AND scope_name = 'manage_students' GROUP BY space);
+			DEFINE FIELD student ON TABLE attendance TYPE record<student>;
+			DEFINE FIELD enrollment ON TABLE attendance TYPE record<enrollment>;
+			DEFINE FIELD group ON TABLE attendance TYPE record<group>;
+			DEFINE FIELD attended_at ON TABLE attendance TYPE datetime;
+			DEFINE FIELD present ON TABLE attendance TYPE bool;
+			DEFINE FIELD space ON TABLE attendance TYPE record<space>;
+
+			DEFINE TABLE payment SCHEMAFULL
+				PERMISSIONS
+					FOR SELECT WHERE (SELECT * FROM scope WHERE user = $auth.id AND scope_name = 'manage_center') OR space IN (SELECT space FROM scope WHERE user = $auth.id GROUP BY space).space,
+					FOR CREATE, UPDATE, DELETE WHERE (SELECT * FROM scope WHERE user = $auth.id AND scope_name = 'manage_center') OR space IN (SELECT space FROM scope WHERE user = $auth.id AND scope_name = 'manage_space' GROUP BY space).space OR space IN (SELECT space FROM scope WHERE user = $auth.id AND scope_name = 'manage_students' GROUP BY space);
+			DEFINE FIELD student ON TABLE payment TYPE record<student>;
+			DEFINE FIELD enrollment ON TABLE payment TYPE record<enrollment>;
+			DEFINE FIELD amount ON TABLE payment TYPE number;
+			DEFINE FIELD paid_at ON TABLE payment TYPE datetime;
+			DEFINE FIELD space ON TABLE payment TYPE record<space>;
+
+			DEFINE TABLE sync_log SCHEMALESS;
+			DEFINE INDEX sync_log_sequence_index ON sync_log FIELDS sequence UNIQUE;
+			DEFINE TABLE sync_counter SCHEMALESS;
 		";
\ No newline at end of file
@@ -20,6 +20,20 @@ pub struct SyncEvent {
     pub created_at: Datetime,
 }
 
+/// Sync event applied to a center database, numbered in the order it was applied
+#[derive(Serialize, Deserialize, Debug, ToSchema)]
+pub struct LoggedSyncEvent {
+    pub sequence: i64,
+    pub record_id: Thing,
+    pub event: String,
+    #[schema(value_type = Object)]
+    pub content: serde_json::Value,
+    pub created_at: Datetime,
+    /// Installation that uploaded the change, `None` for changes logged before it was recorded
+    #[serde(default)]
+    pub origin: Option<String>,
+}
+
 #[derive(Serialize, Debug, ToSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum InsertSyncEventsError {
@@ -30,8 +44,26 @@ pub enum InsertSyncEventsError {
     UpdateError,
     DeletionError,
     UnknownEvent,
+    LogError,
 }
 
+#[derive(Serialize, Debug, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PullSyncEventsError {
+    InvalidCenterID,
+    DatabaseConnectionError,
+    QueryError,
+}
+
+/// Log a change under the next sequence of the center, the counter starts after the highest
+/// sequence logged before it existed
+static SYNC_LOG_QUERY: &str = "
+    BEGIN TRANSACTION;
+    LET $sequence = (UPDATE ONLY sync_counter:log SET sequence = (sequence ?? math::max((SELECT VALUE sequence FROM sync_log)) ?? 0) + 1).sequence;
+    CREATE sync_log CONTENT { sequence: $sequence, record_id: $record_id, event: $event, content: $content, created_at: $created_at, origin: $origin };
+    COMMIT TRANSACTION;
+";
+
 #[derive(Clone)]
 pub struct SyncRepository {
     db: Arc<Surreal<Any>>,
@@ -52,47 +84,48 @@ impl SyncRepository {
         }
     }
 
-    pub async fn insert_sync_events(
+    async fn use_center_database(
         &self,
-        center_id: &String,
-        events: Vec<SyncEvent>,
-    ) -> Result<(), InsertSyncEventsError> {
-        let center_id =
-            Thing::from_str(center_id).map_err(|_| InsertSyncEventsError::InvalidCenterID)?;
-
+        center_id: &Thing,
+    ) -> Result<Arc<Surreal<Any>>, surrealdb::Error> {
         let db: Arc<Surreal<Any>>;
 
         #[cfg(test)]
         {
             db = self.db.clone();
-
-            db.use_ns("magmooty")
-                .use_db(center_id.id.to_string())
-                .await
-                .map_err(|_| InsertSyncEventsError::DatabaseConnectionError)?;
         }
 
         #[cfg(not(test))]
         {
             db = Arc::new(Surreal::init());
 
-            db.connect(self.endpoint)
-                .await
-                .map_err(|_| InsertSyncEventsError::DatabaseConnectionError)?;
+            db.connect(self.endpoint).await?;
 
-            db.use_ns("magmooty")
-                .use_db(center_id.id.to_string())
-                .await
-                .map_err(|_| InsertSyncEventsError::DatabaseConnectionError)?;
+            if let Some(credentials) = self.credentials {
+                db.signin(credentials).await?;
+            }
+        }
 
-            let credentials = self
-                .credentials
-                .ok_or_else(|| InsertSyncEventsError::DatabaseConnectionError)?;
+        db.use_ns("magmooty")
+            .use_db(center_id.id.to_string())
+            .await?;
 
-            db.signin(credentials)
-                .await
-                .map_err(|_| InsertSyncEventsError::DatabaseConnectionError)?;
-        }
+        Ok(db)
+    }
+
+    pub async fn insert_sync_events(
+        &self,
+        center_id: &String,
+        events: Vec<SyncEvent>,
+        origin: Option<&String>,
+    ) -> Result<(), InsertSyncEventsError> {
+        let center_id =
+            Thing::from_str(center_id).map_err(|_| InsertSyncEventsError::InvalidCenterID)?;
+
+        let db = self
+            .use_center_database(&center_id)
+            .await
+            .map_err(|_| InsertSyncEventsError::DatabaseConnectionError)?;
 
         db.query(LOCAL_SCHEMA)
             .await
@@ -102,8 +135,8 @@ impl SyncRepository {
             match event.event.as_str() {
                 "CREATE" => {
                     let _: Record = db
-                        .update(event.record_id)
-                        .content(event.content)
+                        .update(event.record_id.clone())
+                        .content(&event.content)
                         .await
                         .map_err(|err| {
                             InsertSyncEventsError::InsertionError(Some(err.to_string()))
@@ -112,23 +145,59 @@ impl SyncRepository {
                 }
                 "UPDATE" => {
                     let _: Record = db
-                        .update(event.record_id)
-                        .content(event.content)
+                        .update(event.record_id.clone())
+                        .content(&event.content)
                         .await
                         .map_err(|_| InsertSyncEventsError::UpdateError)?
                         .ok_or_else(|| InsertSyncEventsError::UpdateError)?;
                 }
                 "DELETE" => {
                     let _: Record = db
-                        .delete(event.record_id)
+                        .delete(event.record_id.clone())
                         .await
                         .map_err(|_| InsertSyncEventsError::DeletionError)?
                         .ok_or_else(|| InsertSyncEventsError::DeletionError)?;
                 }
                 _ => Err(InsertSyncEventsError::UnknownEvent)?,
             }
+
+            // Keep a numbered log so other installations of the center can pull the change, the
+            // counter is taken in the same transaction so concurrent uploads never share a sequence
+            // and a sequence is never visible before its log entry
+            db.query(SYNC_LOG_QUERY)
+                .bind(("record_id", &event.record_id))
+                .bind(("event", &event.event))
+                .bind(("content", &event.content))
+                .bind(("created_at", &event.created_at))
+                .bind(("origin", origin))
+                .await
+                .and_then(|response| response.check())
+                .map_err(|_| InsertSyncEventsError::LogError)?;
         }
 
         Ok(())
     }
+
+    /// Sync events applied to the center after the `after` sequence, oldest first
+    pub async fn pull_sync_events(
+        &self,
+        center_id: &String,
+        after: i64,
+        limit: i64,
+    ) -> Result<Vec<LoggedSyncEvent>, PullSyncEventsError> {
+        let center_id =
+            Thing::from_str(center_id).map_err(|_| PullSyncEventsError::InvalidCenterID)?;
+
+        let db = self
+            .use_center_database(&center_id)
+            .await
+            .map_err(|_| PullSyncEventsError::DatabaseConnectionError)?;
+
+        db.query("SELECT sequence, record_id, event, content, created_at, origin FROM sync_log WHERE sequence > $after ORDER BY sequence LIMIT $limit")
+            .bind(("after", after))
+            .bind(("limit", limit))
+            .await
+            .and_then(|mut response| response.take::<Vec<LoggedSyncEvent>>(0))
+            .map_err(|_| PullSyncEventsError::QueryError)
+    }
 }
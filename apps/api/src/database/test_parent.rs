@@ -0,0 +1,200 @@
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use surrealdb::sql::Datetime;
+
+    use crate::database::{
+        AttendanceRecord, CenterStudent, ClassSchedule, CourseBalance, Database,
+    };
+
+    async fn setup() -> Database {
+        let db = Database::in_memory().await;
+
+        db.surreal
+            .query(
+                "
+                CREATE center:center1 CONTENT { name: 'Center 1' };
+                CREATE center:center2 CONTENT { name: 'Center 2' };
+                ",
+            )
+            .await
+            .unwrap();
+
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("center1")
+            .await
+            .unwrap();
+
+        db.surreal
+            .query(
+                "
+                CREATE student:ahmed CONTENT { name: 'Ahmed', _name: 'ahmed', phone_numbers: [{ number: '+201096707442', use: 'parent' }] };
+                CREATE student:omar CONTENT { name: 'Omar', _name: 'omar', phone_numbers: [{ number: '+201000000000', use: 'parent' }] };
+                CREATE academic_year_course:course1 CONTENT { grade: 'Grade 10', subjects: ['Math'] };
+                CREATE group:group1 CONTENT { schedule: [{ class_order: 1, day: 'Saturday', start: 960, end: 1050 }] };
+                CREATE enrollment:enrollment1 CONTENT { student: student:ahmed, course: academic_year_course:course1, default_group: group:group1, fees: 1200 };
+                CREATE academic_year_course:course2 CONTENT { grade: 'Grade 10', subjects: ['Physics'] };
+                CREATE enrollment:enrollment2 CONTENT { student: student:ahmed, course: academic_year_course:course2, default_group: group:group1 };
+                CREATE payment:payment1 CONTENT { student: student:ahmed, enrollment: enrollment:enrollment1, amount: 500, paid_at: d'2026-01-01T10:00:00Z' };
+                CREATE payment:payment2 CONTENT { student: student:ahmed, enrollment: enrollment:enrollment1, amount: 300, paid_at: d'2026-02-01T10:00:00Z' };
+                CREATE attendance:attendance1 CONTENT { student: student:ahmed, enrollment: enrollment:enrollment1, group: group:group1, attended_at: d'2026-01-03T16:00:00Z', present: true };
+                CREATE attendance:attendance2 CONTENT { student: student:ahmed, enrollment: enrollment:enrollment1, group: group:group1, attended_at: d'2026-01-10T16:00:00Z', present: false };
+                CREATE attendance:attendance3 CONTENT { student: student:ahmed, enrollment: enrollment:enrollment1, group: group:group1, attended_at: d'2026-01-17T16:00:00Z', present: true };
+                ",
+            )
+            .await
+            .unwrap();
+
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("center2")
+            .await
+            .unwrap();
+
+        db.surreal
+            .query(
+                "CREATE student:mona CONTENT { name: 'Mona', _name: 'mona', phone_numbers: [{ number: '+201096707442', use: 'parent' }] };",
+            )
+            .await
+            .unwrap();
+
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("magmooty")
+            .await
+            .unwrap();
+
+        db
+    }
+
+    #[tokio::test]
+    async fn test_find_students_by_phone_number() {
+        let db = setup().await;
+
+        // Telegram reports phone numbers without the leading plus sign
+        let mut students = db
+            .parent
+            .find_students_by_phone_number(&"201096707442".to_string())
+            .await
+            .unwrap();
+
+        students.sort_by(|a, b| a.student_name.cmp(&b.student_name));
+
+        assert_eq!(students.len(), 2);
+        assert_eq!(students[0].student_name, "Ahmed");
+        assert_eq!(students[0].center_name, "Center 1");
+        assert_eq!(students[1].student_name, "Mona");
+        assert_eq!(students[1].center_name, "Center 2");
+    }
+
+    #[tokio::test]
+    async fn test_find_student_schedule() {
+        let db = setup().await;
+
+        let students = db
+            .parent
+            .find_students_by_phone_number(&"+201096707442".to_string())
+            .await
+            .unwrap();
+
+        let ahmed = students
+            .iter()
+            .find(|student| student.student_name == "Ahmed")
+            .unwrap();
+
+        let schedules = db.parent.find_student_schedule(ahmed).await.unwrap();
+
+        assert_eq!(schedules.len(), 2);
+        let schedules: Vec<_> = schedules
+            .into_iter()
+            .filter(|schedule| schedule.subjects == vec!["Math".to_string()])
+            .collect();
+
+        assert_eq!(schedules[0].grade, "Grade 10");
+        assert_eq!(
+            schedules[0].schedule,
+            vec![ClassSchedule {
+                class_order: 1,
+                day: "Saturday".to_string(),
+                start: 960,
+                end: 1050,
+            }]
+        );
+    }
+
+    async fn find_ahmed(db: &Database) -> CenterStudent {
+        db.parent
+            .find_students_by_phone_number(&"+201096707442".to_string())
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|student| student.student_name == "Ahmed")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_find_student_balance() {
+        let db = setup().await;
+        let ahmed = find_ahmed(&db).await;
+
+        let mut balances = db.parent.find_student_balance(&ahmed).await.unwrap();
+        balances.sort_by(|a, b| a.subjects.cmp(&b.subjects));
+
+        // Enrollments without fees count as free
+        assert_eq!(
+            balances,
+            vec![
+                CourseBalance {
+                    grade: "Grade 10".to_string(),
+                    subjects: vec!["Math".to_string()],
+                    fees: 1200.0,
+                    paid: 800.0,
+                },
+                CourseBalance {
+                    grade: "Grade 10".to_string(),
+                    subjects: vec!["Physics".to_string()],
+                    fees: 0.0,
+                    paid: 0.0,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_find_student_attendance() {
+        let db = setup().await;
+        let ahmed = find_ahmed(&db).await;
+
+        let attendance = db.parent.find_student_attendance(&ahmed, 2).await.unwrap();
+
+        assert_eq!(
+            attendance,
+            vec![
+                AttendanceRecord {
+                    grade: "Grade 10".to_string(),
+                    subjects: vec!["Math".to_string()],
+                    attended_at: Datetime::from_str("2026-01-17T16:00:00Z").unwrap(),
+                    present: true,
+                },
+                AttendanceRecord {
+                    grade: "Grade 10".to_string(),
+                    subjects: vec!["Math".to_string()],
+                    attended_at: Datetime::from_str("2026-01-10T16:00:00Z").unwrap(),
+                    present: false,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_students_are_cached() {
+        let db = setup().await;
+        let ahmed = find_ahmed(&db).await;
+
+        db.surreal.query("REMOVE NAMESPACE magmooty").await.unwrap();
+
+        assert_eq!(find_ahmed(&db).await.student_id, ahmed.student_id);
+    }
+}
@@ -29,7 +29,7 @@ mod tests {
             created_at: Datetime::default(),
         }];
 
-        let result = db.sync.insert_sync_events(&center_id, events).await;
+        let result = db.sync.insert_sync_events(&center_id, events, None).await;
 
         assert_eq!(result.is_ok(), true);
 
@@ -58,7 +58,7 @@ mod tests {
             created_at: Datetime::default(),
         }];
 
-        let result = db.sync.insert_sync_events(&center_id, events).await;
+        let result = db.sync.insert_sync_events(&center_id, events, None).await;
 
         assert_eq!(result.is_ok(), true);
 
@@ -87,7 +87,7 @@ mod tests {
             created_at: Datetime::default(),
         }];
 
-        let result = db.sync.insert_sync_events(&center_id, events).await;
+        let result = db.sync.insert_sync_events(&center_id, events, None).await;
 
         assert_eq!(result.is_ok(), true);
 
@@ -131,7 +131,7 @@ mod tests {
             },
         ];
 
-        let result = db.sync.insert_sync_events(&center_id, events).await;
+        let result = db.sync.insert_sync_events(&center_id, events, None).await;
 
         assert_eq!(result.is_ok(), true);
 
@@ -166,7 +166,7 @@ mod tests {
             created_at: Datetime::default(),
         }];
 
-        let result = db.sync.insert_sync_events(&center_id, events).await;
+        let result = db.sync.insert_sync_events(&center_id, events, None).await;
 
         assert_eq!(result.is_ok(), true);
 
@@ -183,4 +183,94 @@ mod tests {
         assert_eq!(stored_users[0].phone_number, "phone_number");
         assert_eq!(stored_users[0].password, "password");
     }
+
+    fn create_user_event(id: &str) -> SyncEvent {
+        SyncEvent {
+            record_id: Thing::from(("user", id)),
+            event: "CREATE".to_string(),
+            content: Content::User(User {
+                name: id.to_string(),
+                phone_number: id.to_string(),
+                password: "password".to_string(),
+            }),
+            created_at: Datetime::default(),
+        }
+    }
+
+    async fn logged_sequences(db: &Database) -> Vec<i64> {
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("center1")
+            .await
+            .unwrap();
+
+        let sequences: Vec<i64> = db
+            .surreal
+            .query("SELECT VALUE sequence FROM sync_log ORDER BY sequence")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("magmooty")
+            .await
+            .unwrap();
+
+        sequences
+    }
+
+    #[tokio::test]
+    async fn test_sync_log_sequences() {
+        let db = setup().await;
+
+        let center_id = "center:center1".to_string();
+
+        db.sync
+            .insert_sync_events(
+                &center_id,
+                vec![create_user_event("id1"), create_user_event("id2")],
+                None,
+            )
+            .await
+            .unwrap();
+
+        db.sync
+            .insert_sync_events(&center_id, vec![create_user_event("id3")], None)
+            .await
+            .unwrap();
+
+        assert_eq!(logged_sequences(&db).await, vec![1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_sync_log_sequences_continue_existing_log() {
+        let db = setup().await;
+
+        let center_id = "center:center1".to_string();
+
+        // Centers logged changes before the counter existed
+        db.surreal
+            .use_ns("magmooty")
+            .use_db("center1")
+            .await
+            .unwrap();
+
+        db.surreal
+            .query("CREATE sync_log CONTENT { sequence: 41 }")
+            .await
+            .unwrap();
+
+        db.sync
+            .insert_sync_events(
+                &center_id,
+                vec![create_user_event("id1"), create_user_event("id2")],
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(logged_sequences(&db).await, vec![41, 42, 43]);
+    }
 }
@@ -10,11 +10,14 @@ use settings::AppSettings;
 use std::any::Any;
 use std::sync::Arc;
 use surrealdb::opt::auth::Root;
-use telegram_bot::{self, ConsoleAuthorizationHandler, ConsoleConnectionHandler, TelegramClient};
+use telegram_bot::{
+    self, ConsoleAuthorizationHandler, ConsoleConnectionHandler, TelegramClient, TelegramConfig,
+};
 use tower::ServiceBuilder;
 use tower_http::catch_panic::CatchPanicLayer;
 
 pub mod app;
+pub mod bot;
 pub mod database;
 pub mod settings;
 pub mod validation;
@@ -60,8 +63,28 @@ async fn main() {
     whatsapp::WhatsAppBot::initialize_whatsapp();
 
     debug!(target: LOG_TARGET, "Initializing Telegram");
-    let telegram_client =
-        TelegramClient::init::<ConsoleAuthorizationHandler, ConsoleConnectionHandler>().await;
+    let mut telegram_config = TelegramConfig::new(
+        APP_SETTINGS.telegram_api_id,
+        APP_SETTINGS.telegram_api_hash.clone(),
+    )
+    .use_test_dc(APP_SETTINGS.telegram_use_test_dc.unwrap_or(false))
+    .application_version(env!("CARGO_PKG_VERSION").to_string());
+
+    if let Some(data_directory) = &APP_SETTINGS.telegram_data_directory {
+        telegram_config = telegram_config.data_directory(data_directory);
+    }
+
+    let telegram_client = TelegramClient::init::<
+        ConsoleAuthorizationHandler,
+        ConsoleConnectionHandler,
+    >(telegram_config)
+    .await;
+
+    if let Some(phone_numbers) = &APP_SETTINGS.telegram_alert_phone_numbers {
+        telegram_client
+            .set_alert_handler(bot::WhatsAppAlertHandler::from_setting(phone_numbers))
+            .await;
+    }
 
     debug!(target: LOG_TARGET, "Connecting to the database");
     let database = Arc::new(Database::new(
@@ -76,6 +99,11 @@ async fn main() {
     debug!(target: LOG_TARGET, "Defining database schema");
     database.define_database().await;
 
+    debug!(target: LOG_TARGET, "Registering Telegram bot commands");
+    telegram_client
+        .set_command_router(bot::create_command_router(database.clone()))
+        .await;
+
     debug!(target: LOG_TARGET, "Building panic catcher");
     let svc = ServiceBuilder::new()
         // Use `handle_panic` to create the response.
@@ -8,8 +8,12 @@ pub struct AppSettings {
     pub surrealdb_root_password: String,
     pub port: i32,
     pub admin_public_key: String,
-    pub telegram_api_id: String,
+    pub telegram_api_id: i32,
     pub telegram_api_hash: String,
+    pub telegram_data_directory: Option<String>,
+    pub telegram_use_test_dc: Option<bool>,
+    /// Comma separated WhatsApp numbers alerted when Telegram has to be logged in again
+    pub telegram_alert_phone_numbers: Option<String>,
 }
 
 pub fn extract_settings() -> AppSettings {
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use log::info;
+use tauri::Window;
+
+use super::GlobalKeyStore;
+use crate::backup::{self, BackupError, BackupInfo, BackupSettings};
+
+static LOG_TARGET: &str = "Backup";
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_backup_settings() -> Result<BackupSettings, BackupError> {
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|_| BackupError::StorageError)?;
+
+    Ok(backup::backup_settings(&store).await)
+}
+
+/// `password` is only needed to enable scheduled backups the first time or to change it
+#[tauri::command]
+#[specta::specta]
+pub async fn set_backup_settings(
+    settings: BackupSettings,
+    password: Option<String>,
+) -> Result<BackupSettings, BackupError> {
+    backup::set_backup_settings(settings, password).await
+}
+
+/// Back up the local database to `path`, or to the backup directory when `None`
+#[tauri::command]
+#[specta::specta]
+pub async fn create_backup(
+    path: Option<String>,
+    password: Option<String>,
+) -> Result<BackupInfo, BackupError> {
+    info!(target: LOG_TARGET, "Backup requested from the UI");
+    backup::create_backup(path.map(PathBuf::from), password, false).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn list_backups() -> Result<Vec<BackupInfo>, BackupError> {
+    backup::list_backups().await
+}
+
+/// Replace the local database with a backup of the same center, emits `backup_restored`
+#[tauri::command]
+#[specta::specta]
+pub async fn restore_backup(
+    window: Window,
+    path: String,
+    password: Option<String>,
+) -> Result<BackupInfo, BackupError> {
+    info!(target: LOG_TARGET, "Restore of {} requested from the UI", path);
+    let backup = backup::restore_backup(PathBuf::from(path), password).await?;
+
+    window.emit("backup_restored", &backup).unwrap_or_default();
+
+    Ok(backup)
+}
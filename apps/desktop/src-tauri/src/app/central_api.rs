@@ -0,0 +1,56 @@
+use log::{debug, info};
+
+use super::{GlobalKey, GlobalKeyStore};
+use crate::central::{
+    validate_central_api_url, CentralAPI, CentralApiConfigError, MessagingChannel,
+    SendSigninCodeError,
+};
+
+static LOG_TARGET: &str = "Central API settings";
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_central_api_url() -> String {
+    debug!(target: LOG_TARGET, "Fetching central API URL");
+    crate::central::get_central_api_url().await
+}
+
+/// Point the app at another central API (e.g. staging or a local mock), `None` restores the default
+#[tauri::command]
+#[specta::specta]
+pub async fn set_central_api_url(url: Option<String>) -> Result<String, CentralApiConfigError> {
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|_| CentralApiConfigError::StorageError)?;
+
+    info!(target: LOG_TARGET, "Setting central API URL to {:?}", url);
+    match url {
+        Some(url) => {
+            store
+                .set(GlobalKey::CentralApiUrl, validate_central_api_url(&url)?)
+                .await
+        }
+        None => store.remove(GlobalKey::CentralApiUrl).await,
+    }
+    .map_err(|_| CentralApiConfigError::StorageError)?;
+
+    // Don't wait for the change subscriber, the returned URL has to be the one in use
+    CentralAPI::reset_shared().await;
+
+    Ok(crate::central::get_central_api_url().await)
+}
+
+/// Request a sign in code from the configured central API
+#[tauri::command]
+#[specta::specta]
+pub async fn send_signin_code(
+    phone_number: String,
+    channel: MessagingChannel,
+) -> Result<(), SendSigninCodeError> {
+    info!(target: LOG_TARGET, "Requesting signin code for {}", phone_number);
+    let central = CentralAPI::shared()
+        .await
+        .map_err(|_| SendSigninCodeError::InvalidSettings)?;
+
+    central.send_signin_code(&phone_number, channel).await
+}
@@ -1,17 +1,194 @@
+use log::{error, info, warn};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use specta::Type;
+use surrealdb::engine::any::Any;
+use surrealdb::opt::auth::Root;
+use surrealdb::Surreal;
 
-#[derive(Debug, Serialize, Deserialize, Type)]
+use super::{GlobalKey, GlobalKeyError, GlobalKeyStore};
+use crate::ports::service_ports;
+
+static LOG_TARGET: &str = "Database auth";
+
+/// Root user every installation shared before credentials were generated per installation
+static LEGACY_ROOT_USERNAME: &str = "magmooty";
+static LEGACY_ROOT_PASSWORD: &str = "magmooty";
+
+static PASSWORD_LEN: usize = 32;
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
 pub struct RootDatabaseCredentials {
     pub username: String,
     pub password: String,
 }
 
-#[tauri::command]
-#[specta::specta]
-pub fn get_root_database_credentials() -> RootDatabaseCredentials {
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseAuthError {
+    StorageError,
+    ConnectionError,
+    /// The stored root credentials are not accepted by the database
+    SigninError,
+    QueryError,
+}
+
+impl From<GlobalKeyError> for DatabaseAuthError {
+    fn from(_: GlobalKeyError) -> Self {
+        DatabaseAuthError::StorageError
+    }
+}
+
+/// Usernames and passwords are alphanumeric so they can be inlined in `DEFINE USER`
+fn generate_credentials() -> RootDatabaseCredentials {
     RootDatabaseCredentials {
-        username: "magmooty".to_string(),
-        password: "magmooty".to_string(),
+        username: format!(
+            "root_{}",
+            Alphanumeric.sample_string(&mut OsRng, 8).to_lowercase()
+        ),
+        password: Alphanumeric.sample_string(&mut OsRng, PASSWORD_LEN),
+    }
+}
+
+async fn store_credentials(
+    store: &GlobalKeyStore,
+    credentials: &RootDatabaseCredentials,
+) -> Result<(), GlobalKeyError> {
+    // Stored as one value so the username and password never get out of step
+    let credentials =
+        serde_json::to_string(credentials).map_err(|_| GlobalKeyError::InvalidValue)?;
+
+    store
+        .set(GlobalKey::RootDatabaseCredentials, credentials)
+        .await
+}
+
+/// Root credentials of the local database, generated on first run
+pub async fn root_database_credentials() -> Result<RootDatabaseCredentials, GlobalKeyError> {
+    let store = GlobalKeyStore::shared().await?;
+
+    if let Some(credentials) = store.get(GlobalKey::RootDatabaseCredentials).await? {
+        return serde_json::from_str(&credentials).map_err(|_| GlobalKeyError::InvalidValue);
+    }
+
+    info!(target: LOG_TARGET, "Generating root database credentials");
+    let credentials = generate_credentials();
+    store_credentials(&store, &credentials).await?;
+
+    Ok(credentials)
+}
+
+async fn connect(credentials: &RootDatabaseCredentials) -> Result<Surreal<Any>, DatabaseAuthError> {
+    let surreal: Surreal<Any> = Surreal::init();
+
+    surreal
+        .connect(format!("ws://127.0.0.1:{}/rpc", service_ports().database))
+        .await
+        .map_err(|_| DatabaseAuthError::ConnectionError)?;
+
+    surreal
+        .signin(Root {
+            username: &credentials.username,
+            password: &credentials.password,
+        })
+        .await
+        .map_err(|_| DatabaseAuthError::SigninError)?;
+
+    surreal
+        .use_ns("local")
+        .use_db("local")
+        .await
+        .map_err(|_| DatabaseAuthError::ConnectionError)?;
+
+    Ok(surreal)
+}
+
+/// Connection to the local database signed in as root
+pub async fn connect_as_root() -> Result<Surreal<Any>, DatabaseAuthError> {
+    connect(&root_database_credentials().await?).await
+}
+
+/// Define `credentials` as a root user through a connection signed in as another root user
+async fn define_root_user(
+    surreal: &Surreal<Any>,
+    credentials: &RootDatabaseCredentials,
+) -> Result<(), DatabaseAuthError> {
+    surreal
+        .query(format!(
+            "DEFINE USER {} ON ROOT PASSWORD '{}' ROLES OWNER",
+            credentials.username, credentials.password
+        ))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|err| {
+            error!(target: LOG_TARGET, "Failed to define root user {}: {:?}", credentials.username, err);
+            DatabaseAuthError::QueryError
+        })?;
+
+    Ok(())
+}
+
+async fn remove_root_user(surreal: &Surreal<Any>, username: &str) {
+    let result = surreal
+        .query(format!("REMOVE USER IF EXISTS {} ON ROOT", username))
+        .await
+        .and_then(|response| response.check());
+
+    if let Err(err) = result {
+        warn!(target: LOG_TARGET, "Failed to remove root user {}: {:?}", username, err);
     }
 }
+
+/// Replace the shared root user of databases created by older versions with the generated one
+pub async fn migrate_legacy_root_user() -> Result<(), DatabaseAuthError> {
+    let credentials = root_database_credentials().await?;
+
+    match connect(&credentials).await {
+        Ok(_) => return Ok(()),
+        Err(DatabaseAuthError::SigninError) => {}
+        Err(error) => return Err(error),
+    }
+
+    info!(target: LOG_TARGET, "Replacing the legacy root user");
+    let surreal = connect(&RootDatabaseCredentials {
+        username: LEGACY_ROOT_USERNAME.to_string(),
+        password: LEGACY_ROOT_PASSWORD.to_string(),
+    })
+    .await?;
+
+    define_root_user(&surreal, &credentials).await?;
+
+    // Make sure the new user works before locking the legacy one out
+    connect(&credentials).await?;
+    remove_root_user(&surreal, LEGACY_ROOT_USERNAME).await;
+
+    Ok(())
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_root_database_credentials() -> Result<RootDatabaseCredentials, GlobalKeyError> {
+    root_database_credentials().await
+}
+
+/// Replace the root user with a newly generated one, sessions already signed in stay valid
+#[tauri::command]
+#[specta::specta]
+pub async fn rotate_root_database_credentials() -> Result<RootDatabaseCredentials, DatabaseAuthError>
+{
+    let store = GlobalKeyStore::shared().await?;
+    let current = root_database_credentials().await?;
+    let surreal = connect(&current).await?;
+
+    info!(target: LOG_TARGET, "Rotating root database credentials");
+    let credentials = generate_credentials();
+    define_root_user(&surreal, &credentials).await?;
+    connect(&credentials).await?;
+
+    // Stored before removing the old user so a crash in between never locks the app out
+    store_credentials(&store, &credentials).await?;
+    remove_root_user(&surreal, &current.username).await;
+
+    Ok(credentials)
+}
@@ -1,13 +1,27 @@
-use log::{debug, info};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use log::{debug, error, info, warn};
+use rand::distributions::{Alphanumeric, DistString};
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use sqlx::{migrate::MigrateDatabase, Row, Sqlite, SqlitePool};
+use tauri::State;
+use tokio::sync::{broadcast, OnceCell, RwLock};
+
+use super::global_keys_encryption::{is_encrypted, GlobalKeyCipher, GlobalKeyEncryptionError};
+use super::is_key_pair;
+use crate::network_discovery::InstanceType;
 
 static DB_URL: &str = "sqlite://global_keys.db";
 
 static LOG_TARGET: &str = "Global keys";
 
-#[derive(Debug, Serialize, Deserialize, Type)]
+/// Store shared by commands (through Tauri state) and background tasks
+static GLOBAL_KEY_STORE: OnceCell<Arc<GlobalKeyStore>> = OnceCell::const_new();
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum GlobalKey {
     CenterId,
@@ -15,70 +29,403 @@ pub enum GlobalKey {
     InstanceType,
     PrivateKey,
     PublicKey,
+    /// Sequence of the last change pulled from central
+    SyncCursor,
+    /// Random id central records with the changes this installation uploads
+    InstallationId,
+    /// Overrides the central API URL the app was built with
+    CentralApiUrl,
+    /// PEM certificate pinned for central API requests
+    CentralApiCertificate,
+    /// LAN address of the master a slave is paired with
+    MasterAddress,
+    /// Database user the master issued to a paired slave
+    DatabaseUsername,
+    DatabasePassword,
+    /// Certificate the master issued to a paired slave, signed with the center private key
+    PairingCertificate,
+    /// JSON of the generated root user of the local database
+    RootDatabaseCredentials,
+    /// JSON of the position of a slave in the master `sync` table
+    ReplicationCursor,
+    /// JSON of the ports the local services were started on
+    ServicePorts,
+    /// Port of the TLS endpoint of the database of the master a slave is paired with
+    MasterReplicationPort,
+    /// JSON of the schedule and retention of local database backups
+    BackupSettings,
+    /// Password scheduled backups are encrypted with
+    BackupPassword,
+}
+
+impl GlobalKey {
+    /// Keys encrypted at rest
+    const SENSITIVE: [GlobalKey; 4] = [
+        GlobalKey::PrivateKey,
+        GlobalKey::DatabasePassword,
+        GlobalKey::RootDatabaseCredentials,
+        GlobalKey::BackupPassword,
+    ];
+
+    pub fn is_sensitive(&self) -> bool {
+        Self::SENSITIVE.contains(self)
+    }
+
+    /// Keys the UI sets through `set_global_key`, the others are set by the commands owning them
+    const UI_OWNED: [GlobalKey; 1] = [GlobalKey::CenterName];
+
+    pub fn is_ui_owned(&self) -> bool {
+        Self::UI_OWNED.contains(self)
+    }
+
+    /// Name of the key in global_keys.db
+    fn column_key(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum GlobalKeyError {
+    DatabaseError,
+    EncryptionError,
+    DecryptionError,
+    /// The stored value can't be parsed as the type of the key
+    InvalidValue,
+    /// Sensitive keys are only read by the backend
+    SensitiveKey,
+    /// The key is set by the backend, not through `set_global_key`
+    BackendOwnedKey,
+}
+
+/// Sent to subscribers after a key is set or removed
+#[derive(Debug, Clone)]
+pub struct GlobalKeyChange {
+    pub key: GlobalKey,
+    pub value: Option<String>,
+}
+
+/// Whether global_keys.db holds values encrypted with the keystore secret, false before the
+/// table exists
+pub(crate) async fn has_encrypted_values(pool: &SqlitePool) -> bool {
+    sqlx::query_scalar::<_, Option<String>>("SELECT value FROM global_keys")
+        .fetch_all(pool)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .flatten()
+        .any(|value| is_encrypted(value))
 }
 
-async fn open_db() -> SqlitePool {
-    info!(target: LOG_TARGET, "Checking if global_keys.db exists");
-    if !Sqlite::database_exists(DB_URL).await.unwrap_or(false) {
-        info!(target: LOG_TARGET, "Creating global_keys.db");
+pub struct GlobalKeyStore {
+    pool: SqlitePool,
+    /// Fails when the OS keystore is unavailable, only sensitive keys are affected
+    cipher: Result<GlobalKeyCipher, GlobalKeyEncryptionError>,
+    /// Decrypted values, `None` for keys known to be unset
+    cache: RwLock<HashMap<GlobalKey, Option<String>>>,
+    changes: broadcast::Sender<GlobalKeyChange>,
+}
+
+impl GlobalKeyStore {
+    async fn open() -> Result<Self, GlobalKeyError> {
+        info!(target: LOG_TARGET, "Checking if global_keys.db exists");
+        if !Sqlite::database_exists(DB_URL).await.unwrap_or(false) {
+            info!(target: LOG_TARGET, "Creating global_keys.db");
+
+            Sqlite::create_database(DB_URL).await.map_err(|err| {
+                error!(target: LOG_TARGET, "Failed to create global_keys.db: {:?}", err);
+                GlobalKeyError::DatabaseError
+            })?;
+        }
+
+        info!(target: LOG_TARGET, "Opening global_keys.db");
+        let pool = SqlitePool::connect(DB_URL).await.map_err(|err| {
+            error!(target: LOG_TARGET, "Failed to open global_keys.db: {:?}", err);
+            GlobalKeyError::DatabaseError
+        })?;
+
+        let has_encrypted_values = has_encrypted_values(&pool).await;
+        let cipher =
+            tokio::task::spawn_blocking(move || GlobalKeyCipher::load(has_encrypted_values))
+                .await
+                .unwrap_or(Err(GlobalKeyEncryptionError::KeystoreUnavailable));
 
-        match Sqlite::create_database(DB_URL).await {
-            Ok(_) => {
-                info!(target: LOG_TARGET, "Successfully created global_keys.db");
+        Self::with_pool(pool, cipher).await
+    }
+
+    /// Store on an open pool, creates the tables and encrypts leftover plaintext values
+    pub(crate) async fn with_pool(
+        pool: SqlitePool,
+        cipher: Result<GlobalKeyCipher, GlobalKeyEncryptionError>,
+    ) -> Result<Self, GlobalKeyError> {
+        info!(target: LOG_TARGET, "Ensuring tables");
+        for statement in [
+            "CREATE TABLE IF NOT EXISTS global_keys (key TEXT PRIMARY KEY, value TEXT)",
+            "CREATE TABLE IF NOT EXISTS sync_runs (id INTEGER PRIMARY KEY AUTOINCREMENT, started_at TEXT NOT NULL, finished_at TEXT, status TEXT NOT NULL, pushed INTEGER NOT NULL DEFAULT 0, pulled INTEGER NOT NULL DEFAULT 0, error TEXT)",
+        ] {
+            sqlx::query(statement)
+                .execute(&pool)
+                .await
+                .map_err(|_| GlobalKeyError::DatabaseError)?;
+        }
+
+        let store = Self {
+            pool,
+            cipher,
+            cache: RwLock::new(HashMap::new()),
+            changes: broadcast::channel(16).0,
+        };
+
+        store.encrypt_plaintext_keys().await;
+
+        Ok(store)
+    }
+
+    /// Open global_keys.db on first use
+    pub async fn shared() -> Result<Arc<Self>, GlobalKeyError> {
+        GLOBAL_KEY_STORE
+            .get_or_try_init(|| async { Self::open().await.map(Arc::new) })
+            .await
+            .cloned()
+    }
+
+    /// Pool of global_keys.db, also holds app state such as the sync history
+    pub fn pool(&self) -> &SqlitePool {
+        &self.pool
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<GlobalKeyChange> {
+        self.changes.subscribe()
+    }
+
+    /// Encrypt values of sensitive keys stored in plaintext by older versions
+    async fn encrypt_plaintext_keys(&self) {
+        for key in GlobalKey::SENSITIVE {
+            let value = match self.read(key).await {
+                Ok(Some(value)) if !is_encrypted(&value) => value,
+                _ => continue,
+            };
+
+            info!(target: LOG_TARGET, "Encrypting global key {:?}", key);
+            if let Err(err) = self.write(key, value).await {
+                error!(target: LOG_TARGET, "Failed to encrypt global key {:?}: {:?}", key, err);
             }
-            Err(error) => panic!("Failed to create global_keys.db: {}", error),
         }
-    } else {
-        info!(target: LOG_TARGET, "Database global_keys.db exists");
     }
 
-    info!(target: LOG_TARGET, "Opening global_keys.db");
-    let pool = SqlitePool::connect("sqlite://global_keys.db")
-        .await
-        .unwrap();
+    /// Raw value as stored in the database
+    async fn read(&self, key: GlobalKey) -> Result<Option<String>, GlobalKeyError> {
+        let row = sqlx::query("SELECT value FROM global_keys WHERE key = ?")
+            .bind(key.column_key())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|_| GlobalKeyError::DatabaseError)?;
 
-    info!(target: LOG_TARGET, "Ensuring global_keys table");
-    sqlx::query("CREATE TABLE IF NOT EXISTS global_keys (key TEXT PRIMARY KEY, value TEXT)")
-        .execute(&pool)
-        .await
-        .unwrap();
+        Ok(row.and_then(|row| row.try_get::<String, &str>("value").ok()))
+    }
+
+    fn cipher(&self) -> Result<&GlobalKeyCipher, GlobalKeyEncryptionError> {
+        self.cipher.as_ref().map_err(Clone::clone)
+    }
+
+    /// Store a value, encrypting it if the key is sensitive
+    async fn write(&self, key: GlobalKey, value: String) -> Result<(), GlobalKeyError> {
+        let stored = match key.is_sensitive() {
+            true => self
+                .cipher()
+                .and_then(|cipher| cipher.encrypt(&key.column_key(), &value))
+                .map_err(|err| {
+                    error!(target: LOG_TARGET, "Failed to encrypt global key {:?}: {:?}", key, err);
+                    GlobalKeyError::EncryptionError
+                })?,
+            false => value,
+        };
+
+        sqlx::query("INSERT INTO global_keys (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
+            .bind(key.column_key())
+            .bind(stored)
+            .execute(&self.pool)
+            .await
+            .map_err(|_| GlobalKeyError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    pub async fn get(&self, key: GlobalKey) -> Result<Option<String>, GlobalKeyError> {
+        if let Some(value) = self.cache.read().await.get(&key) {
+            return Ok(value.clone());
+        }
 
-    pool
+        // Held through the read, a set finishing in between would be overwritten with the old value
+        let mut cache = self.cache.write().await;
+        if let Some(value) = cache.get(&key) {
+            return Ok(value.clone());
+        }
+
+        debug!(target: LOG_TARGET, "Fetching global key {:?}", key);
+        let value = match self.read(key).await? {
+            Some(value) if key.is_sensitive() && is_encrypted(&value) => {
+                Some(
+                    self.cipher()
+                        .and_then(|cipher| cipher.decrypt(&key.column_key(), &value))
+                        .map_err(|err| {
+                            error!(target: LOG_TARGET, "Failed to decrypt global key {:?}: {:?}", key, err);
+                            GlobalKeyError::DecryptionError
+                        })?,
+                )
+            }
+            value => value,
+        };
+
+        cache.insert(key, value.clone());
+
+        Ok(value)
+    }
+
+    pub async fn set(&self, key: GlobalKey, value: String) -> Result<(), GlobalKeyError> {
+        info!(target: LOG_TARGET, "Setting global key {:?}", key);
+        let mut cache = self.cache.write().await;
+        self.write(key, value.clone()).await?;
+        cache.insert(key, Some(value.clone()));
+        drop(cache);
+
+        self.notify(key, Some(value));
+
+        Ok(())
+    }
+
+    pub async fn remove(&self, key: GlobalKey) -> Result<(), GlobalKeyError> {
+        info!(target: LOG_TARGET, "Removing global key {:?}", key);
+        let mut cache = self.cache.write().await;
+        sqlx::query("DELETE FROM global_keys WHERE key = ?")
+            .bind(key.column_key())
+            .execute(&self.pool)
+            .await
+            .map_err(|_| GlobalKeyError::DatabaseError)?;
+
+        cache.insert(key, None);
+        drop(cache);
+
+        self.notify(key, None);
+
+        Ok(())
+    }
+
+    fn notify(&self, key: GlobalKey, value: Option<String>) {
+        // Sending only fails when nobody is subscribed
+        self.changes
+            .send(GlobalKeyChange { key, value })
+            .unwrap_or_default();
+    }
+
+    pub async fn center_id(&self) -> Result<Option<String>, GlobalKeyError> {
+        self.get(GlobalKey::CenterId).await
+    }
+
+    pub async fn center_name(&self) -> Result<Option<String>, GlobalKeyError> {
+        self.get(GlobalKey::CenterName).await
+    }
+
+    pub async fn instance_type(&self) -> Result<InstanceType, GlobalKeyError> {
+        match self.get(GlobalKey::InstanceType).await? {
+            None => Ok(InstanceType::Uninitialized),
+            // Stored as the plain variant name, e.g. `master`
+            Some(instance_type) => serde_json::from_value(serde_json::Value::String(instance_type))
+                .map_err(|_| GlobalKeyError::InvalidValue),
+        }
+    }
+
+    pub async fn private_key(&self) -> Result<Option<String>, GlobalKeyError> {
+        self.get(GlobalKey::PrivateKey).await
+    }
+
+    pub async fn public_key(&self) -> Result<Option<String>, GlobalKeyError> {
+        self.get(GlobalKey::PublicKey).await
+    }
+
+    /// Sequence of the last change pulled from central, 0 before the first pull
+    pub async fn sync_cursor(&self) -> Result<i64, GlobalKeyError> {
+        match self.get(GlobalKey::SyncCursor).await? {
+            None => Ok(0),
+            Some(cursor) => cursor.parse().map_err(|_| GlobalKeyError::InvalidValue),
+        }
+    }
+
+    pub async fn set_sync_cursor(&self, cursor: i64) -> Result<(), GlobalKeyError> {
+        self.set(GlobalKey::SyncCursor, cursor.to_string()).await
+    }
+
+    /// Id of this installation, generated the first time it is needed
+    pub async fn installation_id(&self) -> Result<String, GlobalKeyError> {
+        if let Some(installation_id) = self.get(GlobalKey::InstallationId).await? {
+            return Ok(installation_id);
+        }
+
+        let installation_id = Alphanumeric.sample_string(&mut OsRng, 16).to_lowercase();
+        self.set(GlobalKey::InstallationId, installation_id.clone())
+            .await?;
+
+        Ok(installation_id)
+    }
+
+    pub async fn central_api_url(&self) -> Result<Option<String>, GlobalKeyError> {
+        self.get(GlobalKey::CentralApiUrl).await
+    }
+
+    pub async fn central_api_certificate(&self) -> Result<Option<String>, GlobalKeyError> {
+        self.get(GlobalKey::CentralApiCertificate).await
+    }
 }
 
 #[tauri::command]
 #[specta::specta]
-pub async fn set_global_key(key: GlobalKey, value: String) -> Result<(), ()> {
-    let key = serde_json::to_string(&key).unwrap();
-
-    debug!(target: LOG_TARGET, "Unwrapping pool to set global key {key}");
-    let pool = open_db().await;
-
-    info!(target: LOG_TARGET, "Setting global key {key}");
-    sqlx::query("INSERT INTO global_keys (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value")
-        .bind(key)
-        .bind(value)
-        .execute(&pool)
-        .await
-        .map_err(|_| ())?;
+pub async fn set_global_key(
+    store: State<'_, Arc<GlobalKeyStore>>,
+    key: GlobalKey,
+    value: String,
+) -> Result<(), GlobalKeyError> {
+    if !key.is_ui_owned() {
+        warn!(target: LOG_TARGET, "Refusing to set backend owned global key {:?}", key);
+        return Err(GlobalKeyError::BackendOwnedKey);
+    }
 
-    Ok(())
+    store.set(key, value).await
 }
 
+/// Make this installation the master of a center created on central
 #[tauri::command]
 #[specta::specta]
-pub async fn get_global_key(key: GlobalKey) -> Option<String> {
-    let key = serde_json::to_string(&key).unwrap();
+pub async fn initialize_center(
+    store: State<'_, Arc<GlobalKeyStore>>,
+    center_id: String,
+    center_name: String,
+    private_key: String,
+    public_key: String,
+) -> Result<(), GlobalKeyError> {
+    if !is_key_pair(&private_key, &public_key) {
+        warn!(target: LOG_TARGET, "Public key of center {} doesn't match its private key", center_id);
+        return Err(GlobalKeyError::InvalidValue);
+    }
 
-    debug!(target: LOG_TARGET, "Unwrapping pool to fetch global key {key}");
-    let pool = open_db().await;
+    info!(target: LOG_TARGET, "Initializing center {}", center_id);
+    store.set(GlobalKey::CenterId, center_id).await?;
+    store.set(GlobalKey::CenterName, center_name).await?;
+    store.set(GlobalKey::PrivateKey, private_key).await?;
+    store.set(GlobalKey::PublicKey, public_key).await?;
+    store
+        .set(GlobalKey::InstanceType, "master".to_string())
+        .await
+}
 
-    info!(target: LOG_TARGET, "Fetching global key {key}");
-    let row = sqlx::query("SELECT value FROM global_keys WHERE key = ?")
-        .bind(key)
-        .fetch_one(&pool)
-        .await;
+#[tauri::command]
+#[specta::specta]
+pub async fn get_global_key(
+    store: State<'_, Arc<GlobalKeyStore>>,
+    key: GlobalKey,
+) -> Result<Option<String>, GlobalKeyError> {
+    if key.is_sensitive() {
+        return Err(GlobalKeyError::SensitiveKey);
+    }
 
-    row.map(|row| row.try_get::<String, &str>("value").ok())
-        .unwrap_or(None)
+    store.get(key).await
 }
@@ -0,0 +1,134 @@
+use base64::Engine;
+use keyring::Entry;
+use log::{error, info};
+use ring::aead::{LessSafeKey, UnboundKey, AES_256_GCM};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+
+use crate::pairing::{open_payload, seal_payload};
+
+/// OS keystore entry (Keychain, Credential Manager, Secret Service) holding the machine secret
+static KEYRING_SERVICE: &str = "magmooty";
+
+static KEYRING_USER: &str = "global-keys";
+
+static SECRET_LEN: usize = 32;
+
+/// Prefix of encrypted values, values without it are plaintext written before encryption
+static ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+static LOG_TARGET: &str = "Global keys encryption";
+
+#[derive(Debug, Clone)]
+pub enum GlobalKeyEncryptionError {
+    KeystoreUnavailable,
+    SecretReadError,
+    /// The keystore has no secret while values encrypted with one are stored
+    SecretMissing,
+    RandomError,
+    KeyDerivationError,
+    EncryptionError,
+    DecryptionError,
+}
+
+/// Encrypts values of sensitive global keys with a key derived from the machine secret
+pub struct GlobalKeyCipher {
+    key: LessSafeKey,
+}
+
+fn generate_secret() -> Result<Vec<u8>, GlobalKeyEncryptionError> {
+    let mut secret = vec![0; SECRET_LEN];
+
+    SystemRandom::new()
+        .fill(&mut secret)
+        .map_err(|_| GlobalKeyEncryptionError::RandomError)?;
+
+    Ok(secret)
+}
+
+/// Machine secret from the OS keystore, created on first use
+///
+/// A new secret is only created while no value is encrypted, replacing a lost secret would make
+/// the stored values unreadable and let them be overwritten.
+fn load_secret(has_encrypted_values: bool) -> Result<Vec<u8>, GlobalKeyEncryptionError> {
+    let entry = Entry::new(KEYRING_SERVICE, KEYRING_USER).map_err(|err| {
+        error!(target: LOG_TARGET, "Failed to open keystore entry: {:?}", err);
+        GlobalKeyEncryptionError::KeystoreUnavailable
+    })?;
+
+    match entry.get_password() {
+        Ok(secret) => match base64::prelude::BASE64_STANDARD.decode(secret) {
+            Ok(secret) if secret.len() == SECRET_LEN => Ok(secret),
+            _ => {
+                error!(target: LOG_TARGET, "Keystore secret is corrupted");
+                Err(GlobalKeyEncryptionError::SecretReadError)
+            }
+        },
+        Err(keyring::Error::NoEntry) if has_encrypted_values => {
+            error!(target: LOG_TARGET, "Keystore secret is missing, encrypted global keys can't be read");
+            Err(GlobalKeyEncryptionError::SecretMissing)
+        }
+        Err(keyring::Error::NoEntry) => {
+            info!(target: LOG_TARGET, "Generating keystore secret");
+            let secret = generate_secret()?;
+
+            entry
+                .set_password(&base64::prelude::BASE64_STANDARD.encode(&secret))
+                .map_err(|err| {
+                    error!(target: LOG_TARGET, "Failed to store keystore secret: {:?}", err);
+                    GlobalKeyEncryptionError::KeystoreUnavailable
+                })?;
+
+            Ok(secret)
+        }
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to read keystore secret: {:?}", err);
+            Err(GlobalKeyEncryptionError::KeystoreUnavailable)
+        }
+    }
+}
+
+pub(crate) fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+impl GlobalKeyCipher {
+    pub fn from_secret(secret: &[u8]) -> Result<Self, GlobalKeyEncryptionError> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, b"magmooty global keys").extract(secret);
+        let key = prk
+            .expand(&[b"aes-256-gcm v1"], &AES_256_GCM)
+            .map_err(|_| GlobalKeyEncryptionError::KeyDerivationError)?;
+
+        Ok(Self {
+            key: LessSafeKey::new(UnboundKey::from(key)),
+        })
+    }
+
+    /// Cipher keyed by the machine secret in the OS keystore, blocks on the keystore
+    pub fn load(has_encrypted_values: bool) -> Result<Self, GlobalKeyEncryptionError> {
+        Self::from_secret(&load_secret(has_encrypted_values)?)
+    }
+
+    /// Encrypt a value, `key` is authenticated so a value can't be moved to another key
+    pub fn encrypt(&self, key: &str, value: &str) -> Result<String, GlobalKeyEncryptionError> {
+        let payload = seal_payload(&self.key, key.as_bytes(), value.as_bytes())
+            .map_err(|_| GlobalKeyEncryptionError::EncryptionError)?;
+
+        Ok(format!(
+            "{}{}",
+            ENCRYPTED_PREFIX,
+            base64::prelude::BASE64_STANDARD.encode(payload)
+        ))
+    }
+
+    pub fn decrypt(&self, key: &str, value: &str) -> Result<String, GlobalKeyEncryptionError> {
+        let payload = base64::prelude::BASE64_STANDARD
+            .decode(value.trim_start_matches(ENCRYPTED_PREFIX))
+            .map_err(|_| GlobalKeyEncryptionError::DecryptionError)?;
+
+        let value = open_payload(&self.key, key.as_bytes(), &payload)
+            .map_err(|_| GlobalKeyEncryptionError::DecryptionError)?;
+
+        String::from_utf8(value).map_err(|_| GlobalKeyEncryptionError::DecryptionError)
+    }
+}
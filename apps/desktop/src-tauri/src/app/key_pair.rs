@@ -2,7 +2,7 @@ use base64::Engine;
 use log::{debug, info};
 use rand::rngs::OsRng;
 use rsa::{
-    pkcs1::{EncodeRsaPrivateKey, EncodeRsaPublicKey},
+    pkcs1::{DecodeRsaPrivateKey, EncodeRsaPrivateKey, EncodeRsaPublicKey},
     RsaPrivateKey, RsaPublicKey,
 };
 use serde::Serialize;
@@ -53,3 +53,18 @@ pub async fn generate_key_pair() -> KeyPair {
     .await
     .expect("Failed to generate key pair")
 }
+
+/// Whether `public_key` belongs to `private_key`, both base64 PKCS#1 DER as generated above
+pub fn is_key_pair(private_key: &str, public_key: &str) -> bool {
+    let engine = base64::prelude::BASE64_STANDARD;
+
+    let (Ok(private_key), Ok(public_key)) = (engine.decode(private_key), engine.decode(public_key))
+    else {
+        return false;
+    };
+
+    RsaPrivateKey::from_pkcs1_der(&private_key)
+        .ok()
+        .and_then(|private_key| RsaPublicKey::from(&private_key).to_pkcs1_der().ok())
+        .is_some_and(|derived| derived.as_bytes() == public_key.as_slice())
+}
@@ -1,13 +1,27 @@
 mod global_keys;
+mod global_keys_encryption;
 mod key_pair;
 mod network_discovery;
 mod database_auth;
 mod splash_screen;
 mod reports;
+mod sync;
+mod central_api;
+mod pairing;
+mod sidecars;
+mod ports;
+mod backup;
+mod test_global_keys;
 
 pub use global_keys::*;
 pub use key_pair::*;
 pub use network_discovery::*;
 pub use database_auth::*;
 pub use splash_screen::*;
-pub use reports::*;
\ No newline at end of file
+pub use reports::*;
+pub use sync::*;
+pub use central_api::*;
+pub use pairing::*;
+pub use sidecars::*;
+pub use ports::*;
+pub use backup::*;
\ No newline at end of file
@@ -0,0 +1,30 @@
+use std::net::Ipv4Addr;
+
+use log::debug;
+
+use crate::pairing::{self, PairedMaster, PairingChallenge, PairingError};
+
+static LOG_TARGET: &str = "Pairing";
+
+#[tauri::command]
+#[specta::specta]
+pub async fn request_pairing(
+    address: Ipv4Addr,
+    port: Option<u16>,
+    device_name: String,
+) -> Result<PairingChallenge, PairingError> {
+    debug!(target: LOG_TARGET, "Pairing requested from the UI");
+    pairing::request_pairing(address, port, device_name).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn confirm_pairing(pin: String) -> Result<PairedMaster, PairingError> {
+    pairing::confirm_pairing(pin).await
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn reject_pairing(request_id: String) {
+    pairing::reject_pairing(&request_id).await;
+}
@@ -0,0 +1,8 @@
+use crate::ports::{service_ports, ServicePorts};
+
+/// Ports the local services run on, the UI connects to the local database with them
+#[tauri::command]
+#[specta::specta]
+pub async fn get_service_ports() -> ServicePorts {
+    service_ports()
+}
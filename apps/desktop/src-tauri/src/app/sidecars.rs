@@ -0,0 +1,10 @@
+use std::collections::HashMap;
+
+use crate::sidecar::{sidecar_statuses, SidecarStatus};
+
+/// Status of every supervised sidecar, later changes are emitted as `sidecar_status` events
+#[tauri::command]
+#[specta::specta]
+pub async fn get_sidecar_statuses() -> HashMap<String, SidecarStatus> {
+    sidecar_statuses()
+}
@@ -0,0 +1,222 @@
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+
+use super::global_keys::{GlobalKeyError, GlobalKeyStore};
+use crate::sync::{request_sync, SyncRunError, SyncRunReport};
+
+static LOG_TARGET: &str = "Sync";
+
+/// Number of sync runs kept in the history
+static SYNC_HISTORY_SIZE: i64 = 500;
+
+/// Number of sync runs listed when no limit is given
+static DEFAULT_SYNC_HISTORY_LIMIT: u32 = 50;
+
+static NOW: &str = "strftime('%Y-%m-%dT%H:%M:%fZ', 'now')";
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncRunStatus {
+    Running,
+    Succeeded,
+    /// Central could not be reached
+    Offline,
+    Failed,
+    /// The app was closed during the run
+    Interrupted,
+}
+
+impl SyncRunStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SyncRunStatus::Running => "running",
+            SyncRunStatus::Succeeded => "succeeded",
+            SyncRunStatus::Offline => "offline",
+            SyncRunStatus::Failed => "failed",
+            SyncRunStatus::Interrupted => "interrupted",
+        }
+    }
+
+    fn from_str(status: &str) -> Self {
+        match status {
+            "running" => SyncRunStatus::Running,
+            "succeeded" => SyncRunStatus::Succeeded,
+            "offline" => SyncRunStatus::Offline,
+            "interrupted" => SyncRunStatus::Interrupted,
+            _ => SyncRunStatus::Failed,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+pub struct SyncRun {
+    pub id: i32,
+    /// RFC 3339 UTC timestamp
+    pub started_at: String,
+    /// RFC 3339 UTC timestamp, missing while the run is going on
+    pub finished_at: Option<String>,
+    pub status: SyncRunStatus,
+    /// Local changes uploaded to central
+    pub pushed: u32,
+    /// Changes from other installations applied locally
+    pub pulled: u32,
+    pub error: Option<String>,
+}
+
+impl SyncRun {
+    fn from_row(row: &SqliteRow) -> Result<Self, sqlx::Error> {
+        Ok(Self {
+            id: row.try_get("id")?,
+            started_at: row.try_get("started_at")?,
+            finished_at: row.try_get("finished_at")?,
+            status: SyncRunStatus::from_str(row.try_get("status")?),
+            pushed: row.try_get("pushed")?,
+            pulled: row.try_get("pulled")?,
+            error: row.try_get("error")?,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Type, Clone)]
+pub struct SyncStatus {
+    pub last_run: Option<SyncRun>,
+    pub last_successful_run: Option<SyncRun>,
+}
+
+/// Record the start of a sync run, returns its ID
+pub(crate) async fn start_sync_run() -> Option<i32> {
+    let store = GlobalKeyStore::shared().await.ok()?;
+
+    debug!(target: LOG_TARGET, "Recording sync run start");
+    let row = sqlx::query(&format!(
+        "INSERT INTO sync_runs (started_at, status) VALUES ({}, ?) RETURNING id",
+        NOW
+    ))
+    .bind(SyncRunStatus::Running.as_str())
+    .fetch_one(store.pool())
+    .await;
+
+    match row.and_then(|row| row.try_get::<i32, &str>("id")) {
+        Ok(id) => Some(id),
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to record sync run: {:?}", err);
+            None
+        }
+    }
+}
+
+/// Record the outcome of a sync run and drop the oldest runs from the history
+pub(crate) async fn finish_sync_run(id: i32, report: &SyncRunReport, error: Option<&SyncRunError>) {
+    let status = match error {
+        None => SyncRunStatus::Succeeded,
+        Some(SyncRunError::Offline(_)) => SyncRunStatus::Offline,
+        Some(SyncRunError::Failed(_)) => SyncRunStatus::Failed,
+    };
+
+    let store = match GlobalKeyStore::shared().await {
+        Ok(store) => store,
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to record sync run {}: {:?}", id, err);
+            return;
+        }
+    };
+
+    info!(target: LOG_TARGET, "Recording sync run {} as {:?}", id, status);
+    let result = sqlx::query(&format!(
+        "UPDATE sync_runs SET finished_at = {}, status = ?, pushed = ?, pulled = ?, error = ? WHERE id = ?",
+        NOW
+    ))
+    .bind(status.as_str())
+    .bind(report.pushed)
+    .bind(report.pulled)
+    .bind(error.map(|error| error.to_string()))
+    .bind(id)
+    .execute(store.pool())
+    .await;
+
+    if let Err(err) = result {
+        error!(target: LOG_TARGET, "Failed to record sync run {}: {:?}", id, err);
+    }
+
+    sqlx::query("DELETE FROM sync_runs WHERE id <= (SELECT MAX(id) FROM sync_runs) - ?")
+        .bind(SYNC_HISTORY_SIZE)
+        .execute(store.pool())
+        .await
+        .unwrap_or_default();
+}
+
+/// Mark runs left running by a previous session as interrupted
+pub(crate) async fn interrupt_sync_runs() {
+    let store = match GlobalKeyStore::shared().await {
+        Ok(store) => store,
+        Err(_) => return,
+    };
+
+    sqlx::query(&format!(
+        "UPDATE sync_runs SET finished_at = {}, status = ? WHERE status = ?",
+        NOW
+    ))
+    .bind(SyncRunStatus::Interrupted.as_str())
+    .bind(SyncRunStatus::Running.as_str())
+    .execute(store.pool())
+    .await
+    .unwrap_or_default();
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn sync_now() {
+    debug!(target: LOG_TARGET, "Sync requested from the UI");
+    request_sync();
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn get_sync_status() -> Result<SyncStatus, GlobalKeyError> {
+    let store = GlobalKeyStore::shared().await?;
+
+    debug!(target: LOG_TARGET, "Fetching sync status");
+    let last_run = sqlx::query("SELECT * FROM sync_runs ORDER BY id DESC LIMIT 1")
+        .fetch_optional(store.pool())
+        .await
+        .map_err(|_| GlobalKeyError::DatabaseError)?;
+
+    let last_successful_run =
+        sqlx::query("SELECT * FROM sync_runs WHERE status = ? ORDER BY id DESC LIMIT 1")
+            .bind(SyncRunStatus::Succeeded.as_str())
+            .fetch_optional(store.pool())
+            .await
+            .map_err(|_| GlobalKeyError::DatabaseError)?;
+
+    Ok(SyncStatus {
+        last_run: last_run
+            .map(|row| SyncRun::from_row(&row))
+            .transpose()
+            .map_err(|_| GlobalKeyError::DatabaseError)?,
+        last_successful_run: last_successful_run
+            .map(|row| SyncRun::from_row(&row))
+            .transpose()
+            .map_err(|_| GlobalKeyError::DatabaseError)?,
+    })
+}
+
+#[tauri::command]
+#[specta::specta]
+pub async fn list_sync_history(limit: Option<u32>) -> Result<Vec<SyncRun>, GlobalKeyError> {
+    let store = GlobalKeyStore::shared().await?;
+
+    debug!(target: LOG_TARGET, "Fetching sync history");
+    let rows = sqlx::query("SELECT * FROM sync_runs ORDER BY id DESC LIMIT ?")
+        .bind(limit.unwrap_or(DEFAULT_SYNC_HISTORY_LIMIT))
+        .fetch_all(store.pool())
+        .await
+        .map_err(|_| GlobalKeyError::DatabaseError)?;
+
+    rows.iter()
+        .map(SyncRun::from_row)
+        .collect::<Result<Vec<SyncRun>, sqlx::Error>>()
+        .map_err(|_| GlobalKeyError::DatabaseError)
+}
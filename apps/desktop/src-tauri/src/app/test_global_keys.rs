@@ -0,0 +1,225 @@
+#[cfg(test)]
+mod tests {
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::{Row, SqlitePool};
+
+    use crate::app::global_keys::has_encrypted_values;
+    use crate::app::global_keys_encryption::{
+        is_encrypted, GlobalKeyCipher, GlobalKeyEncryptionError,
+    };
+    use crate::app::{generate_key_pair, is_key_pair, GlobalKey, GlobalKeyStore};
+
+    static SECRET: [u8; 32] = [7; 32];
+
+    fn cipher() -> GlobalKeyCipher {
+        GlobalKeyCipher::from_secret(&SECRET).unwrap()
+    }
+
+    async fn memory_pool() -> SqlitePool {
+        // A single connection that never expires, every connection gets its own memory database
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    async fn stored_value(pool: &SqlitePool, key: &str) -> String {
+        sqlx::query("SELECT value FROM global_keys WHERE key = ?")
+            .bind(key)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+            .get("value")
+    }
+
+    #[test]
+    fn test_encrypt_decrypt_round_trip() {
+        let encrypted = cipher().encrypt("\"private_key\"", "secret value").unwrap();
+
+        assert!(is_encrypted(&encrypted));
+        assert!(!encrypted.contains("secret value"));
+        assert_eq!(
+            cipher().decrypt("\"private_key\"", &encrypted).unwrap(),
+            "secret value"
+        );
+    }
+
+    #[test]
+    fn test_encryption_uses_fresh_nonces() {
+        let first = cipher().encrypt("\"private_key\"", "secret value").unwrap();
+        let second = cipher().encrypt("\"private_key\"", "secret value").unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn test_decrypt_rejects_other_key() {
+        let encrypted = cipher().encrypt("\"private_key\"", "secret value").unwrap();
+
+        assert!(cipher().decrypt("\"backup_password\"", &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_rejects_other_secret() {
+        let encrypted = cipher().encrypt("\"private_key\"", "secret value").unwrap();
+        let other = GlobalKeyCipher::from_secret(&[8; 32]).unwrap();
+
+        assert!(other.decrypt("\"private_key\"", &encrypted).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store_encrypts_sensitive_keys() {
+        let pool = memory_pool().await;
+        let store = GlobalKeyStore::with_pool(pool.clone(), Ok(cipher()))
+            .await
+            .unwrap();
+
+        store
+            .set(GlobalKey::PrivateKey, "private key".to_string())
+            .await
+            .unwrap();
+        store
+            .set(GlobalKey::CenterName, "center".to_string())
+            .await
+            .unwrap();
+
+        let private_key = stored_value(&pool, "\"private_key\"").await;
+        assert!(is_encrypted(&private_key));
+        assert_eq!(stored_value(&pool, "\"center_name\"").await, "center");
+
+        // A fresh store has nothing cached and reads through the cipher
+        let store = GlobalKeyStore::with_pool(pool, Ok(cipher())).await.unwrap();
+        assert_eq!(
+            store.private_key().await.unwrap(),
+            Some("private key".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_migrates_plaintext_values() {
+        let pool = memory_pool().await;
+        GlobalKeyStore::with_pool(pool.clone(), Ok(cipher()))
+            .await
+            .unwrap();
+
+        for (key, value) in [
+            ("\"private_key\"", "private key"),
+            ("\"backup_password\"", "backup password"),
+            ("\"center_id\"", "center"),
+        ] {
+            sqlx::query("INSERT INTO global_keys (key, value) VALUES (?, ?)")
+                .bind(key)
+                .bind(value)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+
+        let store = GlobalKeyStore::with_pool(pool.clone(), Ok(cipher()))
+            .await
+            .unwrap();
+
+        let private_key = stored_value(&pool, "\"private_key\"").await;
+        let backup_password = stored_value(&pool, "\"backup_password\"").await;
+        assert!(is_encrypted(&private_key));
+        assert!(is_encrypted(&backup_password));
+        assert_eq!(
+            cipher().decrypt("\"private_key\"", &private_key).unwrap(),
+            "private key"
+        );
+        assert_eq!(stored_value(&pool, "\"center_id\"").await, "center");
+
+        assert_eq!(
+            store.private_key().await.unwrap(),
+            Some("private key".to_string())
+        );
+        assert_eq!(
+            store.get(GlobalKey::BackupPassword).await.unwrap(),
+            Some("backup password".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_store_keeps_plaintext_without_keystore() {
+        let pool = memory_pool().await;
+        GlobalKeyStore::with_pool(pool.clone(), Ok(cipher()))
+            .await
+            .unwrap();
+
+        sqlx::query("INSERT INTO global_keys (key, value) VALUES (?, ?)")
+            .bind("\"private_key\"")
+            .bind("private key")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let store = GlobalKeyStore::with_pool(
+            pool.clone(),
+            Err(GlobalKeyEncryptionError::KeystoreUnavailable),
+        )
+        .await
+        .unwrap();
+
+        // Left as is to be encrypted once the keystore is reachable
+        assert_eq!(stored_value(&pool, "\"private_key\"").await, "private key");
+        assert!(store
+            .set(GlobalKey::PrivateKey, "new private key".to_string())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_detects_encrypted_values() {
+        let pool = memory_pool().await;
+
+        assert!(!has_encrypted_values(&pool).await);
+
+        let store = GlobalKeyStore::with_pool(pool.clone(), Ok(cipher()))
+            .await
+            .unwrap();
+        store
+            .set(GlobalKey::CenterId, "center:id".to_string())
+            .await
+            .unwrap();
+
+        assert!(!has_encrypted_values(&pool).await);
+
+        store
+            .set(GlobalKey::PrivateKey, "private key".to_string())
+            .await
+            .unwrap();
+
+        // A lost keystore secret must not be replaced from now on
+        assert!(has_encrypted_values(&pool).await);
+    }
+
+    #[test]
+    fn test_ui_owned_keys() {
+        assert!(GlobalKey::CenterName.is_ui_owned());
+
+        for key in [
+            GlobalKey::CenterId,
+            GlobalKey::InstanceType,
+            GlobalKey::PrivateKey,
+            GlobalKey::PublicKey,
+            GlobalKey::DatabasePassword,
+            GlobalKey::RootDatabaseCredentials,
+            GlobalKey::BackupPassword,
+        ] {
+            assert!(!key.is_ui_owned(), "{:?} is set from the UI", key);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_is_key_pair() {
+        let center = generate_key_pair().await;
+        let other_center = generate_key_pair().await;
+
+        assert!(is_key_pair(&center.private_key, &center.public_key));
+        assert!(!is_key_pair(&center.private_key, &other_center.public_key));
+        assert!(!is_key_pair("not a key", &center.public_key));
+    }
+}
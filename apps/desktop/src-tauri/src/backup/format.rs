@@ -0,0 +1,166 @@
+use std::io::{BufRead, Read, Write};
+use std::num::NonZeroU32;
+
+use base64::Engine;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ring::aead::{LessSafeKey, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::pbkdf2;
+use serde::{Deserialize, Serialize};
+
+use super::BackupError;
+use crate::pairing::{generate_random_bytes, open_payload, seal_payload};
+
+/// First line of every backup file
+static MAGIC: &[u8] = b"MAGMOOTY-BACKUP";
+
+pub static BACKUP_VERSION: u32 = 1;
+
+/// Makes guessing the password of a stolen backup slow
+static PASSWORD_ITERATIONS: u32 = 100_000;
+
+static SALT_LEN: usize = 16;
+
+/// Second line of a backup file, readable without the password but authenticated with the
+/// contents so it can't be changed
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupHeader {
+    pub version: u32,
+    pub center_id: String,
+    pub center_name: String,
+    /// RFC 3339 UTC timestamp
+    pub created_at: String,
+    /// Made by the schedule, only automatic backups are pruned
+    pub automatic: bool,
+    /// Base64 salt the key is derived from the password with
+    pub salt: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BackupTable {
+    pub name: String,
+    /// Records of the table, every page is a SurrealQL array
+    pub pages: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct BackupContents {
+    /// Statements defining the schema of the local database
+    pub definitions: Vec<String>,
+    pub tables: Vec<BackupTable>,
+    /// Sequence of the last change pulled from central when the backup was made
+    pub sync_cursor: i64,
+}
+
+pub fn generate_salt() -> Result<String, BackupError> {
+    let salt = generate_random_bytes(SALT_LEN).map_err(|_| BackupError::EncryptionError)?;
+
+    Ok(base64::prelude::BASE64_STANDARD.encode(salt))
+}
+
+fn derive_key(password: &str, salt: &str) -> Result<LessSafeKey, BackupError> {
+    let salt = base64::prelude::BASE64_STANDARD
+        .decode(salt)
+        .map_err(|_| BackupError::InvalidBackup)?;
+
+    let mut key = [0; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        NonZeroU32::new(PASSWORD_ITERATIONS).unwrap(),
+        &salt,
+        password.as_bytes(),
+        &mut key,
+    );
+
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| BackupError::EncryptionError)?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// Magic line, header line, then the nonce and the encrypted gzip of the contents JSON
+pub fn encode_backup(
+    header: &BackupHeader,
+    contents: &BackupContents,
+    password: &str,
+) -> Result<Vec<u8>, BackupError> {
+    // JSON escapes line breaks in strings, the header stays on one line
+    let header_json = serde_json::to_vec(header).map_err(|_| BackupError::InvalidBackup)?;
+    let contents_json = serde_json::to_vec(contents).map_err(|_| BackupError::InvalidBackup)?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let compressed = encoder
+        .write_all(&contents_json)
+        .and_then(|_| encoder.finish())
+        .map_err(|_| BackupError::InvalidBackup)?;
+
+    let sealed = seal_payload(
+        &derive_key(password, &header.salt)?,
+        &header_json,
+        &compressed,
+    )
+    .map_err(|_| BackupError::EncryptionError)?;
+
+    let mut backup = MAGIC.to_vec();
+    backup.push(b'\n');
+    backup.extend(&header_json);
+    backup.push(b'\n');
+    backup.extend(sealed);
+
+    Ok(backup)
+}
+
+/// Read the header of a backup without decrypting it, returns the header and its raw JSON
+pub fn read_header(reader: &mut impl BufRead) -> Result<(BackupHeader, Vec<u8>), BackupError> {
+    let mut magic = Vec::new();
+    reader
+        .read_until(b'\n', &mut magic)
+        .map_err(|_| BackupError::FileError)?;
+
+    if magic.strip_suffix(b"\n") != Some(MAGIC) {
+        return Err(BackupError::InvalidBackup);
+    }
+
+    let mut header_json = Vec::new();
+    reader
+        .read_until(b'\n', &mut header_json)
+        .map_err(|_| BackupError::FileError)?;
+
+    if header_json.pop() != Some(b'\n') {
+        return Err(BackupError::InvalidBackup);
+    }
+
+    let header: BackupHeader =
+        serde_json::from_slice(&header_json).map_err(|_| BackupError::InvalidBackup)?;
+
+    if header.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion);
+    }
+
+    Ok((header, header_json))
+}
+
+pub fn decode_backup(
+    backup: &[u8],
+    password: &str,
+) -> Result<(BackupHeader, BackupContents), BackupError> {
+    let mut reader = backup;
+    let (header, header_json) = read_header(&mut reader)?;
+
+    if reader.len() < NONCE_LEN {
+        return Err(BackupError::InvalidBackup);
+    }
+
+    // A changed header fails like a wrong password, both break the authentication tag
+    let compressed = open_payload(&derive_key(password, &header.salt)?, &header_json, reader)
+        .map_err(|_| BackupError::WrongPassword)?;
+
+    let mut contents_json = Vec::new();
+    GzDecoder::new(&compressed[..])
+        .read_to_end(&mut contents_json)
+        .map_err(|_| BackupError::InvalidBackup)?;
+
+    let contents =
+        serde_json::from_slice(&contents_json).map_err(|_| BackupError::InvalidBackup)?;
+
+    Ok((header, contents))
+}
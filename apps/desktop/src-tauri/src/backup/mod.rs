@@ -0,0 +1,611 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use surrealdb::engine::any::Any;
+use surrealdb::sql::{self, Thing};
+use surrealdb::Surreal;
+use tauri::Window;
+use tokio::sync::{Mutex, Notify};
+use tokio::time::{sleep, Duration};
+
+use crate::app::{connect_as_root, GlobalKey, GlobalKeyStore};
+use crate::network_discovery::InstanceType;
+use crate::replication::read_schema;
+use crate::sync::RetryPolicy;
+
+mod format;
+mod test_backup;
+
+pub use format::*;
+
+static LOG_TARGET: &str = "Backup";
+
+pub static BACKUP_EXTENSION: &str = "mgbackup";
+
+static MIN_PASSWORD_LEN: usize = 8;
+
+/// Number of records exported in one page
+static PAGE_SIZE: i64 = 500;
+
+/// How often the schedule is looked at while backups are disabled or can't be made
+static SCHEDULE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Only one backup or restore runs at a time
+static BACKUP_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+static SETTINGS_CHANGED: Lazy<Notify> = Lazy::new(Notify::new);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum BackupError {
+    /// There is no local center to back up or restore into
+    NotInitialized,
+    /// The backup belongs to another center
+    CenterMismatch,
+    /// Slaves get their data back from the master
+    NotAllowedOnSlave,
+    /// No password was given and none is stored for scheduled backups
+    NoPassword,
+    WeakPassword,
+    InvalidSettings,
+    /// The password is wrong or the backup was changed
+    WrongPassword,
+    InvalidBackup,
+    /// The backup was made by a newer version of the app
+    UnsupportedVersion,
+    EncryptionError,
+    FileError,
+    DatabaseError,
+    StorageError,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+pub struct BackupSettings {
+    /// Make backups on a schedule
+    pub enabled: bool,
+    /// Directory scheduled backups are written to, relative to global_keys.db
+    pub directory: String,
+    pub interval_hours: u32,
+    /// Scheduled backups kept, older ones are deleted
+    pub retention: u32,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            directory: "backups".to_string(),
+            interval_hours: 24,
+            retention: 7,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+pub struct BackupInfo {
+    pub path: String,
+    pub center_id: String,
+    pub center_name: String,
+    /// RFC 3339 UTC timestamp
+    pub created_at: String,
+    pub automatic: bool,
+}
+
+impl BackupInfo {
+    fn new(path: &Path, header: BackupHeader) -> Self {
+        Self {
+            path: path.to_string_lossy().to_string(),
+            center_id: header.center_id,
+            center_name: header.center_name,
+            created_at: header.created_at,
+            automatic: header.automatic,
+        }
+    }
+}
+
+fn database_error(error: impl std::fmt::Debug) -> BackupError {
+    error!(target: LOG_TARGET, "Database error: {:?}", error);
+    BackupError::DatabaseError
+}
+
+fn file_error(error: std::io::Error) -> BackupError {
+    error!(target: LOG_TARGET, "File error: {:?}", error);
+    BackupError::FileError
+}
+
+async fn shared_store() -> Result<std::sync::Arc<GlobalKeyStore>, BackupError> {
+    GlobalKeyStore::shared()
+        .await
+        .map_err(|_| BackupError::StorageError)
+}
+
+pub async fn backup_settings(store: &GlobalKeyStore) -> BackupSettings {
+    match store.get(GlobalKey::BackupSettings).await {
+        Ok(Some(settings)) => serde_json::from_str(&settings).unwrap_or_default(),
+        _ => BackupSettings::default(),
+    }
+}
+
+fn validate_password(password: &str) -> Result<(), BackupError> {
+    match password.chars().count() >= MIN_PASSWORD_LEN {
+        true => Ok(()),
+        false => Err(BackupError::WeakPassword),
+    }
+}
+
+/// `password` replaces the stored password scheduled backups are encrypted with
+pub async fn set_backup_settings(
+    settings: BackupSettings,
+    password: Option<String>,
+) -> Result<BackupSettings, BackupError> {
+    if settings.directory.trim().is_empty()
+        || settings.interval_hours == 0
+        || settings.retention == 0
+    {
+        return Err(BackupError::InvalidSettings);
+    }
+
+    let store = shared_store().await?;
+
+    if let Some(password) = password {
+        validate_password(&password)?;
+        store
+            .set(GlobalKey::BackupPassword, password)
+            .await
+            .map_err(|_| BackupError::StorageError)?;
+    }
+
+    let has_password = matches!(store.get(GlobalKey::BackupPassword).await, Ok(Some(_)));
+    if settings.enabled && !has_password {
+        return Err(BackupError::NoPassword);
+    }
+
+    let value = serde_json::to_string(&settings).map_err(|_| BackupError::InvalidSettings)?;
+    store
+        .set(GlobalKey::BackupSettings, value)
+        .await
+        .map_err(|_| BackupError::StorageError)?;
+
+    info!(target: LOG_TARGET, "Backup settings changed to {:?}", settings);
+    SETTINGS_CHANGED.notify_one();
+
+    Ok(settings)
+}
+
+/// The given password, else the stored one
+async fn backup_password(
+    store: &GlobalKeyStore,
+    password: Option<String>,
+) -> Result<String, BackupError> {
+    match password {
+        Some(password) => Ok(password),
+        None => store
+            .get(GlobalKey::BackupPassword)
+            .await
+            .map_err(|_| BackupError::StorageError)?
+            .ok_or(BackupError::NoPassword),
+    }
+}
+
+async fn export_contents(
+    surreal: &Surreal<Any>,
+    store: &GlobalKeyStore,
+) -> Result<BackupContents, BackupError> {
+    let (tables, definitions) = read_schema(surreal).await.map_err(database_error)?;
+    let mut backup_tables = Vec::new();
+
+    for table in tables {
+        let mut pages = Vec::new();
+        // Pages follow the ids instead of an offset, records written meanwhile don't shift them
+        let mut last_id: Option<Thing> = None;
+
+        loop {
+            let records: sql::Value = surreal
+                .query("SELECT * FROM type::table($table) WHERE id > $last_id ORDER BY id LIMIT $limit")
+                .bind(("table", &table))
+                .bind(("last_id", &last_id))
+                .bind(("limit", PAGE_SIZE))
+                .await
+                .and_then(|mut response| response.take(0))
+                .map_err(database_error)?;
+
+            let sql::Value::Array(records) = records else {
+                break;
+            };
+
+            let count = records.len() as i64;
+            last_id = match records.last() {
+                Some(sql::Value::Object(record)) => match record.get("id") {
+                    Some(sql::Value::Thing(id)) => Some(id.clone()),
+                    _ => None,
+                },
+                _ => None,
+            };
+
+            if count > 0 {
+                // SurrealQL keeps record IDs and datetimes, JSON would turn them into strings
+                pages.push(sql::Value::Array(records).to_string());
+            }
+
+            if count < PAGE_SIZE || last_id.is_none() {
+                break;
+            }
+        }
+
+        debug!(target: LOG_TARGET, "Exported {} page(s) of {}", pages.len(), table);
+        backup_tables.push(BackupTable { name: table, pages });
+    }
+
+    Ok(BackupContents {
+        definitions,
+        tables: backup_tables,
+        sync_cursor: store
+            .sync_cursor()
+            .await
+            .map_err(|_| BackupError::StorageError)?,
+    })
+}
+
+fn backup_file_name(created_at: &str, automatic: bool) -> String {
+    let kind = match automatic {
+        true => "auto",
+        false => "manual",
+    };
+
+    // Colons are not allowed in Windows file names
+    format!(
+        "backup-{}-{}.{}",
+        kind,
+        created_at.replace(':', "-"),
+        BACKUP_EXTENSION
+    )
+}
+
+/// Export the local database to an encrypted backup at `path`, in the backup directory if
+/// `None`, encrypted with the stored password if `password` is `None`
+pub async fn create_backup(
+    path: Option<PathBuf>,
+    password: Option<String>,
+    automatic: bool,
+) -> Result<BackupInfo, BackupError> {
+    let _lock = BACKUP_LOCK.lock().await;
+    let store = shared_store().await?;
+
+    if let Some(password) = &password {
+        validate_password(password)?;
+    }
+
+    let password = backup_password(&store, password).await?;
+    let center_id = store.center_id().await.unwrap_or_default();
+    let center_name = store.center_name().await.unwrap_or_default();
+    let (Some(center_id), Some(center_name)) = (center_id, center_name) else {
+        return Err(BackupError::NotInitialized);
+    };
+
+    let surreal = connect_as_root().await.map_err(database_error)?;
+
+    let created_at: Option<String> = surreal
+        .query("RETURN time::format(time::now(), '%Y-%m-%dT%H:%M:%SZ')")
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(database_error)?;
+    let created_at = created_at.ok_or(BackupError::DatabaseError)?;
+
+    info!(target: LOG_TARGET, "Exporting the local database");
+    let contents = export_contents(&surreal, &store).await?;
+
+    let header = BackupHeader {
+        version: BACKUP_VERSION,
+        center_id,
+        center_name,
+        created_at,
+        automatic,
+        salt: generate_salt()?,
+    };
+
+    let backup = encode_backup(&header, &contents, &password)?;
+
+    let path = match path {
+        Some(path) => path,
+        None => Path::new(&backup_settings(&store).await.directory)
+            .join(backup_file_name(&header.created_at, automatic)),
+    };
+
+    if let Some(directory) = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(directory).map_err(file_error)?;
+    }
+
+    // Written next to the target first so a crash never leaves half a backup behind
+    let partial_path = path.with_extension("partial");
+    fs::write(&partial_path, backup)
+        .and_then(|_| fs::rename(&partial_path, &path))
+        .map_err(file_error)?;
+
+    info!(target: LOG_TARGET, "Backup written to {}", path.display());
+    Ok(BackupInfo::new(&path, header))
+}
+
+/// Backups in the backup directory, newest first
+pub async fn list_backups() -> Result<Vec<BackupInfo>, BackupError> {
+    let store = shared_store().await?;
+    let directory = backup_settings(&store).await.directory;
+
+    let entries = match fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(file_error(err)),
+    };
+
+    let mut backups: Vec<BackupInfo> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == BACKUP_EXTENSION))
+        .filter_map(|path| {
+            let header = fs::File::open(&path)
+                .map_err(file_error)
+                .and_then(|file| read_header(&mut BufReader::new(file)));
+
+            match header {
+                Ok((header, _)) => Some(BackupInfo::new(&path, header)),
+                Err(err) => {
+                    warn!(target: LOG_TARGET, "Skipping unreadable backup {}: {:?}", path.display(), err);
+                    None
+                }
+            }
+        })
+        .collect();
+
+    backups.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    Ok(backups)
+}
+
+/// Automatic backups of `center_id` beyond the newest `retention` ones
+pub fn backups_to_prune(
+    backups: &[BackupInfo],
+    center_id: &str,
+    retention: u32,
+) -> Vec<BackupInfo> {
+    let mut automatic: Vec<&BackupInfo> = backups
+        .iter()
+        .filter(|backup| backup.automatic && backup.center_id == center_id)
+        .collect();
+
+    automatic.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+    automatic
+        .into_iter()
+        .skip(retention as usize)
+        .cloned()
+        .collect()
+}
+
+/// Time left until a scheduled backup is due, zero when it is due now
+pub fn next_backup_delay(
+    last_backup: Option<SystemTime>,
+    now: SystemTime,
+    interval: Duration,
+) -> Duration {
+    match last_backup {
+        Some(last_backup) => {
+            interval.saturating_sub(now.duration_since(last_backup).unwrap_or_default())
+        }
+        None => Duration::ZERO,
+    }
+}
+
+/// Replace the local database with the backup at `path` after checking it belongs to the local
+/// center, the current data is backed up first
+///
+/// The database is replaced in a single transaction, a failed restore leaves the current data
+/// as it was.
+pub async fn restore_backup(
+    path: PathBuf,
+    password: Option<String>,
+) -> Result<BackupInfo, BackupError> {
+    let store = shared_store().await?;
+
+    let Some(center_id) = store.center_id().await.unwrap_or_default() else {
+        return Err(BackupError::NotInitialized);
+    };
+
+    if matches!(store.instance_type().await, Ok(InstanceType::Slave)) {
+        return Err(BackupError::NotAllowedOnSlave);
+    }
+
+    let backup = fs::read(&path).map_err(file_error)?;
+
+    // Checked before the password so another center's backup is refused even without it
+    let (header, _) = read_header(&mut backup.as_slice())?;
+    if header.center_id != center_id {
+        warn!(target: LOG_TARGET, "Refusing backup of center {} in center {}", header.center_id, center_id);
+        return Err(BackupError::CenterMismatch);
+    }
+
+    let password = backup_password(&store, password).await?;
+    let (header, contents) = decode_backup(&backup, &password)?;
+
+    // Everything is parsed before any data is replaced
+    let mut tables = Vec::new();
+    for table in &contents.tables {
+        let pages = table
+            .pages
+            .iter()
+            .map(|page| sql::value(page).map_err(|_| BackupError::InvalidBackup))
+            .collect::<Result<Vec<sql::Value>, BackupError>>()?;
+
+        tables.push((table.name.as_str(), pages));
+    }
+
+    let safety_backup = create_backup(None, Some(password), false).await?;
+    info!(target: LOG_TARGET, "Current data backed up to {} before restoring", safety_backup.path);
+
+    let _lock = BACKUP_LOCK.lock().await;
+    let surreal = connect_as_root().await.map_err(database_error)?;
+
+    info!(target: LOG_TARGET, "Restoring backup of {} from {}", header.created_at, path.display());
+
+    let (current_tables, _) = read_schema(&surreal).await.map_err(database_error)?;
+    let mut statements: Vec<String> = current_tables
+        .iter()
+        .map(|table| format!("REMOVE TABLE {}", table))
+        .collect();
+    statements.extend(contents.definitions.iter().cloned());
+    statements.push("LET $restored_at = time::now()".to_string());
+
+    let mut pages: HashMap<String, sql::Value> = HashMap::new();
+    for (table, table_pages) in tables {
+        for records in table_pages {
+            let page = format!("page_{}", pages.len());
+            statements.push(format!(
+                "FOR $record IN ${} {{ CREATE $record.id CONTENT $record; }}",
+                page
+            ));
+            pages.insert(page, records);
+        }
+
+        debug!(target: LOG_TARGET, "Restoring {}", table);
+    }
+
+    // Events of restored records must not queue them for sync again
+    statements.push("DELETE sync WHERE created_at >= $restored_at".to_string());
+
+    surreal
+        .query(format!(
+            "BEGIN TRANSACTION;\n{};\nCOMMIT TRANSACTION;",
+            statements.join(";\n")
+        ))
+        .bind(pages)
+        .await
+        .and_then(|response| response.check())
+        .map_err(database_error)?;
+
+    // Changes from central made after the backup are pulled again
+    store
+        .set_sync_cursor(contents.sync_cursor)
+        .await
+        .map_err(|_| BackupError::StorageError)?;
+
+    info!(target: LOG_TARGET, "Restored backup from {}", path.display());
+    Ok(BackupInfo::new(&path, header))
+}
+
+/// Delete automatic backups beyond the retention of the settings
+async fn prune_backups(center_id: &str, settings: &BackupSettings) {
+    let backups = match list_backups().await {
+        Ok(backups) => backups,
+        Err(err) => {
+            warn!(target: LOG_TARGET, "Failed to list backups to prune: {:?}", err);
+            return;
+        }
+    };
+
+    for backup in backups_to_prune(&backups, center_id, settings.retention) {
+        debug!(target: LOG_TARGET, "Deleting old backup {}", backup.path);
+        if let Err(err) = fs::remove_file(&backup.path) {
+            warn!(target: LOG_TARGET, "Failed to delete old backup {}: {:?}", backup.path, err);
+        }
+    }
+}
+
+/// Modification time of the newest automatic backup of `center_id`
+async fn last_automatic_backup(center_id: &str) -> Option<SystemTime> {
+    list_backups()
+        .await
+        .ok()?
+        .iter()
+        .filter(|backup| backup.automatic && backup.center_id == center_id)
+        .filter_map(|backup| {
+            fs::metadata(&backup.path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+        .max()
+}
+
+/// Makes backups on the schedule of the backup settings
+pub struct BackupScheduler {
+    retry_policy: RetryPolicy,
+}
+
+impl BackupScheduler {
+    pub fn new() -> Self {
+        Self {
+            retry_policy: RetryPolicy {
+                base_delay: Duration::from_secs(60),
+                max_delay: SCHEDULE_CHECK_INTERVAL,
+                ..RetryPolicy::default()
+            },
+        }
+    }
+
+    pub async fn start_scheduling(&self, window: Window) {
+        let mut failures = 0;
+
+        loop {
+            let delay = self.run_due_backup(&window, &mut failures).await;
+
+            tokio::select! {
+                _ = sleep(delay) => {}
+                _ = SETTINGS_CHANGED.notified() => {
+                    debug!(target: LOG_TARGET, "Backup settings changed");
+                }
+            }
+        }
+    }
+
+    /// Make a backup if one is due, returns how long to wait before looking again
+    async fn run_due_backup(&self, window: &Window, failures: &mut u32) -> Duration {
+        let Ok(store) = GlobalKeyStore::shared().await else {
+            return SCHEDULE_CHECK_INTERVAL;
+        };
+
+        let settings = backup_settings(&store).await;
+        let Some(center_id) = store.center_id().await.unwrap_or_default() else {
+            return SCHEDULE_CHECK_INTERVAL;
+        };
+
+        if !settings.enabled {
+            return SCHEDULE_CHECK_INTERVAL;
+        }
+
+        let interval = Duration::from_secs(settings.interval_hours as u64 * 60 * 60);
+        let delay = next_backup_delay(
+            last_automatic_backup(&center_id).await,
+            SystemTime::now(),
+            interval,
+        );
+
+        if !delay.is_zero() {
+            return delay.min(SCHEDULE_CHECK_INTERVAL);
+        }
+
+        info!(target: LOG_TARGET, "Scheduled backup is due");
+        match create_backup(None, None, true).await {
+            Ok(backup) => {
+                *failures = 0;
+                window.emit("backup_completed", &backup).unwrap_or_default();
+                prune_backups(&center_id, &settings).await;
+                interval
+            }
+            Err(err) => {
+                *failures += 1;
+                let delay = self.retry_policy.delay(*failures);
+
+                error!(target: LOG_TARGET, "Scheduled backup failed {} time(s) in a row ({:?}), retrying in {:?}", failures, err, delay);
+                window
+                    .emit("backup_failed", format!("{:?}", err))
+                    .unwrap_or_default();
+                delay
+            }
+        }
+    }
+}
@@ -0,0 +1,148 @@
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use crate::backup::{
+        backups_to_prune, decode_backup, encode_backup, generate_salt, next_backup_delay,
+        read_header, BackupContents, BackupError, BackupHeader, BackupInfo, BackupTable,
+        BACKUP_VERSION,
+    };
+
+    fn header() -> BackupHeader {
+        BackupHeader {
+            version: BACKUP_VERSION,
+            center_id: "center:center_id".to_string(),
+            center_name: "Center".to_string(),
+            created_at: "2026-01-01T00:00:00Z".to_string(),
+            automatic: true,
+            salt: generate_salt().unwrap(),
+        }
+    }
+
+    fn contents() -> BackupContents {
+        BackupContents {
+            definitions: vec!["DEFINE TABLE student SCHEMALESS".to_string()],
+            tables: vec![BackupTable {
+                name: "student".to_string(),
+                pages: vec!["[{ id: student:one, name: 'One\nTwo' }]".to_string()],
+            }],
+            sync_cursor: 42,
+        }
+    }
+
+    fn info(center_id: &str, created_at: &str, automatic: bool) -> BackupInfo {
+        BackupInfo {
+            path: format!("backups/{}", created_at),
+            center_id: center_id.to_string(),
+            center_name: "Center".to_string(),
+            created_at: created_at.to_string(),
+            automatic,
+        }
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let backup = encode_backup(&header(), &contents(), "password").unwrap();
+        let (decoded_header, decoded_contents) = decode_backup(&backup, "password").unwrap();
+
+        assert_eq!(decoded_header.center_id, "center:center_id");
+        assert_eq!(decoded_contents, contents());
+    }
+
+    #[test]
+    fn test_read_header_without_password() {
+        let header = header();
+        let backup = encode_backup(&header, &contents(), "password").unwrap();
+
+        let (read, _) = read_header(&mut backup.as_slice()).unwrap();
+        assert_eq!(read, header);
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let backup = encode_backup(&header(), &contents(), "password").unwrap();
+
+        assert_eq!(
+            decode_backup(&backup, "wrong password"),
+            Err(BackupError::WrongPassword)
+        );
+    }
+
+    #[test]
+    fn test_changed_header() {
+        let backup = encode_backup(&header(), &contents(), "password").unwrap();
+
+        let position = backup
+            .windows(16)
+            .position(|window| window == b"center:center_id")
+            .unwrap();
+
+        // Same length and still valid JSON, only the authentication tag can tell
+        let mut tampered = backup.clone();
+        tampered[position..position + 16].copy_from_slice(b"center:other_ids");
+
+        assert_eq!(
+            decode_backup(&tampered, "password"),
+            Err(BackupError::WrongPassword)
+        );
+    }
+
+    #[test]
+    fn test_not_a_backup() {
+        assert_eq!(
+            decode_backup(b"SQLite format 3\0", "password"),
+            Err(BackupError::InvalidBackup)
+        );
+        assert_eq!(
+            decode_backup(b"", "password"),
+            Err(BackupError::InvalidBackup)
+        );
+    }
+
+    #[test]
+    fn test_newer_backup_version() {
+        let header = BackupHeader {
+            version: BACKUP_VERSION + 1,
+            ..header()
+        };
+        let backup = encode_backup(&header, &contents(), "password").unwrap();
+
+        assert_eq!(
+            read_header(&mut backup.as_slice()),
+            Err(BackupError::UnsupportedVersion)
+        );
+    }
+
+    #[test]
+    fn test_prune_oldest_automatic_backups() {
+        let backups = vec![
+            info("center:a", "2026-01-01T00:00:00Z", true),
+            info("center:a", "2026-01-03T00:00:00Z", true),
+            info("center:a", "2026-01-02T00:00:00Z", true),
+            info("center:a", "2025-12-01T00:00:00Z", false),
+            info("center:b", "2025-12-02T00:00:00Z", true),
+        ];
+
+        assert_eq!(
+            backups_to_prune(&backups, "center:a", 2),
+            vec![info("center:a", "2026-01-01T00:00:00Z", true)]
+        );
+        assert!(backups_to_prune(&backups, "center:a", 3).is_empty());
+    }
+
+    #[test]
+    fn test_next_backup_delay() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+
+        assert_eq!(next_backup_delay(None, now, day), Duration::ZERO);
+        assert_eq!(
+            next_backup_delay(Some(now - Duration::from_secs(60 * 60)), now, day),
+            day - Duration::from_secs(60 * 60)
+        );
+        assert_eq!(
+            next_backup_delay(Some(now - 2 * day), now, day),
+            Duration::ZERO
+        );
+    }
+}
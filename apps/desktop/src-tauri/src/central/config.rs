@@ -0,0 +1,111 @@
+use log::{debug, warn};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tokio::time::Duration;
+
+use crate::app::GlobalKeyStore;
+
+#[cfg(debug_assertions)]
+static DEFAULT_CENTRAL_API: &str = "http://127.0.0.1:4000";
+
+#[cfg(not(debug_assertions))]
+static DEFAULT_CENTRAL_API: &str = "https://magmooty-api.fly.dev";
+
+/// Time allowed for a whole request, uploads on slow connections included
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+static CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+static LOG_TARGET: &str = "Central API config";
+
+#[derive(Serialize, Deserialize, Debug, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum CentralApiConfigError {
+    InvalidUrl,
+    UnsupportedScheme,
+    /// Plain HTTP is only allowed for local mock servers
+    InsecureUrl,
+    InvalidCertificate,
+    ClientBuildError,
+    StorageError,
+}
+
+#[derive(Debug, Clone)]
+pub struct CentralApiConfig {
+    /// Base URL without a trailing slash
+    pub base_url: String,
+    pub timeout: Duration,
+    pub connect_timeout: Duration,
+    /// PEM certificate central has to present, system roots are not trusted when set
+    pub pinned_certificate: Option<String>,
+}
+
+impl CentralApiConfig {
+    pub fn new(base_url: &str) -> Result<Self, CentralApiConfigError> {
+        Ok(Self {
+            base_url: validate_central_api_url(base_url)?,
+            timeout: REQUEST_TIMEOUT,
+            connect_timeout: CONNECT_TIMEOUT,
+            pinned_certificate: None,
+        })
+    }
+
+    /// Build the config from the overrides stored in the global keys
+    pub async fn load() -> Result<Self, CentralApiConfigError> {
+        let store = GlobalKeyStore::shared()
+            .await
+            .map_err(|_| CentralApiConfigError::StorageError)?;
+
+        let base_url = get_central_api_url().await;
+        debug!(target: LOG_TARGET, "Using central API at {}", base_url);
+
+        let mut config = match Self::new(&base_url) {
+            Ok(config) => config,
+            Err(error) => {
+                // Overrides stored before set_global_key validated them can be invalid
+                warn!(target: LOG_TARGET, "Invalid central API override {}: {:?}", base_url, error);
+                Self::new(DEFAULT_CENTRAL_API)?
+            }
+        };
+
+        config.pinned_certificate = store
+            .central_api_certificate()
+            .await
+            .map_err(|_| CentralApiConfigError::StorageError)?
+            .filter(|certificate| !certificate.trim().is_empty());
+
+        Ok(config)
+    }
+}
+
+/// Central API URL in use, the stored override or the build default
+pub async fn get_central_api_url() -> String {
+    let url = match GlobalKeyStore::shared().await {
+        Ok(store) => store.central_api_url().await.unwrap_or_default(),
+        Err(_) => None,
+    };
+
+    url.filter(|url| !url.trim().is_empty())
+        .unwrap_or(DEFAULT_CENTRAL_API.to_string())
+}
+
+/// Check a central API base URL and normalize it
+pub fn validate_central_api_url(url: &str) -> Result<String, CentralApiConfigError> {
+    let url = Url::parse(url.trim()).map_err(|_| CentralApiConfigError::InvalidUrl)?;
+
+    if url.query().is_some() || url.fragment().is_some() || url.cannot_be_a_base() {
+        return Err(CentralApiConfigError::InvalidUrl);
+    }
+
+    match url.scheme() {
+        "https" => {}
+        "http" => match url.host_str() {
+            Some("localhost") | Some("127.0.0.1") | Some("[::1]") => {}
+            _ => return Err(CentralApiConfigError::InsecureUrl),
+        },
+        _ => return Err(CentralApiConfigError::UnsupportedScheme),
+    }
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
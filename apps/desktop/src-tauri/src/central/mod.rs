@@ -1,22 +1,30 @@
-use crate::sync::SyncEvent;
+use crate::sync::{PulledSyncEvent, SyncEvent};
 use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use jsonwebtoken::{Algorithm, EncodingKey, Header};
 use log::debug;
+use once_cell::sync::Lazy;
+use reqwest::{Certificate, Client, RequestBuilder};
 use rsa::pkcs1v15::SigningKey;
 use rsa::signature::SignatureEncoding;
 use rsa::{pkcs1::DecodeRsaPrivateKey, sha2::Sha256, signature::SignerMut, RsaPrivateKey};
 use serde::{Deserialize, Serialize};
-use tauri::api::http::{Body, ClientBuilder, HttpRequestBuilder, ResponseData};
-use tauri::http::header::HeaderMap;
+use specta::Type;
+use std::io::Write;
+use std::sync::Arc;
+use tokio::sync::RwLock;
 
-#[cfg(debug_assertions)]
-static CENTRAL_API: &str = "http://127.0.0.1:4000";
+mod config;
+mod test_central;
 
-#[cfg(not(debug_assertions))]
-static CENTRAL_API: &str = "https://central.magmooty.com";
+pub use config::*;
 
 static LOG_TARGET: &str = "Central API";
 
+/// Client shared by the syncer, rebuilt when the central API settings change
+static SHARED_CENTRAL_API: Lazy<RwLock<Option<Arc<CentralAPI>>>> = Lazy::new(|| RwLock::new(None));
+
 #[derive(Serialize, Deserialize, Debug)]
 struct CheckSyncAvailabilityPayload {
     pub center_id: String,
@@ -26,6 +34,35 @@ struct CheckSyncAvailabilityPayload {
 #[derive(Serialize, Deserialize, Debug)]
 struct UploadChunkPayload {
     chunk: Vec<SyncEvent>,
+    installation_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct PullChangesPayload {
+    after: i64,
+    limit: Option<i64>,
+    /// Central leaves out the changes this installation uploaded
+    installation_id: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct SendSigninCodePayload<'a> {
+    phone_number: &'a str,
+    channel: MessagingChannel,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Type)]
+#[serde(rename_all = "lowercase")]
+pub enum MessagingChannel {
+    WhatsApp,
+    Telegram,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PulledChanges {
+    pub changes: Vec<PulledSyncEvent>,
+    pub cursor: i64,
+    pub has_more: bool,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -45,6 +82,7 @@ pub enum CheckSyncAvailabilityError {
 #[serde(rename_all = "snake_case")]
 pub enum SyncUploadChunkError {
     SerializationError,
+    CompressionError,
     CenterNotFound,
     SignatureInvalid,
     Base64DecodeError,
@@ -55,9 +93,100 @@ pub enum SyncUploadChunkError {
     UnknownError,
 }
 
-pub struct CentralAPI {}
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncPullChangesError {
+    SerializationError,
+    CenterNotFound,
+    SignatureInvalid,
+    SignatureGenerationError,
+    NetworkError,
+    ResponseReadError,
+    UnknownError,
+}
+
+#[derive(Serialize, Deserialize, Debug, Type)]
+#[serde(rename_all = "snake_case")]
+pub enum SendSigninCodeError {
+    TargetNotOnWhatsApp,
+    WhatsAppError,
+    TargetNotOnTelegram,
+    TelegramError,
+    TelegramUnavailable,
+    InvalidSettings,
+    NetworkError,
+    ResponseReadError,
+    UnknownError,
+}
+
+pub struct CentralAPI {
+    base_url: String,
+    client: Client,
+}
 
 impl CentralAPI {
+    pub fn new(config: &CentralApiConfig) -> Result<Self, CentralApiConfigError> {
+        let mut builder = Client::builder()
+            .timeout(config.timeout)
+            .connect_timeout(config.connect_timeout);
+
+        if let Some(certificate) = &config.pinned_certificate {
+            // from_pem doesn't parse with rustls, a bundle without certificates would go unnoticed
+            let certificates = Certificate::from_pem_bundle(certificate.as_bytes())
+                .map_err(|_| CentralApiConfigError::InvalidCertificate)?;
+            if certificates.is_empty() {
+                return Err(CentralApiConfigError::InvalidCertificate);
+            }
+
+            builder = builder.tls_built_in_root_certs(false);
+            for certificate in certificates {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let client = builder
+            .build()
+            .map_err(|_| CentralApiConfigError::ClientBuildError)?;
+
+        Ok(Self {
+            base_url: config.base_url.clone(),
+            client,
+        })
+    }
+
+    /// Client built from the stored settings, created on first use
+    pub async fn shared() -> Result<Arc<Self>, CentralApiConfigError> {
+        if let Some(central) = SHARED_CENTRAL_API.read().await.as_ref() {
+            return Ok(central.clone());
+        }
+
+        let mut shared = SHARED_CENTRAL_API.write().await;
+
+        if let Some(central) = shared.as_ref() {
+            return Ok(central.clone());
+        }
+
+        let central = Arc::new(Self::new(&CentralApiConfig::load().await?)?);
+        *shared = Some(central.clone());
+
+        Ok(central)
+    }
+
+    /// Drop the shared client so the next request picks up changed settings
+    pub async fn reset_shared() {
+        debug!(target: LOG_TARGET, "Resetting shared client");
+        *SHARED_CENTRAL_API.write().await = None;
+    }
+
+    fn post(&self, path: &str) -> RequestBuilder {
+        self.client.post(format!("{}{}", self.base_url, path))
+    }
+
+    /// Read the JSON body, error statuses carry a JSON `status` as well
+    async fn read_json(response: reqwest::Response) -> Result<serde_json::Value, ()> {
+        response.json::<serde_json::Value>().await.map_err(|_| ())
+    }
+
     async fn generate_signature(
         center_id: String,
         private_key: String,
@@ -122,16 +251,15 @@ impl CentralAPI {
     }
 
     pub async fn sync_upload_chunk(
+        &self,
         events: &[SyncEvent],
         private_key: &String,
         center_id: &String,
+        installation_id: &String,
     ) -> Result<(), SyncUploadChunkError> {
-        let url = format!("{}/sync/upload_chunk", CENTRAL_API);
-        let client = ClientBuilder::new().build().unwrap();
-        let request = HttpRequestBuilder::new("POST", url).unwrap();
-
         let payload = UploadChunkPayload {
             chunk: events.to_vec(),
+            installation_id: installation_id.clone(),
         };
 
         let chunk = match serde_json::to_string(&payload) {
@@ -139,27 +267,38 @@ impl CentralAPI {
             Err(_) => Err(SyncUploadChunkError::SerializationError),
         }?;
 
+        // Central verifies the signature against the decompressed body
         let signature = Self::sign_chunk(&center_id, &chunk, &private_key).await?;
 
-        let mut headers = HeaderMap::new();
-
-        headers.append("Content-Type", "application/json".parse().unwrap());
-        headers.append("Signature", signature.parse().unwrap());
-        headers.append("Center-ID", center_id.parse().unwrap());
-
-        let request = request
-            .body(Body::Bytes(chunk.bytes().collect()))
-            .headers(headers);
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder
+            .write_all(chunk.as_bytes())
+            .map_err(|_| SyncUploadChunkError::CompressionError)?;
+        let body = encoder
+            .finish()
+            .map_err(|_| SyncUploadChunkError::CompressionError)?;
+
+        debug!(
+            target: LOG_TARGET,
+            "Compressed chunk from {} to {} bytes",
+            chunk.len(),
+            body.len()
+        );
 
         debug!(target: LOG_TARGET, "Sending request to Central API");
-        let response = client
-            .send(request)
+        let response = self
+            .post("/sync/upload_chunk")
+            .header("Content-Type", "application/json")
+            .header("Content-Encoding", "gzip")
+            .header("Signature", signature)
+            .header("Center-ID", center_id)
+            .body(body)
+            .send()
             .await
             .map_err(|_| SyncUploadChunkError::NetworkError)?;
 
         debug!(target: LOG_TARGET, "Parsing response");
-        let ResponseData { data, .. } = response
-            .read()
+        let data = Self::read_json(response)
             .await
             .map_err(|_| SyncUploadChunkError::ResponseReadError)?;
 
@@ -178,37 +317,120 @@ impl CentralAPI {
         }
     }
 
+    pub async fn sync_pull_changes(
+        &self,
+        after: i64,
+        limit: i64,
+        private_key: &String,
+        center_id: &String,
+        installation_id: &String,
+    ) -> Result<PulledChanges, SyncPullChangesError> {
+        let payload = serde_json::to_string(&PullChangesPayload {
+            after,
+            limit: Some(limit),
+            installation_id: installation_id.clone(),
+        })
+        .map_err(|_| SyncPullChangesError::SerializationError)?;
+
+        let signature = Self::sign_chunk(&center_id, &payload, &private_key)
+            .await
+            .map_err(|_| SyncPullChangesError::SignatureGenerationError)?;
+
+        debug!(target: LOG_TARGET, "Sending request to Central API");
+        let response = self
+            .post("/sync/pull_changes")
+            .header("Content-Type", "application/json")
+            .header("Signature", signature)
+            .header("Center-ID", center_id)
+            .body(payload)
+            .send()
+            .await
+            .map_err(|_| SyncPullChangesError::NetworkError)?;
+
+        debug!(target: LOG_TARGET, "Parsing response");
+        let data = Self::read_json(response)
+            .await
+            .map_err(|_| SyncPullChangesError::ResponseReadError)?;
+
+        let status = data
+            .get("status")
+            .ok_or(SyncPullChangesError::ResponseReadError)?;
+
+        match status {
+            serde_json::Value::String(status) => match status.as_str() {
+                "center_not_found" => Err(SyncPullChangesError::CenterNotFound),
+                "signature_invalid" => Err(SyncPullChangesError::SignatureInvalid),
+                "ok" => serde_json::from_value::<PulledChanges>(data)
+                    .map_err(|_| SyncPullChangesError::ResponseReadError),
+                _ => Err(SyncPullChangesError::UnknownError),
+            },
+            _ => Err(SyncPullChangesError::ResponseReadError),
+        }
+    }
+
+    /// Ask central to message a sign in code to `phone_number` through `channel`
+    pub async fn send_signin_code(
+        &self,
+        phone_number: &str,
+        channel: MessagingChannel,
+    ) -> Result<(), SendSigninCodeError> {
+        debug!(target: LOG_TARGET, "Sending request to Central API");
+        let response = self
+            .post("/auth/send_signin_code")
+            .json(&SendSigninCodePayload {
+                phone_number,
+                channel,
+            })
+            .send()
+            .await
+            .map_err(|_| SendSigninCodeError::NetworkError)?;
+
+        debug!(target: LOG_TARGET, "Parsing response");
+        let data = Self::read_json(response)
+            .await
+            .map_err(|_| SendSigninCodeError::ResponseReadError)?;
+
+        let status = data
+            .get("status")
+            .ok_or(SendSigninCodeError::ResponseReadError)?;
+
+        match status {
+            serde_json::Value::String(status) => match status.as_str() {
+                "message_sent" => Ok(()),
+                "target_not_on_whats_app" => Err(SendSigninCodeError::TargetNotOnWhatsApp),
+                "whats_app_error" => Err(SendSigninCodeError::WhatsAppError),
+                "target_not_on_telegram" => Err(SendSigninCodeError::TargetNotOnTelegram),
+                "telegram_error" => Err(SendSigninCodeError::TelegramError),
+                "telegram_unavailable" => Err(SendSigninCodeError::TelegramUnavailable),
+                _ => Err(SendSigninCodeError::UnknownError),
+            },
+            _ => Err(SendSigninCodeError::ResponseReadError),
+        }
+    }
+
     pub async fn check_sync_availability(
+        &self,
         center_id: &String,
         private_key: &String,
     ) -> Result<(), CheckSyncAvailabilityError> {
         debug!(target: LOG_TARGET, "Checking sync availability");
-        let url = format!("{}/sync/check_sync_availability", CENTRAL_API);
-
-        let client = ClientBuilder::new().build().unwrap();
-
-        let request = HttpRequestBuilder::new("POST", url).unwrap();
 
         debug!(target: LOG_TARGET, "Generating signature");
         let signature = Self::generate_signature(center_id.clone(), private_key.clone()).await?;
 
-        let request = request.body(Body::Json(
-            serde_json::to_value(CheckSyncAvailabilityPayload {
+        debug!(target: LOG_TARGET, "Sending request to Central API");
+        let response = self
+            .post("/sync/check_sync_availability")
+            .json(&CheckSyncAvailabilityPayload {
                 center_id: center_id.clone(),
                 signature,
             })
-            .unwrap(),
-        ));
-
-        debug!(target: LOG_TARGET, "Sending request to Central API");
-        let response = client
-            .send(request)
+            .send()
             .await
             .map_err(|_| CheckSyncAvailabilityError::NetworkError)?;
 
         debug!(target: LOG_TARGET, "Parsing response");
-        let ResponseData { data, .. } = response
-            .read()
+        let data = Self::read_json(response)
             .await
             .map_err(|_| CheckSyncAvailabilityError::ResponseReadError)?;
 
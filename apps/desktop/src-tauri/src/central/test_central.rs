@@ -0,0 +1,370 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::io::Read;
+    use std::sync::{Arc, Mutex};
+
+    use flate2::read::GzDecoder;
+    use serde_json::{json, Value};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::time::Duration;
+
+    use crate::central::{
+        validate_central_api_url, CentralAPI, CentralApiConfig, CentralApiConfigError,
+        CheckSyncAvailabilityError, MessagingChannel, SendSigninCodeError, SyncPullChangesError,
+        SyncUploadChunkError,
+    };
+
+    static CENTER_ID: &str = "center:center_id";
+
+    static INSTALLATION_ID: &str = "installation_id";
+
+    static PRIVATE_KEY: &str = "MIIEpQIBAAKCAQEA2X259apxTri5rV1mFJadvzc7YZZgdxuvQPoxBRTf6x2cAULCnx/UkQAwfNKxTp4pQ9thrLOwx5a8OZN74xpqQXzTjqn7OkQ8pm3qpmQ+av+XD2LLnRisMA2C//i8A3qeQc5CAyy+6gMPyMEz7ku718qlxZxAdqO1sjB0bIdaRHHXoTt2+MAv1bba6Q3aePZbj+NQY9okE/4wE3Y5iKS7C/4leXP1nhqAEnwio/sv3BgUF7bvYZhaGQ0sdBXBviDwYAixW4MtPGujZ+UWmZ4CNZdA7p18lPdSqMpgGd5oFOaTLifrQGCSCExgoqVcF5kSJ3pBpcNXGdvdZpA8CH7yXwIDAQABAoIBAQDD7vk1sWxMkCxuW4MYLyxD1J1BaDjVdPJopjy9KDYl2VHu7NbqhcF5M+N6wFEN03y3bg9Lh8JNvKUrdYuZZ/Wrs6nfj5ENx+WfxcwsRIja2hGbwdRPXafZzoJi2hF/TNr/y7I6q/f+V+3DXRLMrhu25xB6uy5z8z5AHlj0YOfYWdGX57oFt7AjQv57q51PKDTXas1pwidlPf8tqAZhTc+cz5y84e/l1nsDp1XL9xv+Qpy2IqtYSi7jCqI1YzcYpQZJPPWCbE43lfIMSz8CozFf0kQKfuJqPBWkij+BOx6bA08KA2tH8deOS/XoB1eOsorZUJja7PWnVP0iZdLzCeVZAoGBAPPX4xBBfuLNV3/6FAmtmnLThhtr4JFQlXh9MSfR5c2d1POWD1POG4ZvM5Bd03K/EYHg2oocguynMHq8bACMYllEmlH6BeRQJNhNOJV8cZNfC1gYMK/QKFqAh66wNDN1Ja9cATesIv62yLy0711RyEuPkFkZr+xEtoBnrQTIsT1LAoGBAORVg0jHQ8Xl6Nzo9QQJsNDrgZzJZB/vuslbeAVhhGRpNVDcvj1837Izv3Cm0UILP/mlR3G7Sv9mtkj8f8aquwSJh25mIk8g0bGEP8UZH3WuH7OfjtZu4n2M+PocpG9icFuUOQ3q3BMdvq2TfcbdUyFGySh3POxMj2OCzQtkONa9AoGAF+2EY5D5wYnC31UL6FM3x7LIAyLX24qb0EIAs9aeBUpKnkiIYoHkI5H/7le2qxjiv+rvpwPbORvC3xhkRL2B3R6lQgwVzeyrYOpa9hhLENoPw+pDxMzZWOAp8FNsG+yP8SBHIk5q9LG9Cv96SZ7/16JT2Npzb+ziN8F0sfZ7pfcCgYEAni3jUhLBD6KzS/6SZma0ODc+Rjh6BWnVE1MrdUbWKZ180vTpUb1lVpVkxQy7oK4cdryaHt7qGL61/x/1ANMb1gvUZ5WXpQuOWRTN/KPn2GV1DsG1eTW9784uWU5oV8VxIvAvCkYuiYusoaCwnIiM41ufVUotSWHMX9qoY4Ddo10CgYEAr2DMNA9UsWtaKWoU4xo8Y/e7dUVDPDIbCIqMrpJ+aScCg02S7L7bnAJcbYRnseabJA+ZmWFqX9hfEoeO0i8KU4IPUcMY/ujMqumLoGTjVseMxTJks7DzPZM9aTuNx8GdOZpyZoNED+uZB0m3/3MJ9CkOT7RcbaB6CKo6WVY2c7o=";
+
+    #[derive(Debug, Clone)]
+    struct MockRequest {
+        path: String,
+        headers: HashMap<String, String>,
+        body: Vec<u8>,
+    }
+
+    /// Minimal HTTP server answering every request with the same JSON response
+    struct MockCentral {
+        url: String,
+        requests: Arc<Mutex<Vec<MockRequest>>>,
+    }
+
+    async fn read_request(stream: &mut tokio::net::TcpStream) -> Option<MockRequest> {
+        let mut buffer = Vec::new();
+        let mut chunk = [0; 4096];
+
+        let header_end = loop {
+            let read = stream.read(&mut chunk).await.ok()?;
+
+            if read == 0 {
+                return None;
+            }
+
+            buffer.extend_from_slice(&chunk[..read]);
+
+            if let Some(index) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                break index + 4;
+            }
+        };
+
+        let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+        let mut lines = head.lines();
+        let path = lines.next()?.split(' ').nth(1)?.to_string();
+
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+
+        let content_length: usize = headers
+            .get("content-length")
+            .and_then(|length| length.parse().ok())
+            .unwrap_or(0);
+
+        let mut body = buffer[header_end..].to_vec();
+
+        while body.len() < content_length {
+            let read = stream.read(&mut chunk).await.ok()?;
+
+            if read == 0 {
+                break;
+            }
+
+            body.extend_from_slice(&chunk[..read]);
+        }
+
+        Some(MockRequest {
+            path,
+            headers,
+            body,
+        })
+    }
+
+    async fn mock_central(status: u16, response: Value) -> MockCentral {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let Some(request) = read_request(&mut stream).await else {
+                    continue;
+                };
+
+                recorded.lock().unwrap().push(request);
+
+                let body = response.to_string();
+                let response = format!(
+                    "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+
+                stream
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap_or_default();
+                stream.shutdown().await.unwrap_or_default();
+            }
+        });
+
+        MockCentral { url, requests }
+    }
+
+    fn central(url: &str) -> CentralAPI {
+        CentralAPI::new(&CentralApiConfig::new(url).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_check_sync_availability() {
+        let mock = mock_central(200, json!({ "status": "available" })).await;
+
+        let result = central(&mock.url)
+            .check_sync_availability(&CENTER_ID.to_string(), &PRIVATE_KEY.to_string())
+            .await;
+
+        assert!(result.is_ok());
+
+        let requests = mock.requests.lock().unwrap().clone();
+
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/sync/check_sync_availability");
+
+        let payload: Value = serde_json::from_slice(&requests[0].body).unwrap();
+
+        assert_eq!(payload["center_id"], CENTER_ID);
+    }
+
+    #[tokio::test]
+    async fn test_maps_error_statuses() {
+        let mock = mock_central(404, json!({ "status": "center_not_found" })).await;
+
+        let result = central(&mock.url)
+            .check_sync_availability(&CENTER_ID.to_string(), &PRIVATE_KEY.to_string())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(CheckSyncAvailabilityError::CenterNotFound)
+        ));
+
+        let result = central(&mock.url)
+            .sync_pull_changes(
+                0,
+                10,
+                &PRIVATE_KEY.to_string(),
+                &CENTER_ID.to_string(),
+                &INSTALLATION_ID.to_string(),
+            )
+            .await;
+
+        assert!(matches!(result, Err(SyncPullChangesError::CenterNotFound)));
+    }
+
+    #[tokio::test]
+    async fn test_upload_chunk_is_compressed() {
+        let mock = mock_central(200, json!({ "status": "accepted" })).await;
+
+        let result = central(&mock.url)
+            .sync_upload_chunk(
+                &[],
+                &PRIVATE_KEY.to_string(),
+                &CENTER_ID.to_string(),
+                &INSTALLATION_ID.to_string(),
+            )
+            .await;
+
+        assert!(result.is_ok());
+
+        let request = mock.requests.lock().unwrap()[0].clone();
+
+        assert_eq!(request.path, "/sync/upload_chunk");
+        assert_eq!(request.headers["content-encoding"], "gzip");
+        assert_eq!(request.headers["center-id"], CENTER_ID);
+
+        let mut body = String::new();
+        GzDecoder::new(request.body.as_slice())
+            .read_to_string(&mut body)
+            .unwrap();
+
+        assert_eq!(
+            serde_json::from_str::<Value>(&body).unwrap(),
+            json!({ "chunk": [], "installation_id": INSTALLATION_ID })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_changes() {
+        let mock = mock_central(
+            200,
+            json!({ "status": "ok", "changes": [], "cursor": 7, "has_more": false }),
+        )
+        .await;
+
+        let pulled = central(&mock.url)
+            .sync_pull_changes(
+                7,
+                10,
+                &PRIVATE_KEY.to_string(),
+                &CENTER_ID.to_string(),
+                &INSTALLATION_ID.to_string(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(pulled.cursor, 7);
+        assert!(!pulled.has_more);
+
+        let request = mock.requests.lock().unwrap()[0].clone();
+        let payload: Value = serde_json::from_slice(&request.body).unwrap();
+
+        assert_eq!(
+            payload,
+            json!({ "after": 7, "limit": 10, "installation_id": INSTALLATION_ID })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_signin_code() {
+        let mock = mock_central(200, json!({ "status": "message_sent" })).await;
+
+        let result = central(&mock.url)
+            .send_signin_code("+201096707442", MessagingChannel::WhatsApp)
+            .await;
+
+        assert!(result.is_ok());
+
+        let request = mock.requests.lock().unwrap()[0].clone();
+        let payload: Value = serde_json::from_slice(&request.body).unwrap();
+
+        assert_eq!(request.path, "/auth/send_signin_code");
+        assert_eq!(
+            payload,
+            json!({ "phone_number": "+201096707442", "channel": "whatsapp" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_signin_code_error_statuses() {
+        let mock = mock_central(400, json!({ "status": "target_not_on_whats_app" })).await;
+
+        let result = central(&mock.url)
+            .send_signin_code("+201096707442", MessagingChannel::WhatsApp)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SendSigninCodeError::TargetNotOnWhatsApp)
+        ));
+
+        let mock = mock_central(503, json!({ "status": "telegram_unavailable" })).await;
+
+        let result = central(&mock.url)
+            .send_signin_code("+201096707442", MessagingChannel::Telegram)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(SendSigninCodeError::TelegramUnavailable)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_central_is_a_network_error() {
+        // Bind then drop a listener to get a port nobody listens on
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let result = central(&url)
+            .sync_upload_chunk(
+                &[],
+                &PRIVATE_KEY.to_string(),
+                &CENTER_ID.to_string(),
+                &INSTALLATION_ID.to_string(),
+            )
+            .await;
+
+        assert!(matches!(result, Err(SyncUploadChunkError::NetworkError)));
+    }
+
+    #[tokio::test]
+    async fn test_slow_central_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        // Accept connections without ever answering
+        tokio::spawn(async move {
+            let mut streams = Vec::new();
+
+            while let Ok((stream, _)) = listener.accept().await {
+                streams.push(stream);
+            }
+        });
+
+        let mut config = CentralApiConfig::new(&url).unwrap();
+        config.timeout = Duration::from_millis(200);
+
+        let result = CentralAPI::new(&config)
+            .unwrap()
+            .check_sync_availability(&CENTER_ID.to_string(), &PRIVATE_KEY.to_string())
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(CheckSyncAvailabilityError::NetworkError)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_invalid_pinned_certificate() {
+        let mut config = CentralApiConfig::new("https://central.magmooty.com").unwrap();
+        config.pinned_certificate = Some("not a certificate".to_string());
+
+        assert!(matches!(
+            CentralAPI::new(&config),
+            Err(CentralApiConfigError::InvalidCertificate)
+        ));
+    }
+
+    #[test]
+    fn test_validate_central_api_url() {
+        assert_eq!(
+            validate_central_api_url("https://staging.magmooty.com/").unwrap(),
+            "https://staging.magmooty.com"
+        );
+        assert_eq!(
+            validate_central_api_url("http://localhost:4000").unwrap(),
+            "http://localhost:4000"
+        );
+        assert_eq!(
+            validate_central_api_url("http://127.0.0.1:4000/api/").unwrap(),
+            "http://127.0.0.1:4000/api"
+        );
+
+        assert!(matches!(
+            validate_central_api_url("http://central.magmooty.com"),
+            Err(CentralApiConfigError::InsecureUrl)
+        ));
+        assert!(matches!(
+            validate_central_api_url("ftp://central.magmooty.com"),
+            Err(CentralApiConfigError::UnsupportedScheme)
+        ));
+        assert!(matches!(
+            validate_central_api_url("https://central.magmooty.com?debug=true"),
+            Err(CentralApiConfigError::InvalidUrl)
+        ));
+        assert!(matches!(
+            validate_central_api_url("central"),
+            Err(CentralApiConfigError::InvalidUrl)
+        ));
+    }
+}
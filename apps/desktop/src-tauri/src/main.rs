@@ -1,11 +1,16 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use std::collections::HashMap;
 use std::sync::Arc;
 
+use app::{GlobalKey, GlobalKeyStore};
+use backup::BackupScheduler;
+use central::CentralAPI;
 use log::info;
 use panic_handler::initialize_graceful_panic_handler;
 use process_killer::kill_hanging_sidecars;
+use replication::Replicator;
 use simple_logger;
 use sync::Syncer;
 use tauri::{api::process::CommandChild, Manager};
@@ -16,11 +21,16 @@ use specta::collect_types;
 use tauri_specta::ts;
 
 mod app;
+mod backup;
 mod central;
 mod network_discovery;
+mod pairing;
 mod panic_handler;
 mod pdf;
+mod ports;
 mod process_killer;
+mod replication;
+mod sidecar;
 mod surreal_sidecar;
 mod sync;
 mod whatsapp_sidecar;
@@ -30,8 +40,10 @@ use whatsapp_sidecar::run_whatsapp_sidecar;
 
 static LOG_TARGET: &str = "main";
 
-static SIDECARS: once_cell::sync::Lazy<Arc<std::sync::Mutex<Vec<CommandChild>>>> =
-    once_cell::sync::Lazy::new(|| Arc::new(std::sync::Mutex::new(Vec::new())));
+/// Running sidecar processes, by sidecar name
+static SIDECARS: once_cell::sync::Lazy<
+    Arc<std::sync::Mutex<HashMap<&'static str, CommandChild>>>,
+> = once_cell::sync::Lazy::new(|| Arc::new(std::sync::Mutex::new(HashMap::new())));
 
 #[cfg(debug_assertions)]
 fn generate_typescript_bindings() {
@@ -39,12 +51,30 @@ fn generate_typescript_bindings() {
         collect_types![
             app::set_global_key,
             app::get_global_key,
+            app::initialize_center,
             app::generate_key_pair,
             app::discover_network,
             app::get_root_database_credentials,
+            app::rotate_root_database_credentials,
             app::open_splash_screen,
             app::close_splash_screen,
             app::generate_report,
+            app::sync_now,
+            app::get_sync_status,
+            app::list_sync_history,
+            app::get_central_api_url,
+            app::set_central_api_url,
+            app::send_signin_code,
+            app::request_pairing,
+            app::confirm_pairing,
+            app::reject_pairing,
+            app::get_sidecar_statuses,
+            app::get_service_ports,
+            app::get_backup_settings,
+            app::set_backup_settings,
+            app::create_backup,
+            app::list_backups,
+            app::restore_backup,
         ],
         "../src/lib/bindings.ts",
     )
@@ -55,9 +85,6 @@ fn generate_typescript_bindings() {
 async fn main() {
     simple_logger::init_with_level(log::Level::Debug).unwrap();
 
-    info!(target: LOG_TARGET, "Killing hanging sidecars");
-    kill_hanging_sidecars();
-
     info!(target: LOG_TARGET, "Initializing graceful panic handler");
     initialize_graceful_panic_handler();
 
@@ -68,9 +95,36 @@ async fn main() {
         generate_typescript_bindings();
     }
 
+    info!(target: LOG_TARGET, "Opening global keys");
+    let global_keys = GlobalKeyStore::shared()
+        .await
+        .expect("Failed to open global keys");
+
+    info!(target: LOG_TARGET, "Killing hanging sidecars");
+    let stored_ports = ports::stored_service_ports(&global_keys).await;
+    kill_hanging_sidecars(&stored_ports);
+
+    info!(target: LOG_TARGET, "Resolving service ports");
+    ports::resolve_service_ports(&global_keys, stored_ports).await;
+
+    let mut global_key_changes = global_keys.subscribe();
+    tokio::spawn(async move {
+        while let Ok(change) = global_key_changes.recv().await {
+            if matches!(
+                change.key,
+                GlobalKey::CentralApiUrl | GlobalKey::CentralApiCertificate
+            ) {
+                CentralAPI::reset_shared().await;
+            }
+        }
+    });
+
     info!(target: LOG_TARGET, "Initializing network discovery UDP transceiver");
     tokio::spawn(network_discovery::start_network_discovery_receiver());
 
+    info!(target: LOG_TARGET, "Advertising over mDNS");
+    tokio::spawn(network_discovery::start_mdns_advertisement());
+
     info!(target: LOG_TARGET, "Running local WhatsApp API");
     run_whatsapp_sidecar().await;
 
@@ -80,19 +134,58 @@ async fn main() {
     // Run App
     info!(target: LOG_TARGET, "Running main application window");
     tauri::Builder::default()
+        .manage(global_keys)
         .invoke_handler(tauri::generate_handler![
             app::set_global_key,
             app::get_global_key,
+            app::initialize_center,
             app::generate_key_pair,
             app::discover_network,
             app::get_root_database_credentials,
+            app::rotate_root_database_credentials,
             app::open_splash_screen,
             app::close_splash_screen,
             app::generate_report,
+            app::sync_now,
+            app::get_sync_status,
+            app::list_sync_history,
+            app::get_central_api_url,
+            app::set_central_api_url,
+            app::send_signin_code,
+            app::request_pairing,
+            app::confirm_pairing,
+            app::reject_pairing,
+            app::get_sidecar_statuses,
+            app::get_service_ports,
+            app::get_backup_settings,
+            app::set_backup_settings,
+            app::create_backup,
+            app::list_backups,
+            app::restore_backup,
         ])
         .setup(|app| {
             let window = app.get_window("main").expect("Main window not found");
 
+            tokio::spawn(sidecar::emit_sidecar_statuses(window.clone()));
+
+            tokio::spawn(pairing::start_pairing_listener(window.clone()));
+
+            tokio::spawn(replication::start_replication_listener());
+
+            let replication_window = window.clone();
+            tokio::spawn(async move {
+                info!(target: LOG_TARGET, "Running replicator");
+                let replicator = Replicator::new();
+                replicator.start_replicating(replication_window).await;
+            });
+
+            let backup_window = window.clone();
+            tokio::spawn(async move {
+                info!(target: LOG_TARGET, "Running backup scheduler");
+                let scheduler = BackupScheduler::new();
+                scheduler.start_scheduling(backup_window).await;
+            });
+
             tokio::spawn(async move {
                 info!(target: LOG_TARGET, "Running syncer");
                 let syncer = Syncer::new();
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+
+use log::{debug, error, info, warn};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use once_cell::sync::Lazy;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::rngs::OsRng;
+use tokio::sync::mpsc::Sender;
+use tokio::time::{Duration, Instant};
+
+use super::{get_current_instance_info, InstanceType, NetworkInstanceInfo};
+use crate::app::{GlobalKey, GlobalKeyStore};
+use crate::ports::{service_ports, ServicePorts};
+
+static LOG_TARGET: &str = "mDNS";
+
+pub static MDNS_SERVICE_TYPE: &str = "_magmooty._tcp.local.";
+
+/// mDNS daemon shared by the advertisement and browsing, `None` when it failed to start
+static MDNS_DAEMON: Lazy<Option<ServiceDaemon>> = Lazy::new(|| match ServiceDaemon::new() {
+    Ok(daemon) => Some(daemon),
+    Err(err) => {
+        error!(target: LOG_TARGET, "Failed to start mDNS daemon: {:?}", err);
+        None
+    }
+});
+
+/// Name of this instance on the network, stable for the app run
+static INSTANCE_NAME: Lazy<String> = Lazy::new(|| {
+    format!(
+        "magmooty-{}",
+        Alphanumeric.sample_string(&mut OsRng, 8).to_lowercase()
+    )
+});
+
+/// TXT records of the advertised service, the center public key doesn't fit in a TXT record
+/// so mDNS results are verified with a discovery request sent to the advertised address
+pub fn txt_properties(info: &NetworkInstanceInfo) -> HashMap<String, String> {
+    let instance_type = match info.instance_type {
+        InstanceType::Master => "master",
+        InstanceType::Slave => "slave",
+        InstanceType::Uninitialized => "uninitialized",
+    };
+
+    let mut properties = HashMap::from([
+        ("center_name".to_string(), info.center_name.clone()),
+        ("version".to_string(), info.version.clone()),
+        ("instance_type".to_string(), instance_type.to_string()),
+        (
+            "local_center_initialized".to_string(),
+            info.local_center_initialized.to_string(),
+        ),
+    ]);
+
+    if let Some(center_id) = &info.center_id {
+        properties.insert("center_id".to_string(), center_id.clone());
+    }
+
+    for (key, port) in [
+        ("discovery_port", info.ports.discovery),
+        ("pairing_port", info.ports.pairing),
+        ("replication_port", info.ports.replication),
+    ] {
+        properties.insert(key.to_string(), port.to_string());
+    }
+
+    properties
+}
+
+pub fn instance_info_from_txt(
+    properties: &HashMap<String, String>,
+    ip_addresses: Vec<Ipv4Addr>,
+) -> Option<NetworkInstanceInfo> {
+    let instance_type = match properties.get("instance_type")?.as_str() {
+        "master" => InstanceType::Master,
+        "slave" => InstanceType::Slave,
+        _ => InstanceType::Uninitialized,
+    };
+
+    let port = |key: &str, default: u16| {
+        properties
+            .get(key)
+            .and_then(|port| port.parse().ok())
+            .unwrap_or(default)
+    };
+
+    let defaults = ServicePorts::default();
+    let ports = ServicePorts {
+        discovery: port("discovery_port", defaults.discovery),
+        pairing: port("pairing_port", defaults.pairing),
+        replication: port("replication_port", defaults.replication),
+        ..defaults
+    };
+
+    Some(NetworkInstanceInfo {
+        center_id: properties.get("center_id").cloned(),
+        center_name: properties.get("center_name").cloned().unwrap_or_default(),
+        version: properties.get("version").cloned().unwrap_or_default(),
+        instance_type,
+        local_center_initialized: properties
+            .get("local_center_initialized")
+            .map(|initialized| initialized == "true")
+            .unwrap_or(false),
+        ip_addresses,
+        public_key: None,
+        signed: false,
+        known_center: false,
+        latency_ms: None,
+        ports,
+    })
+}
+
+async fn register_service(daemon: &ServiceDaemon) {
+    let info = get_current_instance_info().await;
+    let host_name = format!("{}.local.", INSTANCE_NAME.as_str());
+
+    let service = ServiceInfo::new(
+        MDNS_SERVICE_TYPE,
+        INSTANCE_NAME.as_str(),
+        &host_name,
+        "",
+        service_ports().pairing,
+        txt_properties(&info),
+    )
+    .map(|service| service.enable_addr_auto());
+
+    match service.and_then(|service| daemon.register(service)) {
+        Ok(_) => {
+            info!(target: LOG_TARGET, "Advertising {} as {}", MDNS_SERVICE_TYPE, INSTANCE_NAME.as_str())
+        }
+        Err(err) => error!(target: LOG_TARGET, "Failed to advertise mDNS service: {:?}", err),
+    }
+}
+
+/// Advertise this instance over mDNS and keep the TXT records in line with the global keys
+pub async fn start_mdns_advertisement() {
+    let Some(daemon) = MDNS_DAEMON.as_ref() else {
+        return;
+    };
+
+    register_service(daemon).await;
+
+    let Ok(store) = GlobalKeyStore::shared().await else {
+        return;
+    };
+
+    let mut changes = store.subscribe();
+
+    while let Ok(change) = changes.recv().await {
+        if matches!(
+            change.key,
+            GlobalKey::CenterId | GlobalKey::CenterName | GlobalKey::InstanceType
+        ) {
+            debug!(target: LOG_TARGET, "Updating advertised service after {:?} changed", change.key);
+            register_service(daemon).await;
+        }
+    }
+}
+
+/// Send services resolved over mDNS until `deadline`, other instances advertising
+/// `_magmooty._tcp` included
+pub fn browse_mdns(deadline: Instant, resolved: Sender<(Vec<Ipv4Addr>, HashMap<String, String>)>) {
+    let Some(daemon) = MDNS_DAEMON.as_ref() else {
+        return;
+    };
+
+    let receiver = match daemon.browse(MDNS_SERVICE_TYPE) {
+        Ok(receiver) => receiver,
+        Err(err) => {
+            warn!(target: LOG_TARGET, "Failed to browse mDNS services: {:?}", err);
+            return;
+        }
+    };
+
+    tokio::task::spawn_blocking(move || {
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining == Duration::ZERO {
+                break;
+            }
+
+            let Ok(event) = receiver.recv_timeout(remaining) else {
+                break;
+            };
+
+            if let ServiceEvent::ServiceResolved(service) = event {
+                if service.get_fullname().starts_with(INSTANCE_NAME.as_str()) {
+                    continue;
+                }
+
+                debug!(target: LOG_TARGET, "Resolved {}", service.get_fullname());
+                let ip_addresses = service
+                    .get_addresses()
+                    .iter()
+                    .filter_map(|ip| match ip {
+                        IpAddr::V4(ip) => Some(*ip),
+                        IpAddr::V6(_) => None,
+                    })
+                    .collect();
+
+                let properties = service
+                    .get_properties()
+                    .iter()
+                    .map(|property| (property.key().to_string(), property.val_str().to_string()))
+                    .collect();
+
+                if resolved.blocking_send((ip_addresses, properties)).is_err() {
+                    break;
+                }
+            }
+        }
+
+        daemon.stop_browse(MDNS_SERVICE_TYPE).unwrap_or_default();
+    });
+}
@@ -1,9 +1,10 @@
-use futures::future::join_all;
 use log::{debug, info, warn};
 use pnet::datalink;
+use rand::distributions::{Alphanumeric, DistString};
+use rand::rngs::OsRng;
 use serde::{Deserialize, Serialize};
 use specta::Type;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::net::IpAddr;
 use std::time::Duration;
 use std::{
@@ -11,12 +12,32 @@ use std::{
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
 };
 use tokio::net::UdpSocket;
-use tokio::time::timeout;
+use tokio::sync::mpsc;
+use tokio::time::{timeout, Instant};
 
-use crate::app::{get_global_key, GlobalKey};
+use crate::app::{GlobalKey, GlobalKeyStore};
+use crate::pairing::{sign_with_center_key, verify_center_signature};
+use crate::ports::{service_ports, ServicePorts, DEFAULT_DISCOVERY_PORT};
+
+mod mdns;
+mod protocol;
+mod test_discovery;
+mod test_mdns;
+mod test_protocol;
+
+pub use mdns::*;
+pub use protocol::*;
 
 static LOG_TARGET: &str = "Network discovery";
 
+/// How long replies are collected for
+static DISCOVERY_WINDOW: Duration = Duration::from_millis(1500);
+
+/// Broadcasts sent per interface, UDP packets get lost on busy networks
+static DISCOVERY_ATTEMPTS: u32 = 3;
+
+static DISCOVERY_RETRY_INTERVAL: Duration = Duration::from_millis(400);
+
 #[derive(Debug, Serialize, Deserialize, Type)]
 #[serde(rename_all = "snake_case")]
 pub enum InstanceType {
@@ -27,24 +48,59 @@ pub enum InstanceType {
 
 #[derive(Debug, Serialize, Deserialize, Type)]
 pub struct NetworkInstanceInfo {
+    #[serde(default)]
+    pub center_id: Option<String>,
     pub center_name: String,
     pub version: String,
     pub instance_type: InstanceType,
     pub local_center_initialized: bool,
     pub ip_addresses: Vec<Ipv4Addr>,
+    /// Center public key the reply is signed with
+    #[serde(default)]
+    pub public_key: Option<String>,
+    /// Set by the discovering side when the reply signature matches `public_key`, anyone can
+    /// sign with a key of their own so it doesn't tell which center replied
+    #[serde(default)]
+    pub signed: bool,
+    /// Set by the discovering side when the reply is signed with the key of the local center,
+    /// the only proof the reply comes from that center
+    #[serde(default)]
+    pub known_center: bool,
+    /// Set by the discovering side, time between the broadcast and the reply
+    #[serde(default)]
+    pub latency_ms: Option<u32>,
+    /// Ports the instance runs its services on, older versions only use the defaults
+    #[serde(default)]
+    pub ports: ServicePorts,
 }
 
 async fn get_current_instance_info() -> NetworkInstanceInfo {
-    let center_name = get_global_key(GlobalKey::CenterName).await;
+    let store = GlobalKeyStore::shared().await.ok();
+
+    let center_id = match &store {
+        Some(store) => store.center_id().await.unwrap_or_default(),
+        None => None,
+    };
+
+    let center_name = match &store {
+        Some(store) => store.center_name().await.unwrap_or_default(),
+        None => None,
+    };
 
     let version = env!("CARGO_PKG_VERSION").to_string();
 
-    let instance_type = get_global_key(GlobalKey::InstanceType).await;
-    let instance_type = instance_type
-        .map(|instance_type| {
-            serde_json::from_str(&instance_type).unwrap_or(InstanceType::Uninitialized)
-        })
-        .unwrap_or(InstanceType::Uninitialized);
+    let instance_type = match &store {
+        Some(store) => store
+            .instance_type()
+            .await
+            .unwrap_or(InstanceType::Uninitialized),
+        None => InstanceType::Uninitialized,
+    };
+
+    let public_key = match &store {
+        Some(store) => store.public_key().await.unwrap_or_default(),
+        None => None,
+    };
 
     let local_center_initialized = center_name.is_some();
 
@@ -55,17 +111,79 @@ async fn get_current_instance_info() -> NetworkInstanceInfo {
         .collect();
 
     NetworkInstanceInfo {
+        center_id,
         center_name: center_name.unwrap_or("".to_string()),
         version,
         instance_type: instance_type,
         local_center_initialized,
         ip_addresses,
+        public_key,
+        signed: false,
+        known_center: false,
+        latency_ms: None,
+        ports: service_ports(),
+    }
+}
+
+/// Reply to a discovery request, signed when the local center has a key pair
+async fn build_discovery_reply(nonce: String) -> DiscoveryReply {
+    debug!(target: LOG_TARGET, "Fetching current instance metadata");
+    let info = serde_json::to_string(&get_current_instance_info().await).unwrap_or_default();
+
+    let private_key = match GlobalKeyStore::shared().await {
+        Ok(store) => store.private_key().await.unwrap_or_default(),
+        Err(_) => None,
+    };
+
+    let signature = private_key.and_then(|private_key| {
+        sign_with_center_key(&DiscoveryReply::signed_bytes(&nonce, &info), &private_key).ok()
+    });
+
+    DiscoveryReply {
+        nonce,
+        info,
+        signature,
     }
 }
 
+/// Check a reply and read the instance info from it along with the nonce it answers
+async fn read_discovery_reply(packet: &[u8]) -> Option<(String, NetworkInstanceInfo)> {
+    let reply: DiscoveryReply = match decode_packet(packet) {
+        Ok(reply) => reply,
+        Err(error) => {
+            warn!(target: LOG_TARGET, "Dropping discovery reply: {:?}", error);
+            return None;
+        }
+    };
+
+    let mut info: NetworkInstanceInfo = serde_json::from_str(&reply.info).ok()?;
+
+    info.signed = match (&reply.signature, &info.public_key) {
+        (Some(signature), Some(public_key)) => verify_center_signature(
+            &DiscoveryReply::signed_bytes(&reply.nonce, &reply.info),
+            signature,
+            public_key,
+        ),
+        _ => false,
+    };
+
+    if info.signed {
+        let local_public_key = match GlobalKeyStore::shared().await {
+            Ok(store) => store.public_key().await.unwrap_or_default(),
+            Err(_) => None,
+        };
+
+        info.known_center = local_public_key.is_some() && local_public_key == info.public_key;
+    }
+
+    Some((reply.nonce, info))
+}
+
 pub async fn start_network_discovery_receiver() {
-    info!(target: LOG_TARGET, "Binding UDP listener to 0.0.0.0:5005");
-    let local_addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 5005));
+    let port = service_ports().discovery;
+
+    info!(target: LOG_TARGET, "Binding UDP listener to 0.0.0.0:{port}");
+    let local_addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port));
 
     info!(target: LOG_TARGET, "Initializing socket");
     let socket = UdpSocket::bind(local_addr).await.unwrap();
@@ -73,28 +191,35 @@ pub async fn start_network_discovery_receiver() {
     info!(target: LOG_TARGET, "Enabling broadcasting on socket");
     socket.set_broadcast(true).unwrap();
 
-    let mut buf = [0; 1024];
+    let mut buf = [0; MAX_PACKET_SIZE];
 
     loop {
         info!(target: LOG_TARGET, "Waiting for UDP packets");
-        let (len, addr) = socket.recv_from(&mut buf).await.unwrap();
+        let (len, addr) = match socket.recv_from(&mut buf).await {
+            Ok(received) => received,
+            Err(error) => {
+                warn!(target: LOG_TARGET, "Failed to receive UDP packet: {error}");
+                continue;
+            }
+        };
 
         info!(target: LOG_TARGET, "Parsing received UDP packet from {addr}");
-        let msg = String::from_utf8_lossy(&buf[..len]);
-
-        info!(target: LOG_TARGET, "Got UDP packet from {addr}: {msg}");
-
-        debug!(target: LOG_TARGET, "Fetching current instance metadata");
-        let current_instance_info = get_current_instance_info().await;
+        let request: DiscoveryRequest = match decode_packet(&buf[..len]) {
+            Ok(request) => request,
+            Err(error) => {
+                // Unknown senders and future protocol versions get no reply
+                warn!(target: LOG_TARGET, "Ignoring UDP packet from {addr}: {:?}", error);
+                continue;
+            }
+        };
 
-        debug!(target: LOG_TARGET, "Serializing current instance metadata into JSON");
-        let current_instance_info = serde_json::to_string(&current_instance_info).unwrap();
+        let reply = build_discovery_reply(request.nonce).await;
 
         info!(target: LOG_TARGET, "Replying to {addr} with current metadata");
         socket
-            .send_to(current_instance_info.as_bytes(), addr)
+            .send_to(&encode_packet(&reply), addr)
             .await
-            .unwrap();
+            .unwrap_or_default();
     }
 }
 
@@ -151,6 +276,114 @@ fn filter_network_instances(
         .collect()
 }
 
+/// Identity used to merge replies of the same instance received on several interfaces or
+/// attempts, instances of the same center are merged as well
+///
+/// Any device can claim a center ID, so replies are only merged with replies signed with the
+/// same key and unsigned replies are kept apart from signed ones.
+fn instance_key(instance: &NetworkInstanceInfo) -> String {
+    match &instance.center_id {
+        Some(center_id) => match (&instance.public_key, instance.signed) {
+            (Some(public_key), true) => format!("center:{}:{}", center_id, public_key),
+            _ => format!("center:{}:unsigned", center_id),
+        },
+        None => {
+            let mut ip_addresses = instance.ip_addresses.clone();
+            ip_addresses.sort();
+            format!("instance:{:?}", ip_addresses)
+        }
+    }
+}
+
+/// Whether `candidate` should replace `current` for the same key, masters go first as they
+/// are the ones other devices pair with, then replies of the local center, then signed
+/// replies, then faster replies
+fn is_preferred(candidate: &NetworkInstanceInfo, current: &NetworkInstanceInfo) -> bool {
+    let candidate_master = matches!(candidate.instance_type, InstanceType::Master);
+    let current_master = matches!(current.instance_type, InstanceType::Master);
+
+    if candidate_master != current_master {
+        return candidate_master;
+    }
+
+    if candidate.known_center != current.known_center {
+        return candidate.known_center;
+    }
+
+    if candidate.signed != current.signed {
+        return candidate.signed;
+    }
+
+    candidate.latency_ms.unwrap_or(u32::MAX) < current.latency_ms.unwrap_or(u32::MAX)
+}
+
+fn deduplicate_instances(instances: Vec<NetworkInstanceInfo>) -> Vec<NetworkInstanceInfo> {
+    let mut keys: Vec<String> = Vec::new();
+    let mut unique: HashMap<String, NetworkInstanceInfo> = HashMap::new();
+
+    for instance in instances {
+        let key = instance_key(&instance);
+
+        match unique.get(&key) {
+            Some(current) if !is_preferred(&instance, current) => {}
+            Some(_) => {
+                unique.insert(key, instance);
+            }
+            None => {
+                keys.push(key.clone());
+                unique.insert(key, instance);
+            }
+        }
+    }
+
+    // Keep the order instances were first seen in
+    keys.into_iter()
+        .filter_map(|key| unique.remove(&key))
+        .collect()
+}
+
+/// Replication port of the master of the local center, only taken from replies signed with the
+/// key of the local center
+fn paired_master_port(instances: &[NetworkInstanceInfo]) -> Option<u16> {
+    instances
+        .iter()
+        .find(|instance| {
+            instance.known_center && matches!(instance.instance_type, InstanceType::Master)
+        })
+        .map(|instance| instance.ports.replication)
+}
+
+/// Store the replication port the master advertises when the local center is a paired slave,
+/// the master falls back to another port when its own is taken
+async fn refresh_master_port(instances: &[NetworkInstanceInfo]) {
+    let Some(port) = paired_master_port(instances) else {
+        return;
+    };
+
+    let Ok(store) = GlobalKeyStore::shared().await else {
+        return;
+    };
+
+    if !matches!(store.instance_type().await, Ok(InstanceType::Slave)) {
+        return;
+    }
+
+    let port = port.to_string();
+    let stored_port = store
+        .get(GlobalKey::MasterReplicationPort)
+        .await
+        .unwrap_or_default();
+
+    if stored_port.as_deref() == Some(port.as_str()) {
+        return;
+    }
+
+    info!(target: LOG_TARGET, "Master replication moved to port {}", port);
+    if let Err(err) = store.set(GlobalKey::MasterReplicationPort, port).await {
+        warn!(target: LOG_TARGET, "Failed to store the master replication port: {:?}", err);
+    }
+}
+
 pub async fn discover_network() -> Result<Vec<NetworkInstanceInfo>, io::Error> {
     info!(target: LOG_TARGET, "Starting network discovery");
 
@@ -162,65 +395,167 @@ pub async fn discover_network() -> Result<Vec<NetworkInstanceInfo>, io::Error> {
 
     let ip_addresses = get_current_ip_addresses().await;
 
-    let broadcasted_packets = ip_addresses.iter().map(|(ip, mask)| async {
-        let broadcast_addr = calculate_broadcast_address(ip.clone(), mask.clone());
-        let message = b"Hello, world!";
+    let broadcast_addresses: Vec<Ipv4Addr> = ip_addresses
+        .iter()
+        .map(|(ip, mask)| calculate_broadcast_address(*ip, *mask))
+        .collect();
 
-        info!(
-            target: LOG_TARGET,
-            "Broadcasting to {broadcast_addr}"
-        );
+    // Other installations listen on the default port unless it was taken on their side, an
+    // installation that had to fall back likely shares the fallback with ours
+    let mut discovery_ports = vec![DEFAULT_DISCOVERY_PORT];
+    if service_ports().discovery != DEFAULT_DISCOVERY_PORT {
+        discovery_ports.push(service_ports().discovery);
+    }
 
-        socket
-            .send_to(message, SocketAddr::new(IpAddr::V4(broadcast_addr), 5005))
-            .await
-            .unwrap_or_default();
+    // Every attempt gets its own nonce, so a reply tells which broadcast it answers
+    let mut sent_at: HashMap<String, Instant> = HashMap::new();
+    let mut instances: Vec<NetworkInstanceInfo> = Vec::new();
 
-        info!(target: LOG_TARGET, "Waiting for reply");
+    let started_at = Instant::now();
 
-        let mut buf: [u8; 1024] = [0; 1024];
+    let (mdns_resolved_tx, mut mdns_resolved) = mpsc::channel(16);
+    browse_mdns(started_at + DISCOVERY_WINDOW, mdns_resolved_tx);
+    let mut mdns_instances: Vec<NetworkInstanceInfo> = Vec::new();
 
-        match timeout(Duration::new(1, 0), socket.recv_from(&mut buf)).await {
-            Ok(Ok((len, addr))) => {
-                info!(target: LOG_TARGET, "Parsing reply from {addr}");
-                let msg = String::from_utf8_lossy(&buf[..len]).to_string();
+    let mut attempts = 0;
+    let mut next_attempt = started_at;
+    let mut buf = [0; MAX_PACKET_SIZE];
 
-                debug!(target: LOG_TARGET, "Received reply from {addr}: {msg}");
+    loop {
+        if attempts < DISCOVERY_ATTEMPTS && Instant::now() >= next_attempt {
+            attempts += 1;
+
+            let nonce = Alphanumeric.sample_string(&mut OsRng, 16);
+            let message = encode_packet(&DiscoveryRequest {
+                nonce: nonce.clone(),
+            });
+
+            sent_at.insert(nonce, Instant::now());
+
+            for broadcast_addr in &broadcast_addresses {
+                info!(
+                    target: LOG_TARGET,
+                    "Broadcasting to {broadcast_addr} (attempt {attempts})"
+                );
+
+                for port in &discovery_ports {
+                    socket
+                        .send_to(
+                            &message,
+                            SocketAddr::new(IpAddr::V4(*broadcast_addr), *port),
+                        )
+                        .await
+                        .unwrap_or_default();
+                }
+            }
 
-                let center_info: Result<NetworkInstanceInfo, serde_json::Error> =
-                    serde_json::from_str(msg.as_str());
+            next_attempt = Instant::now() + DISCOVERY_RETRY_INTERVAL;
+        }
 
-                match center_info {
-                    Ok(center_info) => {
-                        info!(target: LOG_TARGET, "Found a network instance: {}", &center_info.center_name);
-                        Some(center_info)
-                    }
-                    Err(error) => {
-                        warn!(target: LOG_TARGET, "Failed to parse reply from {addr}: {error}");
-                        None
-                    }
+        let deadline = started_at + DISCOVERY_WINDOW;
+        let now = Instant::now();
+
+        if now >= deadline {
+            break;
+        }
+
+        let wait = match attempts < DISCOVERY_ATTEMPTS {
+            true => next_attempt.min(deadline) - now,
+            false => deadline - now,
+        };
+
+        tokio::select! {
+            received = timeout(wait, socket.recv_from(&mut buf)) => match received {
+                Ok(Ok((len, addr))) => {
+                    info!(target: LOG_TARGET, "Parsing reply from {addr}");
+
+                    let (nonce, mut center_info) = match read_discovery_reply(&buf[..len]).await {
+                        Some(reply) => reply,
+                        None => {
+                            warn!(target: LOG_TARGET, "Failed to parse reply from {addr}");
+                            continue;
+                        }
+                    };
+
+                    let Some(sent_at) = sent_at.get(&nonce) else {
+                        warn!(target: LOG_TARGET, "Dropping reply from {addr} to another request");
+                        continue;
+                    };
+
+                    center_info.latency_ms = Some(sent_at.elapsed().as_millis() as u32);
+
+                    info!(
+                        target: LOG_TARGET,
+                        "Found a network instance: {} (signed: {}, known center: {})",
+                        &center_info.center_name,
+                        center_info.signed,
+                        center_info.known_center
+                    );
+                    instances.push(center_info);
+                }
+                Ok(Err(error)) => {
+                    warn!(target: LOG_TARGET, "Failed to receive reply: {error}");
+                }
+                Err(_) => {}
+            },
+            Some((mdns_ip_addresses, properties)) = mdns_resolved.recv() => {
+                // Ask the advertised addresses directly, unicast gets through where
+                // broadcast is blocked and the reply is signed unlike the TXT records
+                let nonce = Alphanumeric.sample_string(&mut OsRng, 16);
+                let message = encode_packet(&DiscoveryRequest {
+                    nonce: nonce.clone(),
+                });
+
+                sent_at.insert(nonce, Instant::now());
+
+                let info = instance_info_from_txt(&properties, mdns_ip_addresses.clone());
+                let port = info
+                    .as_ref()
+                    .map(|info| info.ports.discovery)
+                    .unwrap_or(DEFAULT_DISCOVERY_PORT);
+
+                for ip in &mdns_ip_addresses {
+                    debug!(target: LOG_TARGET, "Sending discovery request to {ip}:{port} found over mDNS");
+                    socket
+                        .send_to(&message, SocketAddr::new(IpAddr::V4(*ip), port))
+                        .await
+                        .unwrap_or_default();
+                }
+
+                if let Some(info) = info {
+                    mdns_instances.push(info);
                 }
-            }
-            Ok(Err(error)) => {
-                warn!(target: LOG_TARGET, "Failed to receive reply: {error}");
-                None
-            }
-            Err(_) => {
-                info!(target: LOG_TARGET, "Timed out waiting for reply, sending again");
-                None
             }
         }
-    });
+    }
 
-    let instances: Vec<NetworkInstanceInfo> = join_all(broadcasted_packets)
-        .await
-        .into_iter()
-        .filter_map(|instance| instance)
-        .collect();
+    // Instances only found over mDNS are kept with their unverified TXT records
+    for mdns_instance in mdns_instances {
+        let replied = instances.iter().any(|instance| {
+            instance
+                .ip_addresses
+                .iter()
+                .any(|ip| mdns_instance.ip_addresses.contains(ip))
+        });
+
+        if !replied {
+            instances.push(mdns_instance);
+        }
+    }
+
+    info!(
+        target: LOG_TARGET,
+        "Received {} replies in {} attempts",
+        instances.len(),
+        attempts
+    );
 
     let ip_addresses = ip_addresses.into_iter().map(|(ip, _)| ip).collect();
 
-    let instances = filter_network_instances(instances, ip_addresses);
+    // Own replies are dropped first so they never stand in for another instance of the center
+    let instances = deduplicate_instances(filter_network_instances(instances, ip_addresses));
+
+    refresh_master_port(&instances).await;
 
     Ok(instances)
 }
@@ -0,0 +1,62 @@
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// Every discovery packet starts with the magic followed by the protocol version
+pub static DISCOVERY_MAGIC: &[u8] = b"MAGMOOTY";
+
+pub static DISCOVERY_PROTOCOL_VERSION: u8 = 1;
+
+/// Big enough for a reply carrying the center public key and a signature
+pub static MAX_PACKET_SIZE: usize = 4096;
+
+#[derive(Debug, PartialEq)]
+pub enum DiscoveryPacketError {
+    UnknownMagic,
+    UnsupportedVersion(u8),
+    InvalidPayload,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiscoveryRequest {
+    /// Echoed in the reply, replies with another nonce are dropped
+    pub nonce: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DiscoveryReply {
+    pub nonce: String,
+    /// JSON of `NetworkInstanceInfo`, kept as signed
+    pub info: String,
+    /// Signature of the nonce followed by `info` with the center private key, missing for
+    /// instances without a center
+    pub signature: Option<String>,
+}
+
+impl DiscoveryReply {
+    /// Bytes covered by the signature
+    pub fn signed_bytes(nonce: &str, info: &str) -> Vec<u8> {
+        let mut bytes = nonce.as_bytes().to_vec();
+        bytes.extend(info.as_bytes());
+        bytes
+    }
+}
+
+pub fn encode_packet<T: Serialize>(payload: &T) -> Vec<u8> {
+    let mut packet = DISCOVERY_MAGIC.to_vec();
+    packet.push(DISCOVERY_PROTOCOL_VERSION);
+    packet.extend(serde_json::to_vec(payload).unwrap_or_default());
+    packet
+}
+
+pub fn decode_packet<T: DeserializeOwned>(packet: &[u8]) -> Result<T, DiscoveryPacketError> {
+    let payload = packet
+        .strip_prefix(DISCOVERY_MAGIC)
+        .ok_or(DiscoveryPacketError::UnknownMagic)?;
+
+    match payload.split_first() {
+        Some((&version, payload)) if version == DISCOVERY_PROTOCOL_VERSION => {
+            serde_json::from_slice(payload).map_err(|_| DiscoveryPacketError::InvalidPayload)
+        }
+        Some((&version, _)) => Err(DiscoveryPacketError::UnsupportedVersion(version)),
+        None => Err(DiscoveryPacketError::InvalidPayload),
+    }
+}
@@ -0,0 +1,236 @@
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use crate::network_discovery::{
+        deduplicate_instances, filter_network_instances, paired_master_port, InstanceType,
+        NetworkInstanceInfo,
+    };
+    use crate::ports::ServicePorts;
+
+    fn instance(
+        center_id: Option<&str>,
+        instance_type: InstanceType,
+        ip: Ipv4Addr,
+        latency_ms: u32,
+    ) -> NetworkInstanceInfo {
+        NetworkInstanceInfo {
+            center_id: center_id.map(|center_id| center_id.to_string()),
+            center_name: "Center".to_string(),
+            version: "0.0.0".to_string(),
+            instance_type,
+            local_center_initialized: center_id.is_some(),
+            ip_addresses: vec![ip],
+            public_key: None,
+            signed: false,
+            known_center: false,
+            latency_ms: Some(latency_ms),
+            ports: ServicePorts::default(),
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_by_center() {
+        let instances = deduplicate_instances(vec![
+            instance(
+                Some("center:a"),
+                InstanceType::Slave,
+                Ipv4Addr::new(10, 0, 0, 2),
+                5,
+            ),
+            instance(
+                Some("center:a"),
+                InstanceType::Master,
+                Ipv4Addr::new(10, 0, 0, 1),
+                20,
+            ),
+            instance(
+                Some("center:a"),
+                InstanceType::Master,
+                Ipv4Addr::new(10, 0, 0, 1),
+                10,
+            ),
+            instance(
+                Some("center:b"),
+                InstanceType::Master,
+                Ipv4Addr::new(10, 0, 0, 3),
+                30,
+            ),
+        ]);
+
+        assert_eq!(instances.len(), 2);
+
+        // The master of a center is kept over its slaves, with its fastest reply
+        assert_eq!(instances[0].center_id.as_deref(), Some("center:a"));
+        assert!(matches!(instances[0].instance_type, InstanceType::Master));
+        assert_eq!(instances[0].latency_ms, Some(10));
+
+        assert_eq!(instances[1].center_id.as_deref(), Some("center:b"));
+    }
+
+    fn signed_instance(
+        center_id: &str,
+        instance_type: InstanceType,
+        ip: Ipv4Addr,
+        public_key: &str,
+        known_center: bool,
+    ) -> NetworkInstanceInfo {
+        NetworkInstanceInfo {
+            public_key: Some(public_key.to_string()),
+            signed: true,
+            known_center,
+            ..instance(Some(center_id), instance_type, ip, 10)
+        }
+    }
+
+    #[test]
+    fn test_deduplicate_keeps_other_keys_apart() {
+        let mut unsigned = instance(
+            Some("center:a"),
+            InstanceType::Master,
+            Ipv4Addr::new(10, 0, 0, 9),
+            1,
+        );
+        // Claims the key of the center without a matching signature
+        unsigned.public_key = Some("key:a".to_string());
+
+        let instances = deduplicate_instances(vec![
+            signed_instance(
+                "center:a",
+                InstanceType::Slave,
+                Ipv4Addr::new(10, 0, 0, 2),
+                "key:a",
+                true,
+            ),
+            signed_instance(
+                "center:a",
+                InstanceType::Master,
+                Ipv4Addr::new(10, 0, 0, 8),
+                "key:other",
+                false,
+            ),
+            unsigned,
+        ]);
+
+        // A device claiming the same center ID never replaces the replies of the center
+        assert_eq!(instances.len(), 3);
+        assert_eq!(instances[0].ip_addresses, vec![Ipv4Addr::new(10, 0, 0, 2)]);
+        assert!(instances[0].known_center);
+        assert!(!instances[1].known_center);
+        assert!(!instances[2].signed);
+    }
+
+    #[test]
+    fn test_known_center_preferred_over_signed() {
+        let mut signed = signed_instance(
+            "center:a",
+            InstanceType::Slave,
+            Ipv4Addr::new(10, 0, 0, 3),
+            "key:a",
+            false,
+        );
+        signed.latency_ms = Some(1);
+
+        let instances = deduplicate_instances(vec![
+            signed,
+            signed_instance(
+                "center:a",
+                InstanceType::Slave,
+                Ipv4Addr::new(10, 0, 0, 2),
+                "key:a",
+                true,
+            ),
+        ]);
+
+        assert_eq!(instances.len(), 1);
+        assert!(instances[0].known_center);
+        assert_eq!(instances[0].ip_addresses, vec![Ipv4Addr::new(10, 0, 0, 2)]);
+    }
+
+    #[test]
+    fn test_deduplicate_uninitialized() {
+        let instances = deduplicate_instances(vec![
+            instance(
+                None,
+                InstanceType::Uninitialized,
+                Ipv4Addr::new(10, 0, 0, 4),
+                15,
+            ),
+            instance(
+                None,
+                InstanceType::Uninitialized,
+                Ipv4Addr::new(10, 0, 0, 5),
+                5,
+            ),
+            instance(
+                None,
+                InstanceType::Uninitialized,
+                Ipv4Addr::new(10, 0, 0, 4),
+                10,
+            ),
+        ]);
+
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[0].ip_addresses, vec![Ipv4Addr::new(10, 0, 0, 4)]);
+        assert_eq!(instances[0].latency_ms, Some(10));
+        assert_eq!(instances[1].ip_addresses, vec![Ipv4Addr::new(10, 0, 0, 5)]);
+    }
+
+    #[test]
+    fn test_filter_own_instance() {
+        let instances = filter_network_instances(
+            vec![
+                instance(
+                    Some("center:a"),
+                    InstanceType::Master,
+                    Ipv4Addr::new(10, 0, 0, 1),
+                    1,
+                ),
+                instance(
+                    Some("center:a"),
+                    InstanceType::Slave,
+                    Ipv4Addr::new(10, 0, 0, 2),
+                    5,
+                ),
+            ],
+            vec![Ipv4Addr::new(10, 0, 0, 1)],
+        );
+
+        assert_eq!(instances.len(), 1);
+        assert!(matches!(instances[0].instance_type, InstanceType::Slave));
+    }
+
+    #[test]
+    fn test_paired_master_port() {
+        let mut master = signed_instance(
+            "center:a",
+            InstanceType::Master,
+            Ipv4Addr::new(10, 0, 0, 1),
+            "key:a",
+            false,
+        );
+        master.ports.replication = 8001;
+
+        // Anyone can sign a reply claiming the center, only the key of the center counts
+        assert_eq!(paired_master_port(&[master]), None);
+
+        let mut master = signed_instance(
+            "center:a",
+            InstanceType::Master,
+            Ipv4Addr::new(10, 0, 0, 1),
+            "key:a",
+            true,
+        );
+        master.ports.replication = 8001;
+
+        let slave = signed_instance(
+            "center:a",
+            InstanceType::Slave,
+            Ipv4Addr::new(10, 0, 0, 2),
+            "key:a",
+            true,
+        );
+
+        assert_eq!(paired_master_port(&[slave, master]), Some(8001));
+    }
+}
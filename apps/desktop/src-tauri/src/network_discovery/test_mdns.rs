@@ -0,0 +1,64 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::Ipv4Addr;
+
+    use crate::network_discovery::{
+        instance_info_from_txt, txt_properties, InstanceType, NetworkInstanceInfo,
+    };
+    use crate::ports::ServicePorts;
+
+    #[test]
+    fn test_txt_round_trip() {
+        let info = NetworkInstanceInfo {
+            center_id: Some("center:center_id".to_string()),
+            center_name: "Center".to_string(),
+            version: "0.0.35".to_string(),
+            instance_type: InstanceType::Master,
+            local_center_initialized: true,
+            ip_addresses: vec![Ipv4Addr::new(10, 0, 0, 1)],
+            public_key: Some("public_key".to_string()),
+            signed: true,
+            known_center: true,
+            latency_ms: Some(5),
+            ports: ServicePorts {
+                whatsapp: 5003,
+                database: 5004,
+                discovery: 5011,
+                pairing: 5012,
+                replication: 5013,
+            },
+        };
+
+        let properties = txt_properties(&info);
+        let parsed = instance_info_from_txt(&properties, vec![Ipv4Addr::new(10, 0, 0, 1)]).unwrap();
+
+        assert_eq!(parsed.center_id, info.center_id);
+        assert_eq!(parsed.center_name, info.center_name);
+        assert_eq!(parsed.version, info.version);
+        assert!(matches!(parsed.instance_type, InstanceType::Master));
+        assert!(parsed.local_center_initialized);
+        assert_eq!(parsed.ip_addresses, info.ip_addresses);
+        assert_eq!(parsed.ports, info.ports);
+
+        // Nothing in the TXT records is signed
+        assert_eq!(parsed.public_key, None);
+        assert!(!parsed.signed);
+        assert!(!parsed.known_center);
+    }
+
+    #[test]
+    fn test_txt_without_instance_type() {
+        let properties = HashMap::from([("center_name".to_string(), "Center".to_string())]);
+
+        assert!(instance_info_from_txt(&properties, vec![]).is_none());
+    }
+
+    #[test]
+    fn test_txt_without_ports() {
+        let properties = HashMap::from([("instance_type".to_string(), "master".to_string())]);
+        let parsed = instance_info_from_txt(&properties, vec![]).unwrap();
+
+        assert_eq!(parsed.ports, ServicePorts::default());
+    }
+}
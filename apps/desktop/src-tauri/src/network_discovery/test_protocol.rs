@@ -0,0 +1,81 @@
+#[cfg(test)]
+mod tests {
+    use crate::network_discovery::{
+        decode_packet, encode_packet, DiscoveryPacketError, DiscoveryReply, DiscoveryRequest,
+        DISCOVERY_MAGIC, DISCOVERY_PROTOCOL_VERSION,
+    };
+
+    #[test]
+    fn test_request_round_trip() {
+        let request = DiscoveryRequest {
+            nonce: "nonce".to_string(),
+        };
+
+        let packet = encode_packet(&request);
+        assert!(packet.starts_with(DISCOVERY_MAGIC));
+        assert_eq!(packet[DISCOVERY_MAGIC.len()], DISCOVERY_PROTOCOL_VERSION);
+
+        assert_eq!(decode_packet::<DiscoveryRequest>(&packet), Ok(request));
+    }
+
+    #[test]
+    fn test_reply_round_trip() {
+        let reply = DiscoveryReply {
+            nonce: "nonce".to_string(),
+            info: "{}".to_string(),
+            signature: Some("signature".to_string()),
+        };
+
+        assert_eq!(
+            decode_packet::<DiscoveryReply>(&encode_packet(&reply)),
+            Ok(reply)
+        );
+    }
+
+    #[test]
+    fn test_unknown_magic() {
+        assert_eq!(
+            decode_packet::<DiscoveryRequest>(b"Hello, world!"),
+            Err(DiscoveryPacketError::UnknownMagic)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let mut packet = DISCOVERY_MAGIC.to_vec();
+        packet.push(DISCOVERY_PROTOCOL_VERSION + 1);
+        packet.extend(br#"{"nonce":"nonce"}"#);
+
+        assert_eq!(
+            decode_packet::<DiscoveryRequest>(&packet),
+            Err(DiscoveryPacketError::UnsupportedVersion(
+                DISCOVERY_PROTOCOL_VERSION + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        assert_eq!(
+            decode_packet::<DiscoveryRequest>(DISCOVERY_MAGIC),
+            Err(DiscoveryPacketError::InvalidPayload)
+        );
+
+        let mut packet = DISCOVERY_MAGIC.to_vec();
+        packet.push(DISCOVERY_PROTOCOL_VERSION);
+        packet.extend(b"not json");
+
+        assert_eq!(
+            decode_packet::<DiscoveryRequest>(&packet),
+            Err(DiscoveryPacketError::InvalidPayload)
+        );
+    }
+
+    #[test]
+    fn test_signed_bytes() {
+        assert_eq!(
+            DiscoveryReply::signed_bytes("nonce", "{}"),
+            b"nonce{}".to_vec()
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::error::Unspecified;
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, hmac};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs1v15::{Signature, SigningKey, VerifyingKey};
+use rsa::sha2::Sha256;
+use rsa::signature::{SignatureEncoding, Signer, Verifier};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use serde::{Deserialize, Serialize};
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+
+use super::PairingError;
+
+/// Identities bound into the PIN exchange, the slave takes the `A` side
+static SLAVE_IDENTITY: &[u8] = b"magmooty pairing slave";
+
+static MASTER_IDENTITY: &[u8] = b"magmooty pairing master";
+
+/// One SPAKE2 run with the PIN shown on the master
+///
+/// Someone capturing or relaying a run can only test the PIN they used in it, so the PIN can't
+/// be guessed offline and every guess costs one of the attempts the master allows.
+pub struct PinExchange {
+    state: Spake2<Ed25519Group>,
+    message: Vec<u8>,
+}
+
+impl PinExchange {
+    pub fn start_slave(pin: &str) -> Self {
+        let (state, message) = Spake2::<Ed25519Group>::start_a(
+            &Password::new(pin.as_bytes()),
+            &Identity::new(SLAVE_IDENTITY),
+            &Identity::new(MASTER_IDENTITY),
+        );
+
+        Self { state, message }
+    }
+
+    pub fn start_master(pin: &str) -> Self {
+        let (state, message) = Spake2::<Ed25519Group>::start_b(
+            &Password::new(pin.as_bytes()),
+            &Identity::new(SLAVE_IDENTITY),
+            &Identity::new(MASTER_IDENTITY),
+        );
+
+        Self { state, message }
+    }
+
+    /// Message for the other side
+    pub fn message(&self) -> String {
+        base64::prelude::BASE64_STANDARD.encode(&self.message)
+    }
+
+    /// Keys from the message of the other side, both sides only get the same keys with the same PIN
+    pub fn finish(self, message: &str) -> Result<PairingKeys, PairingError> {
+        let message = base64::prelude::BASE64_STANDARD
+            .decode(message)
+            .map_err(|_| PairingError::ProtocolError)?;
+
+        let secret = self
+            .state
+            .finish(&message)
+            .map_err(|_| PairingError::ProtocolError)?;
+
+        PairingKeys::derive(&secret)
+    }
+}
+
+/// Keys derived from the secret of a PIN exchange
+pub struct PairingKeys {
+    proof_key: hmac::Key,
+    grant_key: LessSafeKey,
+}
+
+impl PairingKeys {
+    fn derive(secret: &[u8]) -> Result<Self, PairingError> {
+        let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, b"magmooty pairing").extract(secret);
+
+        let proof_key = prk
+            .expand(&[b"proof"], hmac::HMAC_SHA256)
+            .map_err(|_| PairingError::ProtocolError)?;
+
+        let grant_key = prk
+            .expand(&[b"grant"], &AES_256_GCM)
+            .map_err(|_| PairingError::ProtocolError)?;
+
+        Ok(Self {
+            proof_key: hmac::Key::from(proof_key),
+            grant_key: LessSafeKey::new(UnboundKey::from(grant_key)),
+        })
+    }
+
+    /// Proof that the slave knows the PIN, bound to the pairing request
+    pub fn proof(&self, request_id: &str) -> String {
+        let tag = hmac::sign(&self.proof_key, request_id.as_bytes());
+        base64::prelude::BASE64_STANDARD.encode(tag.as_ref())
+    }
+
+    pub fn verify_proof(&self, request_id: &str, proof: &str) -> bool {
+        match base64::prelude::BASE64_STANDARD.decode(proof) {
+            Ok(proof) => hmac::verify(&self.proof_key, request_id.as_bytes(), &proof).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    /// Encrypt the grant so the database password never crosses the LAN in the clear
+    pub fn seal(&self, request_id: &str, grant: &[u8]) -> Result<String, PairingError> {
+        let payload = seal_payload(&self.grant_key, request_id.as_bytes(), grant)
+            .map_err(|_| PairingError::ProtocolError)?;
+
+        Ok(base64::prelude::BASE64_STANDARD.encode(payload))
+    }
+
+    pub fn open(&self, request_id: &str, sealed: &str) -> Result<Vec<u8>, PairingError> {
+        let payload = base64::prelude::BASE64_STANDARD
+            .decode(sealed)
+            .map_err(|_| PairingError::ProtocolError)?;
+
+        open_payload(&self.grant_key, request_id.as_bytes(), &payload)
+            .map_err(|_| PairingError::ProtocolError)
+    }
+}
+
+/// Encrypt with AES-256-GCM under a fresh random nonce, returns the nonce followed by the
+/// encrypted data and its tag
+pub fn seal_payload(key: &LessSafeKey, aad: &[u8], data: &[u8]) -> Result<Vec<u8>, Unspecified> {
+    let mut nonce = [0; NONCE_LEN];
+    SystemRandom::new().fill(&mut nonce)?;
+
+    let mut sealed = data.to_vec();
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key(nonce),
+        Aad::from(aad),
+        &mut sealed,
+    )?;
+
+    let mut payload = nonce.to_vec();
+    payload.extend(sealed);
+
+    Ok(payload)
+}
+
+/// Decrypt a payload of `seal_payload`, fails when the key, `aad` or the payload don't match
+pub fn open_payload(key: &LessSafeKey, aad: &[u8], payload: &[u8]) -> Result<Vec<u8>, Unspecified> {
+    if payload.len() < NONCE_LEN {
+        return Err(Unspecified);
+    }
+
+    let (nonce, sealed) = payload.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce)?;
+
+    let mut sealed = sealed.to_vec();
+    let data = key.open_in_place(nonce, Aad::from(aad), &mut sealed)?;
+
+    Ok(data.to_vec())
+}
+
+/// Random 6 digit PIN
+pub fn generate_pin() -> Result<String, PairingError> {
+    let mut bytes = [0; 4];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| PairingError::ProtocolError)?;
+
+    Ok(format!("{:06}", u32::from_be_bytes(bytes) % 1_000_000))
+}
+
+pub fn generate_random_bytes(len: usize) -> Result<Vec<u8>, PairingError> {
+    let mut bytes = vec![0; len];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| PairingError::ProtocolError)?;
+
+    Ok(bytes)
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Sign with the center private key (base64 PKCS#1 DER), returns a base64 signature
+pub fn sign_with_center_key(message: &[u8], private_key: &str) -> Result<String, PairingError> {
+    let private_key_der = base64::prelude::BASE64_STANDARD
+        .decode(private_key)
+        .map_err(|_| PairingError::CertificateError)?;
+
+    let private_key = RsaPrivateKey::from_pkcs1_der(&private_key_der)
+        .map_err(|_| PairingError::CertificateError)?;
+    let signature = SigningKey::<Sha256>::new(private_key).sign(message);
+
+    Ok(base64::prelude::BASE64_STANDARD.encode(signature.to_bytes()))
+}
+
+pub fn verify_center_signature(message: &[u8], signature: &str, public_key: &str) -> bool {
+    let verify = || -> Result<(), ()> {
+        let public_key_der = base64::prelude::BASE64_STANDARD
+            .decode(public_key)
+            .map_err(|_| ())?;
+
+        let public_key = RsaPublicKey::from_pkcs1_der(&public_key_der).map_err(|_| ())?;
+
+        let signature = base64::prelude::BASE64_STANDARD
+            .decode(signature)
+            .map_err(|_| ())?;
+
+        let signature = Signature::try_from(signature.as_slice()).map_err(|_| ())?;
+
+        VerifyingKey::<Sha256>::new(public_key)
+            .verify(message, &signature)
+            .map_err(|_| ())
+    };
+
+    verify().is_ok()
+}
+
+/// What the master vouches for when pairing a slave
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct PairingCertificatePayload {
+    pub center_id: String,
+    pub device_id: String,
+    pub device_name: String,
+    pub database_username: String,
+    /// Seconds since the Unix epoch
+    pub issued_at: u64,
+}
+
+/// Certificate issued by the master, signed with the center private key
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PairingCertificate {
+    /// JSON of `PairingCertificatePayload`, kept as signed
+    pub payload: String,
+    pub signature: String,
+}
+
+impl PairingCertificate {
+    pub fn sign(
+        payload: &PairingCertificatePayload,
+        private_key: &str,
+    ) -> Result<Self, PairingError> {
+        let payload = serde_json::to_string(payload).map_err(|_| PairingError::CertificateError)?;
+        let signature = sign_with_center_key(payload.as_bytes(), private_key)?;
+
+        Ok(Self { payload, signature })
+    }
+}
@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::Window;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::time::{timeout, Duration, Instant};
+
+use crate::app::{connect_as_root, GlobalKey, GlobalKeyStore};
+use crate::network_discovery::InstanceType;
+use crate::ports::{service_ports, DEFAULT_PAIRING_PORT};
+
+mod crypto;
+mod test_pairing;
+
+pub use crypto::*;
+
+static LOG_TARGET: &str = "Pairing";
+
+/// How long the PIN shown on the master stays valid
+static PAIRING_REQUEST_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// PIN exchanges allowed before a pairing request is dropped, each one tests a single PIN
+static MAX_PIN_ATTEMPTS: u32 = 3;
+
+/// Pairing requests waiting for a PIN at once, older ones are dropped first
+static MAX_PENDING_REQUESTS: usize = 8;
+
+/// Pairing requests of a single address waiting for a PIN at once, a device flooding the
+/// listener only drops its own requests
+static MAX_PENDING_REQUESTS_PER_ADDRESS: usize = 2;
+
+static MAX_MESSAGE_SIZE: u64 = 64 * 1024;
+
+static MESSAGE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests the master showed a PIN for, by request ID
+static PENDING_REQUESTS: Lazy<Mutex<HashMap<String, PendingRequest>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Request the slave is waiting to confirm with the PIN
+static OUTGOING_REQUEST: Lazy<Mutex<Option<OutgoingRequest>>> = Lazy::new(|| Mutex::new(None));
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PairingError {
+    /// Only a master can pair other devices
+    NotMaster,
+    /// This device is already a master or paired to one
+    AlreadyInitialized,
+    Unreachable,
+    ProtocolError,
+    NoPendingRequest,
+    RequestNotFound,
+    RequestExpired,
+    TooManyAttempts,
+    InvalidPin,
+    DatabaseError,
+    CertificateError,
+    StorageError,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PairingMessage {
+    /// Slave asks to join, the master shows a PIN
+    Join {
+        device_name: String,
+        version: String,
+    },
+    /// Slave starts a PIN exchange, the master answers with its half on the same connection
+    Confirm {
+        request_id: String,
+        exchange: String,
+    },
+    /// Slave proves it used the PIN shown on the master, sent after `Exchange`
+    Proof { proof: String },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PairingReply {
+    Challenge {
+        request_id: String,
+        center_name: String,
+    },
+    /// Master half of the PIN exchange
+    Exchange {
+        exchange: String,
+    },
+    /// `PairingGrant` encrypted with the key from the PIN exchange
+    Accepted {
+        grant: String,
+    },
+    Refused {
+        error: PairingError,
+    },
+}
+
+/// Everything the slave needs to work against the master
+#[derive(Serialize, Deserialize, Debug)]
+struct PairingGrant {
+    center_id: String,
+    center_name: String,
+    /// Center public key, verifies the certificate
+    public_key: String,
+    database_username: String,
+    database_password: String,
+    /// Port of the TLS endpoint of the master database
+    replication_port: u16,
+    certificate: PairingCertificate,
+}
+
+struct PendingRequest {
+    device_name: String,
+    address: SocketAddr,
+    pin: String,
+    created_at: Instant,
+    /// PIN exchanges started, counted up front so an abandoned exchange still costs an attempt
+    attempts: u32,
+}
+
+struct OutgoingRequest {
+    address: SocketAddr,
+    request_id: String,
+}
+
+/// Sent to the master window so it can show the PIN (as digits or a QR code)
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct PairingRequestInfo {
+    pub request_id: String,
+    pub device_name: String,
+    pub address: String,
+    pub pin: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct PairingChallenge {
+    pub request_id: String,
+    pub center_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct PairedMaster {
+    pub center_id: String,
+    pub center_name: String,
+    pub address: String,
+}
+
+async fn read_message<T: for<'de> Deserialize<'de>>(
+    stream: &mut TcpStream,
+) -> Result<T, PairingError> {
+    let mut line = String::new();
+    let mut reader = BufReader::new(stream).take(MAX_MESSAGE_SIZE);
+
+    match timeout(MESSAGE_TIMEOUT, reader.read_line(&mut line)).await {
+        Ok(Ok(read)) if read > 0 => {
+            serde_json::from_str(&line).map_err(|_| PairingError::ProtocolError)
+        }
+        _ => Err(PairingError::ProtocolError),
+    }
+}
+
+async fn write_message<T: Serialize>(
+    stream: &mut TcpStream,
+    message: &T,
+) -> Result<(), PairingError> {
+    let mut line = serde_json::to_vec(message).map_err(|_| PairingError::ProtocolError)?;
+    line.push(b'\n');
+
+    stream
+        .write_all(&line)
+        .await
+        .map_err(|_| PairingError::Unreachable)
+}
+
+/// Create the database user a paired slave signs in with, it can't manage users or the schema
+async fn create_database_user(username: &str, password: &str) -> Result<(), PairingError> {
+    let surreal = connect_as_root()
+        .await
+        .map_err(|_| PairingError::DatabaseError)?;
+
+    // Username and password are generated hex strings, safe to inline
+    surreal
+        .query(format!(
+            "DEFINE USER {} ON DATABASE PASSWORD '{}' ROLES EDITOR",
+            username, password
+        ))
+        .await
+        .and_then(|response| response.check())
+        .map_err(|err| {
+            error!(target: LOG_TARGET, "Failed to create database user {}: {:?}", username, err);
+            PairingError::DatabaseError
+        })?;
+
+    Ok(())
+}
+
+/// Drop expired requests and the oldest ones over the limits before a request from `address`
+fn make_room_for_request(
+    pending_requests: &mut HashMap<String, PendingRequest>,
+    address: SocketAddr,
+) {
+    pending_requests.retain(|_, request| request.created_at.elapsed() < PAIRING_REQUEST_TTL);
+
+    let oldest = |pending_requests: &HashMap<String, PendingRequest>, same_address: bool| {
+        pending_requests
+            .iter()
+            .filter(|(_, request)| !same_address || request.address.ip() == address.ip())
+            .min_by_key(|(_, request)| request.created_at)
+            .map(|(request_id, _)| request_id.clone())
+    };
+
+    let from_address = pending_requests
+        .values()
+        .filter(|request| request.address.ip() == address.ip())
+        .count();
+
+    let dropped = match from_address >= MAX_PENDING_REQUESTS_PER_ADDRESS {
+        true => oldest(pending_requests, true),
+        false if pending_requests.len() >= MAX_PENDING_REQUESTS => oldest(pending_requests, false),
+        false => None,
+    };
+
+    if let Some(request_id) = dropped {
+        pending_requests.remove(&request_id);
+    }
+}
+
+async fn handle_join(
+    window: &Window,
+    address: SocketAddr,
+    device_name: String,
+) -> Result<PairingReply, PairingError> {
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|_| PairingError::StorageError)?;
+
+    if !matches!(store.instance_type().await, Ok(InstanceType::Master)) {
+        return Err(PairingError::NotMaster);
+    }
+
+    let request_id = to_hex(&generate_random_bytes(16)?);
+    let pin = generate_pin()?;
+
+    let mut pending_requests = PENDING_REQUESTS.lock().await;
+    make_room_for_request(&mut pending_requests, address);
+
+    pending_requests.insert(
+        request_id.clone(),
+        PendingRequest {
+            device_name: device_name.clone(),
+            address,
+            pin: pin.clone(),
+            created_at: Instant::now(),
+            attempts: 0,
+        },
+    );
+
+    info!(target: LOG_TARGET, "{} at {} asked to pair", device_name, address);
+    window
+        .emit(
+            "pairing_requested",
+            PairingRequestInfo {
+                request_id: request_id.clone(),
+                device_name,
+                address: address.ip().to_string(),
+                pin,
+            },
+        )
+        .unwrap_or_default();
+
+    Ok(PairingReply::Challenge {
+        request_id,
+        center_name: store.center_name().await.ok().flatten().unwrap_or_default(),
+    })
+}
+
+async fn handle_confirm(
+    window: &Window,
+    stream: &mut TcpStream,
+    request_id: String,
+    exchange: String,
+) -> Result<PairingReply, PairingError> {
+    let mut pending_requests = PENDING_REQUESTS.lock().await;
+
+    let request = pending_requests
+        .get_mut(&request_id)
+        .ok_or(PairingError::RequestNotFound)?;
+
+    if request.created_at.elapsed() >= PAIRING_REQUEST_TTL {
+        pending_requests.remove(&request_id);
+        return Err(PairingError::RequestExpired);
+    }
+
+    if request.attempts >= MAX_PIN_ATTEMPTS {
+        pending_requests.remove(&request_id);
+        return Err(PairingError::TooManyAttempts);
+    }
+
+    request.attempts += 1;
+    let pin_exchange = PinExchange::start_master(&request.pin);
+    drop(pending_requests);
+
+    // The lock isn't held while waiting for the slave, other requests stay responsive
+    write_message(
+        stream,
+        &PairingReply::Exchange {
+            exchange: pin_exchange.message(),
+        },
+    )
+    .await?;
+    let keys = pin_exchange.finish(&exchange)?;
+
+    let PairingMessage::Proof { proof } = read_message(stream).await? else {
+        return Err(PairingError::ProtocolError);
+    };
+
+    let mut pending_requests = PENDING_REQUESTS.lock().await;
+
+    // Rejected or used by another exchange in the meantime
+    let request = pending_requests
+        .get(&request_id)
+        .ok_or(PairingError::RequestNotFound)?;
+
+    if !keys.verify_proof(&request_id, &proof) {
+        warn!(target: LOG_TARGET, "Wrong PIN for pairing request {}", request_id);
+
+        if request.attempts >= MAX_PIN_ATTEMPTS {
+            pending_requests.remove(&request_id);
+            window
+                .emit("pairing_failed", request_id)
+                .unwrap_or_default();
+            return Err(PairingError::TooManyAttempts);
+        }
+
+        return Err(PairingError::InvalidPin);
+    }
+
+    // The PIN can only be used once
+    let request = pending_requests.remove(&request_id).unwrap();
+    drop(pending_requests);
+
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|_| PairingError::StorageError)?;
+
+    let (center_id, center_name, private_key, public_key) = match (
+        store.center_id().await,
+        store.center_name().await,
+        store.private_key().await,
+        store.public_key().await,
+    ) {
+        (Ok(Some(center_id)), Ok(center_name), Ok(Some(private_key)), Ok(Some(public_key))) => (
+            center_id,
+            center_name.unwrap_or_default(),
+            private_key,
+            public_key,
+        ),
+        _ => return Err(PairingError::NotMaster),
+    };
+
+    let database_username = format!("device_{}", &request_id[..16]);
+    let database_password = to_hex(&generate_random_bytes(32)?);
+
+    create_database_user(&database_username, &database_password).await?;
+
+    let certificate = PairingCertificate::sign(
+        &PairingCertificatePayload {
+            center_id: center_id.clone(),
+            device_id: request_id.clone(),
+            device_name: request.device_name.clone(),
+            database_username: database_username.clone(),
+            issued_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|duration| duration.as_secs())
+                .unwrap_or_default(),
+        },
+        &private_key,
+    )?;
+
+    let grant = serde_json::to_vec(&PairingGrant {
+        center_id,
+        center_name,
+        public_key,
+        database_username,
+        database_password,
+        replication_port: service_ports().replication,
+        certificate,
+    })
+    .map_err(|_| PairingError::ProtocolError)?;
+
+    info!(target: LOG_TARGET, "Paired {} at {}", request.device_name, request.address);
+    window
+        .emit("pairing_completed", request_id.clone())
+        .unwrap_or_default();
+
+    Ok(PairingReply::Accepted {
+        grant: keys.seal(&request_id, &grant)?,
+    })
+}
+
+async fn handle_connection(window: Window, mut stream: TcpStream, address: SocketAddr) {
+    let reply = match read_message::<PairingMessage>(&mut stream).await {
+        Ok(PairingMessage::Join {
+            device_name,
+            version,
+        }) => {
+            debug!(target: LOG_TARGET, "Join request from {} running {}", address, version);
+            handle_join(&window, address, device_name).await
+        }
+        Ok(PairingMessage::Confirm {
+            request_id,
+            exchange,
+        }) => handle_confirm(&window, &mut stream, request_id, exchange).await,
+        Ok(PairingMessage::Proof { .. }) => Err(PairingError::ProtocolError),
+        Err(error) => Err(error),
+    };
+
+    let reply = reply.unwrap_or_else(|error| {
+        warn!(target: LOG_TARGET, "Refusing pairing message from {}: {:?}", address, error);
+        PairingReply::Refused { error }
+    });
+
+    write_message(&mut stream, &reply).await.unwrap_or_default();
+}
+
+/// Accept pairing requests from other devices on the LAN, only a master grants them
+pub async fn start_pairing_listener(window: Window) {
+    let port = service_ports().pairing;
+
+    info!(target: LOG_TARGET, "Binding pairing listener to 0.0.0.0:{}", port);
+    let listener = match TcpListener::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to bind pairing listener: {:?}", err);
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, address)) => {
+                tokio::spawn(handle_connection(window.clone(), stream, address));
+            }
+            Err(err) => {
+                warn!(target: LOG_TARGET, "Failed to accept pairing connection: {:?}", err);
+            }
+        }
+    }
+}
+
+/// Drop a pairing request so its PIN can't be used anymore
+pub async fn reject_pairing(request_id: &str) {
+    if PENDING_REQUESTS.lock().await.remove(request_id).is_some() {
+        info!(target: LOG_TARGET, "Rejected pairing request {}", request_id);
+    }
+}
+
+async fn connect(address: SocketAddr) -> Result<TcpStream, PairingError> {
+    timeout(MESSAGE_TIMEOUT, TcpStream::connect(address))
+        .await
+        .map_err(|_| PairingError::Unreachable)?
+        .map_err(|_| PairingError::Unreachable)
+}
+
+async fn send_message(
+    stream: &mut TcpStream,
+    message: &PairingMessage,
+) -> Result<PairingReply, PairingError> {
+    write_message(stream, message).await?;
+
+    match read_message(stream).await? {
+        PairingReply::Refused { error } => Err(error),
+        reply => Ok(reply),
+    }
+}
+
+/// Ask the master at `address` to pair, it shows the PIN to enter in `confirm_pairing`
+///
+/// `port` is the pairing port the master advertised, the default port when unknown.
+pub async fn request_pairing(
+    address: Ipv4Addr,
+    port: Option<u16>,
+    device_name: String,
+) -> Result<PairingChallenge, PairingError> {
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|_| PairingError::StorageError)?;
+
+    if !matches!(store.instance_type().await, Ok(InstanceType::Uninitialized)) {
+        return Err(PairingError::AlreadyInitialized);
+    }
+
+    let address = SocketAddr::V4(SocketAddrV4::new(
+        address,
+        port.unwrap_or(DEFAULT_PAIRING_PORT),
+    ));
+
+    info!(target: LOG_TARGET, "Requesting to pair with {}", address);
+    let reply = send_message(
+        &mut connect(address).await?,
+        &PairingMessage::Join {
+            device_name,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        },
+    )
+    .await?;
+
+    let PairingReply::Challenge {
+        request_id,
+        center_name,
+    } = reply
+    else {
+        return Err(PairingError::ProtocolError);
+    };
+
+    *OUTGOING_REQUEST.lock().await = Some(OutgoingRequest {
+        address,
+        request_id: request_id.clone(),
+    });
+
+    Ok(PairingChallenge {
+        request_id,
+        center_name,
+    })
+}
+
+/// Confirm the pending pairing request with the PIN shown on the master and store the grant
+pub async fn confirm_pairing(pin: String) -> Result<PairedMaster, PairingError> {
+    let mut outgoing_request = OUTGOING_REQUEST.lock().await;
+
+    let request = outgoing_request
+        .as_ref()
+        .ok_or(PairingError::NoPendingRequest)?;
+
+    let pin_exchange = PinExchange::start_slave(pin.trim());
+    let mut stream = connect(request.address).await?;
+
+    let reply = send_message(
+        &mut stream,
+        &PairingMessage::Confirm {
+            request_id: request.request_id.clone(),
+            exchange: pin_exchange.message(),
+        },
+    )
+    .await;
+
+    let keys = match reply {
+        Ok(PairingReply::Exchange { exchange }) => pin_exchange.finish(&exchange)?,
+        Ok(_) => return Err(PairingError::ProtocolError),
+        Err(error) => {
+            outgoing_request.take();
+            return Err(error);
+        }
+    };
+
+    let reply = send_message(
+        &mut stream,
+        &PairingMessage::Proof {
+            proof: keys.proof(&request.request_id),
+        },
+    )
+    .await;
+
+    let grant = match reply {
+        Ok(PairingReply::Accepted { grant }) => grant,
+        Ok(_) => return Err(PairingError::ProtocolError),
+        // A wrong PIN can be entered again, other refusals end the request
+        Err(PairingError::InvalidPin) => return Err(PairingError::InvalidPin),
+        Err(error) => {
+            outgoing_request.take();
+            return Err(error);
+        }
+    };
+
+    let grant = keys.open(&request.request_id, &grant)?;
+    let grant: PairingGrant =
+        serde_json::from_slice(&grant).map_err(|_| PairingError::ProtocolError)?;
+
+    // Only the master showing the PIN could seal the grant, its public key is trusted from here
+    let address = request.address.ip().to_string();
+    outgoing_request.take();
+
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|_| PairingError::StorageError)?;
+
+    let certificate =
+        serde_json::to_string(&grant.certificate).map_err(|_| PairingError::CertificateError)?;
+
+    for (key, value) in [
+        (GlobalKey::CenterId, grant.center_id.clone()),
+        (GlobalKey::CenterName, grant.center_name.clone()),
+        (GlobalKey::PublicKey, grant.public_key),
+        (GlobalKey::MasterAddress, address.clone()),
+        (
+            GlobalKey::MasterReplicationPort,
+            grant.replication_port.to_string(),
+        ),
+        (GlobalKey::DatabaseUsername, grant.database_username),
+        (GlobalKey::DatabasePassword, grant.database_password),
+        (GlobalKey::PairingCertificate, certificate),
+        // Set last, the device only counts as paired once everything else is stored
+        (GlobalKey::InstanceType, "slave".to_string()),
+    ] {
+        store
+            .set(key, value)
+            .await
+            .map_err(|_| PairingError::StorageError)?;
+    }
+
+    info!(target: LOG_TARGET, "Paired with {} at {}", grant.center_name, address);
+
+    Ok(PairedMaster {
+        center_id: grant.center_id,
+        center_name: grant.center_name,
+        address,
+    })
+}
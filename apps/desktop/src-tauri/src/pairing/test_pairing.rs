@@ -0,0 +1,210 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    use base64::Engine;
+    use rsa::pkcs1::{DecodeRsaPrivateKey, EncodeRsaPublicKey};
+    use rsa::{RsaPrivateKey, RsaPublicKey};
+    use tokio::time::Instant;
+
+    use crate::pairing::{
+        generate_pin, make_room_for_request, verify_center_signature, PairingCertificate,
+        PairingCertificatePayload, PairingError, PairingKeys, PendingRequest, PinExchange,
+        MAX_PENDING_REQUESTS, MAX_PENDING_REQUESTS_PER_ADDRESS, PAIRING_REQUEST_TTL,
+    };
+
+    static REQUEST_ID: &str = "0123456789abcdef0123456789abcdef";
+
+    static PRIVATE_KEY: &str = "MIIEpQIBAAKCAQEA2X259apxTri5rV1mFJadvzc7YZZgdxuvQPoxBRTf6x2cAULCnx/UkQAwfNKxTp4pQ9thrLOwx5a8OZN74xpqQXzTjqn7OkQ8pm3qpmQ+av+XD2LLnRisMA2C//i8A3qeQc5CAyy+6gMPyMEz7ku718qlxZxAdqO1sjB0bIdaRHHXoTt2+MAv1bba6Q3aePZbj+NQY9okE/4wE3Y5iKS7C/4leXP1nhqAEnwio/sv3BgUF7bvYZhaGQ0sdBXBviDwYAixW4MtPGujZ+UWmZ4CNZdA7p18lPdSqMpgGd5oFOaTLifrQGCSCExgoqVcF5kSJ3pBpcNXGdvdZpA8CH7yXwIDAQABAoIBAQDD7vk1sWxMkCxuW4MYLyxD1J1BaDjVdPJopjy9KDYl2VHu7NbqhcF5M+N6wFEN03y3bg9Lh8JNvKUrdYuZZ/Wrs6nfj5ENx+WfxcwsRIja2hGbwdRPXafZzoJi2hF/TNr/y7I6q/f+V+3DXRLMrhu25xB6uy5z8z5AHlj0YOfYWdGX57oFt7AjQv57q51PKDTXas1pwidlPf8tqAZhTc+cz5y84e/l1nsDp1XL9xv+Qpy2IqtYSi7jCqI1YzcYpQZJPPWCbE43lfIMSz8CozFf0kQKfuJqPBWkij+BOx6bA08KA2tH8deOS/XoB1eOsorZUJja7PWnVP0iZdLzCeVZAoGBAPPX4xBBfuLNV3/6FAmtmnLThhtr4JFQlXh9MSfR5c2d1POWD1POG4ZvM5Bd03K/EYHg2oocguynMHq8bACMYllEmlH6BeRQJNhNOJV8cZNfC1gYMK/QKFqAh66wNDN1Ja9cATesIv62yLy0711RyEuPkFkZr+xEtoBnrQTIsT1LAoGBAORVg0jHQ8Xl6Nzo9QQJsNDrgZzJZB/vuslbeAVhhGRpNVDcvj1837Izv3Cm0UILP/mlR3G7Sv9mtkj8f8aquwSJh25mIk8g0bGEP8UZH3WuH7OfjtZu4n2M+PocpG9icFuUOQ3q3BMdvq2TfcbdUyFGySh3POxMj2OCzQtkONa9AoGAF+2EY5D5wYnC31UL6FM3x7LIAyLX24qb0EIAs9aeBUpKnkiIYoHkI5H/7le2qxjiv+rvpwPbORvC3xhkRL2B3R6lQgwVzeyrYOpa9hhLENoPw+pDxMzZWOAp8FNsG+yP8SBHIk5q9LG9Cv96SZ7/16JT2Npzb+ziN8F0sfZ7pfcCgYEAni3jUhLBD6KzS/6SZma0ODc+Rjh6BWnVE1MrdUbWKZ180vTpUb1lVpVkxQy7oK4cdryaHt7qGL61/x/1ANMb1gvUZ5WXpQuOWRTN/KPn2GV1DsG1eTW9784uWU5oV8VxIvAvCkYuiYusoaCwnIiM41ufVUotSWHMX9qoY4Ddo10CgYEAr2DMNA9UsWtaKWoU4xo8Y/e7dUVDPDIbCIqMrpJ+aScCg02S7L7bnAJcbYRnseabJA+ZmWFqX9hfEoeO0i8KU4IPUcMY/ujMqumLoGTjVseMxTJks7DzPZM9aTuNx8GdOZpyZoNED+uZB0m3/3MJ9CkOT7RcbaB6CKo6WVY2c7o=";
+
+    fn public_key() -> String {
+        let private_key_der = base64::prelude::BASE64_STANDARD
+            .decode(PRIVATE_KEY)
+            .unwrap();
+        let private_key = RsaPrivateKey::from_pkcs1_der(&private_key_der).unwrap();
+        let public_key_der = RsaPublicKey::from(&private_key).to_pkcs1_der().unwrap();
+
+        base64::prelude::BASE64_STANDARD.encode(public_key_der.as_bytes())
+    }
+
+    fn certificate_payload() -> PairingCertificatePayload {
+        PairingCertificatePayload {
+            center_id: "center:center_id".to_string(),
+            device_id: REQUEST_ID.to_string(),
+            device_name: "Reception".to_string(),
+            database_username: "device_0123456789abcdef".to_string(),
+            issued_at: 1_700_000_000,
+        }
+    }
+
+    #[test]
+    fn test_generate_pin() {
+        for _ in 0..100 {
+            let pin = generate_pin().unwrap();
+            assert_eq!(pin.len(), 6);
+            assert!(pin.chars().all(|digit| digit.is_ascii_digit()));
+        }
+    }
+
+    /// Keys of the slave and the master after a PIN exchange
+    fn exchange_keys(slave_pin: &str, master_pin: &str) -> (PairingKeys, PairingKeys) {
+        let slave = PinExchange::start_slave(slave_pin);
+        let master = PinExchange::start_master(master_pin);
+
+        let slave_message = slave.message();
+        let master_message = master.message();
+
+        (
+            slave.finish(&master_message).unwrap(),
+            master.finish(&slave_message).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_proof() {
+        let (slave, master) = exchange_keys("123456", "123456");
+
+        let proof = slave.proof(REQUEST_ID);
+        assert!(master.verify_proof(REQUEST_ID, &proof));
+
+        // Bound to the request
+        assert!(!master.verify_proof("another_request", &proof));
+        assert!(!master.verify_proof(REQUEST_ID, "not base64"));
+    }
+
+    #[test]
+    fn test_proof_wrong_pin() {
+        let (slave, master) = exchange_keys("654321", "123456");
+
+        assert!(!master.verify_proof(REQUEST_ID, &slave.proof(REQUEST_ID)));
+    }
+
+    #[test]
+    fn test_exchanges_have_fresh_keys() {
+        let (slave, _) = exchange_keys("123456", "123456");
+        let (_, master) = exchange_keys("123456", "123456");
+
+        // A proof from an earlier exchange with the right PIN can't be replayed
+        assert!(!master.verify_proof(REQUEST_ID, &slave.proof(REQUEST_ID)));
+    }
+
+    #[test]
+    fn test_exchange_rejects_invalid_message() {
+        assert_eq!(
+            PinExchange::start_master("123456")
+                .finish("not base64")
+                .err(),
+            Some(PairingError::ProtocolError)
+        );
+        assert_eq!(
+            PinExchange::start_master("123456").finish("AAAA").err(),
+            Some(PairingError::ProtocolError)
+        );
+    }
+
+    #[test]
+    fn test_seal_grant() {
+        let (slave, master) = exchange_keys("123456", "123456");
+
+        let sealed = master.seal(REQUEST_ID, b"grant").unwrap();
+        assert_eq!(slave.open(REQUEST_ID, &sealed).unwrap(), b"grant");
+
+        let (wrong_pin, _) = exchange_keys("654321", "123456");
+        assert_eq!(
+            wrong_pin.open(REQUEST_ID, &sealed),
+            Err(PairingError::ProtocolError)
+        );
+        assert_eq!(
+            slave.open("another_request", &sealed),
+            Err(PairingError::ProtocolError)
+        );
+    }
+
+    #[test]
+    fn test_certificate() {
+        let certificate = PairingCertificate::sign(&certificate_payload(), PRIVATE_KEY).unwrap();
+
+        assert!(verify_center_signature(
+            certificate.payload.as_bytes(),
+            &certificate.signature,
+            &public_key()
+        ));
+        assert_eq!(
+            serde_json::from_str::<PairingCertificatePayload>(&certificate.payload).unwrap(),
+            certificate_payload()
+        );
+    }
+
+    #[test]
+    fn test_tampered_certificate() {
+        let mut certificate =
+            PairingCertificate::sign(&certificate_payload(), PRIVATE_KEY).unwrap();
+        certificate.payload = certificate.payload.replace("Reception", "Office");
+
+        assert!(!verify_center_signature(
+            certificate.payload.as_bytes(),
+            &certificate.signature,
+            &public_key()
+        ));
+    }
+
+    fn pending_request(address: &str, age: Duration) -> PendingRequest {
+        PendingRequest {
+            device_name: "Reception".to_string(),
+            address: address.parse().unwrap(),
+            pin: "123456".to_string(),
+            created_at: Instant::now().checked_sub(age).unwrap(),
+            attempts: 0,
+        }
+    }
+
+    #[test]
+    fn test_flood_only_drops_own_requests() {
+        let mut pending_requests = HashMap::new();
+        pending_requests.insert(
+            "reception".to_string(),
+            pending_request("10.0.0.2:40000", Duration::from_secs(60)),
+        );
+
+        for attempt in 0..(MAX_PENDING_REQUESTS * 2) {
+            let address = format!("10.0.0.3:{}", 40000 + attempt);
+            make_room_for_request(&mut pending_requests, address.parse().unwrap());
+            pending_requests.insert(
+                format!("flood_{}", attempt),
+                pending_request(&address, Duration::ZERO),
+            );
+        }
+
+        assert!(pending_requests.contains_key("reception"));
+        assert_eq!(pending_requests.len(), 1 + MAX_PENDING_REQUESTS_PER_ADDRESS);
+    }
+
+    #[test]
+    fn test_drops_expired_then_oldest_requests() {
+        let mut pending_requests = HashMap::new();
+        pending_requests.insert(
+            "expired".to_string(),
+            pending_request(
+                "10.0.1.1:40000",
+                PAIRING_REQUEST_TTL + Duration::from_secs(1),
+            ),
+        );
+
+        for index in 0..MAX_PENDING_REQUESTS {
+            pending_requests.insert(
+                format!("request_{}", index),
+                pending_request(
+                    &format!("10.0.0.{}:40000", index + 2),
+                    Duration::from_secs(100 - index as u64),
+                ),
+            );
+        }
+
+        make_room_for_request(&mut pending_requests, "10.0.0.100:40000".parse().unwrap());
+
+        assert!(!pending_requests.contains_key("expired"));
+        assert!(!pending_requests.contains_key("request_0"));
+        assert_eq!(pending_requests.len(), MAX_PENDING_REQUESTS - 1);
+    }
+}
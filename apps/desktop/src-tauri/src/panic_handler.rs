@@ -1,10 +1,16 @@
 use std::panic::{self, take_hook};
 
+use crate::process_killer::remove_pid_file;
+use crate::sidecar::stop_supervising;
 use crate::SIDECARS;
 
 pub fn close_sidecars() {
+    stop_supervising();
+
     if let Ok(mut sidecars) = SIDECARS.lock() {
-        while let Some(sidecar) = sidecars.pop() {
+        for (name, sidecar) in sidecars.drain() {
+            remove_pid_file(name);
+
             eprintln!("Killing sidecar: {:?}", sidecar.pid());
 
             if let Err(err) = &sidecar.kill() {
@@ -0,0 +1,141 @@
+use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+
+use log::{error, info, warn};
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use crate::app::{GlobalKey, GlobalKeyStore};
+
+mod test_ports;
+
+static LOG_TARGET: &str = "Ports";
+
+/// Ports used unless taken, other installations look for the discovery and pairing ports first
+pub static DEFAULT_WHATSAPP_PORT: u16 = 5003;
+pub static DEFAULT_DATABASE_PORT: u16 = 5004;
+pub static DEFAULT_DISCOVERY_PORT: u16 = 5005;
+pub static DEFAULT_PAIRING_PORT: u16 = 5006;
+pub static DEFAULT_REPLICATION_PORT: u16 = 5007;
+
+/// Ports tried after a taken port before letting the OS pick one
+static FALLBACK_RANGE: u16 = 20;
+
+/// Ports resolved at startup, set once before the services start
+static SERVICE_PORTS: OnceCell<ServicePorts> = OnceCell::new();
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Type, PartialEq)]
+pub struct ServicePorts {
+    pub whatsapp: u16,
+    pub database: u16,
+    /// UDP port answering discovery requests
+    pub discovery: u16,
+    pub pairing: u16,
+    /// TLS endpoint of the database for paired slaves, the database itself only listens locally
+    #[serde(default = "default_replication_port")]
+    pub replication: u16,
+}
+
+fn default_replication_port() -> u16 {
+    DEFAULT_REPLICATION_PORT
+}
+
+impl Default for ServicePorts {
+    fn default() -> Self {
+        Self {
+            whatsapp: DEFAULT_WHATSAPP_PORT,
+            database: DEFAULT_DATABASE_PORT,
+            discovery: DEFAULT_DISCOVERY_PORT,
+            pairing: DEFAULT_PAIRING_PORT,
+            replication: DEFAULT_REPLICATION_PORT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// Ports the services run on, the defaults until `resolve_service_ports` ran
+pub fn service_ports() -> ServicePorts {
+    SERVICE_PORTS.get().copied().unwrap_or_default()
+}
+
+/// Ports stored by the last run, the defaults on first run
+pub async fn stored_service_ports(store: &GlobalKeyStore) -> ServicePorts {
+    match store.get(GlobalKey::ServicePorts).await {
+        Ok(Some(ports)) => serde_json::from_str(&ports).unwrap_or_default(),
+        _ => ServicePorts::default(),
+    }
+}
+
+pub fn is_port_free(port: u16, protocol: Protocol) -> bool {
+    match protocol {
+        Protocol::Tcp => TcpListener::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok(),
+        Protocol::Udp => UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).is_ok(),
+    }
+}
+
+/// First port from `preferred` on that is free and not `taken` by another service
+pub fn pick_port(preferred: u16, taken: &[u16], is_free: impl Fn(u16) -> bool) -> Option<u16> {
+    (preferred..=preferred.saturating_add(FALLBACK_RANGE))
+        .find(|port| !taken.contains(port) && is_free(*port))
+}
+
+/// Port picked by the OS, `None` if nothing can be bound at all
+fn os_assigned_port(protocol: Protocol) -> Option<u16> {
+    let address = match protocol {
+        Protocol::Tcp => {
+            TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).and_then(|listener| listener.local_addr())
+        }
+        Protocol::Udp => {
+            UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).and_then(|socket| socket.local_addr())
+        }
+    };
+
+    address.ok().map(|address| address.port())
+}
+
+fn resolve_port(name: &str, preferred: u16, taken: &mut Vec<u16>, protocol: Protocol) -> u16 {
+    let port = pick_port(preferred, taken, |port| is_port_free(port, protocol))
+        .or_else(|| os_assigned_port(protocol))
+        .unwrap_or(preferred);
+
+    if port != preferred {
+        warn!(target: LOG_TARGET, "Port {} of {} is taken, using {}", preferred, name, port);
+    }
+
+    taken.push(port);
+    port
+}
+
+/// Check the stored ports are free, fall back to free ones and store the result
+///
+/// Runs before the sidecars and listeners start, the ports stay fixed for the app run.
+pub async fn resolve_service_ports(store: &GlobalKeyStore, stored: ServicePorts) -> ServicePorts {
+    let mut taken = Vec::new();
+
+    let ports = ServicePorts {
+        whatsapp: resolve_port("WhatsApp", stored.whatsapp, &mut taken, Protocol::Tcp),
+        database: resolve_port("SurrealDB", stored.database, &mut taken, Protocol::Tcp),
+        discovery: resolve_port("discovery", stored.discovery, &mut taken, Protocol::Udp),
+        pairing: resolve_port("pairing", stored.pairing, &mut taken, Protocol::Tcp),
+        replication: resolve_port("replication", stored.replication, &mut taken, Protocol::Tcp),
+    };
+
+    info!(target: LOG_TARGET, "Using ports {:?}", ports);
+
+    match serde_json::to_string(&ports) {
+        Ok(value) => {
+            if let Err(err) = store.set(GlobalKey::ServicePorts, value).await {
+                error!(target: LOG_TARGET, "Failed to store ports: {:?}", err);
+            }
+        }
+        Err(err) => error!(target: LOG_TARGET, "Failed to serialize ports: {:?}", err),
+    }
+
+    SERVICE_PORTS.set(ports).unwrap_or_default();
+    service_ports()
+}
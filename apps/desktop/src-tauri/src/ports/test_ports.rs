@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, TcpListener, UdpSocket};
+
+    use crate::ports::{is_port_free, pick_port, Protocol, ServicePorts};
+
+    #[test]
+    fn test_pick_preferred_port() {
+        assert_eq!(pick_port(5004, &[], |_| true), Some(5004));
+    }
+
+    #[test]
+    fn test_pick_next_free_port() {
+        assert_eq!(pick_port(5004, &[], |port| port > 5006), Some(5007));
+    }
+
+    #[test]
+    fn test_pick_port_skips_taken_ports() {
+        assert_eq!(pick_port(5004, &[5004, 5005], |_| true), Some(5006));
+    }
+
+    #[test]
+    fn test_pick_port_gives_up_after_range() {
+        assert_eq!(pick_port(5004, &[], |_| false), None);
+        assert_eq!(pick_port(u16::MAX, &[], |_| false), None);
+    }
+
+    #[test]
+    fn test_is_port_free() {
+        let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(!is_port_free(port, Protocol::Tcp));
+
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        assert!(!is_port_free(port, Protocol::Udp));
+
+        drop(socket);
+        assert!(is_port_free(port, Protocol::Udp));
+    }
+
+    #[test]
+    fn test_default_service_ports() {
+        assert_eq!(
+            ServicePorts::default(),
+            ServicePorts {
+                whatsapp: 5003,
+                database: 5004,
+                discovery: 5005,
+                pairing: 5006,
+                replication: 5007,
+            }
+        );
+    }
+}
@@ -1,116 +0,0 @@
-use log::debug;
-use std::process::Command;
-
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
-
-static LOG_TARGET: &str = "Process Killer";
-
-#[cfg(target_os = "macos")]
-fn shutdown_process(process_id: u32) -> Result<(), std::io::Error> {
-    let output = Command::new("kill")
-        .arg("-TERM")
-        .arg(process_id.to_string())
-        .output()?;
-
-    if output.status.success() {
-        debug!(target: LOG_TARGET, "Process {} killed", process_id);
-    } else {
-        debug!(target: LOG_TARGET, "Failed to kill process {}", process_id);
-    }
-
-    Ok(())
-}
-
-#[cfg(target_os = "macos")]
-fn find_process_on_port(port: u16) -> Option<u32> {
-    debug!(target: LOG_TARGET, "Finding processes listening on port {}", port);
-
-    let output = Command::new("lsof")
-        .arg("-t")
-        .arg("-i")
-        .arg(format!(":{}", port))
-        .output()
-        .expect("Failed to execute lsof command");
-
-    if output.stdout.is_empty() {
-        debug!(target: LOG_TARGET, "No process is listening on port {}", port);
-        None
-    } else {
-        String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .parse::<u32>()
-            .ok()
-    }
-}
-
-#[cfg(target_os = "windows")]
-fn shutdown_process(process_id: u32) -> Result<(), std::io::Error> {
-    let output;
-
-    output = Command::new("taskkill")
-        .arg("/PID")
-        .arg(process_id.to_string())
-        .creation_flags(0x08000000)
-        .output()
-        .expect("Failed to execute lsof command");
-
-    if output.status.success() {
-        debug!(target: LOG_TARGET, "Process {} killed", process_id);
-    } else {
-        debug!(target: LOG_TARGET, "Failed to kill process {}", process_id);
-    }
-
-    Ok(())
-}
-
-#[cfg(target_os = "windows")]
-fn find_process_on_port(port: u16) -> Option<u32> {
-    let output = Command::new("netstat")
-        .arg("-ano")
-        .arg("-p")
-        .arg("TCP")
-        .creation_flags(0x08000000)
-        .output()
-        .expect("Failed to execute netstat command");
-
-    if output.stdout.is_empty() {
-        debug!(target: LOG_TARGET, "No process is listening on port {}", port);
-        None
-    } else {
-        let output_str = String::from_utf8_lossy(&output.stdout);
-
-        let lines: Vec<&str> = output_str.split("\n").collect();
-        for line in lines {
-            if line.contains(&format!(":{}", port)) {
-                debug!(target: LOG_TARGET, "Process found on port {}: {}", port, line);
-                let parts: Vec<&str> = line.split_whitespace().collect();
-
-                // The last part should be the PID
-                if let Some(pid) = parts.last() {
-                    if let Ok(pid_number) = pid.parse::<u32>() {
-                        return Some(pid_number);
-                    }
-                }
-            }
-        }
-
-        None
-    }
-}
-
-pub fn kill_hanging_sidecars() {
-    let whatsapp = find_process_on_port(5003);
-
-    if let Some(whatsapp) = whatsapp {
-        debug!(target: LOG_TARGET, "Killing hanging WhatsApp sidecar on port 5003 with PID {}", &whatsapp);
-        shutdown_process(whatsapp).unwrap_or_default();
-    }
-
-    let surreal = find_process_on_port(5004);
-
-    if let Some(surreal) = surreal {
-        debug!(target: LOG_TARGET, "Killing hanging Surreal sidecar on port 5004 with PID {}", &surreal);
-        shutdown_process(surreal).unwrap_or_default();
-    }
-}
@@ -0,0 +1,306 @@
+use log::{debug, warn};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+#[cfg(target_os = "windows")]
+use std::os::windows::process::CommandExt;
+
+use crate::ports::ServicePorts;
+
+mod test_process_killer;
+
+static LOG_TARGET: &str = "Process Killer";
+
+/// How long a killed sidecar gets to release its port before the ports are resolved
+static RELEASE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Listening state in `/proc/net/tcp`
+#[cfg(any(target_os = "linux", test))]
+static TCP_LISTEN: &str = "0A";
+
+/// PID of the running sidecar is kept in `<name>.pid` next to global_keys.db
+fn pid_file_path(name: &str) -> String {
+    format!("{}.pid", name)
+}
+
+/// Remember the PID of a started sidecar, only tracked processes are killed on the next start
+pub fn write_pid_file(name: &str, process_id: u32) {
+    if let Err(err) = fs::write(pid_file_path(name), process_id.to_string()) {
+        warn!(target: LOG_TARGET, "Failed to write PID file of {}: {:?}", name, err);
+    }
+}
+
+pub fn remove_pid_file(name: &str) {
+    fs::remove_file(pid_file_path(name)).unwrap_or_default();
+}
+
+fn read_pid_file(name: &str) -> Option<u32> {
+    fs::read_to_string(pid_file_path(name))
+        .ok()
+        .and_then(|process_id| process_id.trim().parse().ok())
+}
+
+/// Whether an executable path or image name is the binary of the `name` sidecar
+///
+/// Sidecar binaries keep their name with an optional target triple and extension, e.g.
+/// `surreal-x86_64-unknown-linux-gnu` or `surreal.exe`.
+pub fn is_sidecar_binary(executable: &str, name: &str) -> bool {
+    let file_name = Path::new(executable.trim())
+        .file_name()
+        .map(|file_name| file_name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    match file_name.strip_prefix(name) {
+        Some(rest) => rest.is_empty() || rest.starts_with('-') || rest.starts_with('.'),
+        None => false,
+    }
+}
+
+/// Inodes of the sockets listening on `port` in the contents of `/proc/net/tcp` or `/proc/net/tcp6`
+#[cfg(any(target_os = "linux", test))]
+pub fn parse_listening_inodes(contents: &str, port: u16) -> Vec<u64> {
+    contents
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            // sl local_address rem_address st tx_queue:rx_queue tr:tm->when retrnsmt uid timeout inode
+            let local_address = fields.get(1)?;
+            let state = fields.get(3)?;
+            let inode = fields.get(9)?;
+
+            let local_port = local_address.rsplit(':').next()?;
+            let local_port = u16::from_str_radix(local_port, 16).ok()?;
+
+            match local_port == port && *state == TCP_LISTEN {
+                true => inode.parse().ok(),
+                false => None,
+            }
+        })
+        .filter(|inode| *inode != 0)
+        .collect()
+}
+
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn shutdown_process(process_id: u32) -> Result<(), std::io::Error> {
+    let output = Command::new("kill")
+        .arg("-TERM")
+        .arg(process_id.to_string())
+        .output()?;
+
+    if output.status.success() {
+        debug!(target: LOG_TARGET, "Process {} killed", process_id);
+    } else {
+        debug!(target: LOG_TARGET, "Failed to kill process {}", process_id);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn find_process_on_port(port: u16) -> Option<u32> {
+    debug!(target: LOG_TARGET, "Finding processes listening on port {}", port);
+
+    let output = Command::new("lsof")
+        .arg("-t")
+        .arg("-i")
+        .arg(format!(":{}", port))
+        .output()
+        .expect("Failed to execute lsof command");
+
+    if output.stdout.is_empty() {
+        debug!(target: LOG_TARGET, "No process is listening on port {}", port);
+        None
+    } else {
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse::<u32>()
+            .ok()
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn process_executable(process_id: u32) -> Option<String> {
+    let output = Command::new("ps")
+        .arg("-p")
+        .arg(process_id.to_string())
+        .arg("-o")
+        .arg("comm=")
+        .output()
+        .ok()?;
+
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(target_os = "linux")]
+fn find_process_on_port(port: u16) -> Option<u32> {
+    debug!(target: LOG_TARGET, "Finding processes listening on port {}", port);
+
+    let inodes: Vec<u64> = ["/proc/net/tcp", "/proc/net/tcp6"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .flat_map(|contents| parse_listening_inodes(&contents, port))
+        .collect();
+
+    if inodes.is_empty() {
+        debug!(target: LOG_TARGET, "No process is listening on port {}", port);
+        return None;
+    }
+
+    let sockets: Vec<String> = inodes
+        .iter()
+        .map(|inode| format!("socket:[{}]", inode))
+        .collect();
+
+    // The process holding the socket has a file descriptor linking to it
+    for process in fs::read_dir("/proc").ok()?.flatten() {
+        let Some(process_id) = process
+            .file_name()
+            .to_str()
+            .and_then(|pid| pid.parse().ok())
+        else {
+            continue;
+        };
+
+        let Ok(descriptors) = fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+
+        for descriptor in descriptors.flatten() {
+            if let Ok(target) = fs::read_link(descriptor.path()) {
+                if sockets.contains(&target.to_string_lossy().to_string()) {
+                    return Some(process_id);
+                }
+            }
+        }
+    }
+
+    debug!(target: LOG_TARGET, "No visible process owns the socket on port {}", port);
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn process_executable(process_id: u32) -> Option<String> {
+    fs::read_link(format!("/proc/{}/exe", process_id))
+        .ok()
+        .map(|executable| {
+            executable
+                .to_string_lossy()
+                .trim_end_matches(" (deleted)")
+                .to_string()
+        })
+}
+
+#[cfg(target_os = "windows")]
+fn shutdown_process(process_id: u32) -> Result<(), std::io::Error> {
+    let output;
+
+    output = Command::new("taskkill")
+        .arg("/PID")
+        .arg(process_id.to_string())
+        .creation_flags(0x08000000)
+        .output()
+        .expect("Failed to execute lsof command");
+
+    if output.status.success() {
+        debug!(target: LOG_TARGET, "Process {} killed", process_id);
+    } else {
+        debug!(target: LOG_TARGET, "Failed to kill process {}", process_id);
+    }
+
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn find_process_on_port(port: u16) -> Option<u32> {
+    let output = Command::new("netstat")
+        .arg("-ano")
+        .arg("-p")
+        .arg("TCP")
+        .creation_flags(0x08000000)
+        .output()
+        .expect("Failed to execute netstat command");
+
+    if output.stdout.is_empty() {
+        debug!(target: LOG_TARGET, "No process is listening on port {}", port);
+        None
+    } else {
+        let output_str = String::from_utf8_lossy(&output.stdout);
+
+        let lines: Vec<&str> = output_str.split("\n").collect();
+        for line in lines {
+            if line.contains(&format!(":{}", port)) {
+                debug!(target: LOG_TARGET, "Process found on port {}: {}", port, line);
+                let parts: Vec<&str> = line.split_whitespace().collect();
+
+                // The last part should be the PID
+                if let Some(pid) = parts.last() {
+                    if let Ok(pid_number) = pid.parse::<u32>() {
+                        return Some(pid_number);
+                    }
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn process_executable(process_id: u32) -> Option<String> {
+    let output = Command::new("tasklist")
+        .arg("/FI")
+        .arg(format!("PID eq {}", process_id))
+        .arg("/FO")
+        .arg("CSV")
+        .arg("/NH")
+        .creation_flags(0x08000000)
+        .output()
+        .ok()?;
+
+    // "surreal.exe","1234","Console","1","12,345 K"
+    String::from_utf8_lossy(&output.stdout)
+        .split(',')
+        .next()
+        .map(|image_name| image_name.trim().trim_matches('"').to_string())
+}
+
+/// Kill sidecars left running by a previous run, `ports` are the ports that run used
+pub fn kill_hanging_sidecars(ports: &ServicePorts) {
+    for (name, port) in [
+        ("whatsapp-bot", ports.whatsapp),
+        ("surreal", ports.database),
+    ] {
+        let tracked = read_pid_file(name);
+
+        match find_process_on_port(port) {
+            Some(process_id) if Some(process_id) == tracked => {
+                let executable = process_executable(process_id).unwrap_or_default();
+
+                if is_sidecar_binary(&executable, name) {
+                    debug!(target: LOG_TARGET, "Killing hanging {} sidecar on port {} with PID {}", name, port, process_id);
+                    shutdown_process(process_id).unwrap_or_default();
+
+                    let killed_at = Instant::now();
+                    while find_process_on_port(port).is_some()
+                        && killed_at.elapsed() < RELEASE_TIMEOUT
+                    {
+                        sleep(Duration::from_millis(100));
+                    }
+                } else {
+                    warn!(target: LOG_TARGET, "PID {} on port {} is {}, not the {} sidecar, leaving it running", process_id, port, executable, name);
+                }
+            }
+            Some(process_id) => {
+                warn!(target: LOG_TARGET, "Port {} is used by PID {} which was not started as the {} sidecar, leaving it running", port, process_id, name);
+            }
+            None => {}
+        }
+
+        remove_pid_file(name);
+    }
+}
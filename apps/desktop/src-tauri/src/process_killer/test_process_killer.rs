@@ -0,0 +1,51 @@
+#[cfg(test)]
+mod tests {
+    use crate::process_killer::{is_sidecar_binary, parse_listening_inodes};
+
+    static PROC_NET_TCP: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:138C 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41235 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:138B 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41236 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:C35A 0100007F:138C 01 00000000:00000000 00:00000000 00000000  1000        0 41240 1 0000000000000000 20 4 30 10 -1
+   3: 0100007F:138C 0100007F:C35A 06 00000000:00000000 03:00000DA5 00000000     0        0 0 3 0000000000000000";
+
+    static PROC_NET_TCP6: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:138B 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 52001 1 0000000000000000 100 0 0 10 0";
+
+    #[test]
+    fn test_parse_listening_inodes() {
+        // 0x138C is 5004, connections to or from the port are not listening sockets
+        assert_eq!(parse_listening_inodes(PROC_NET_TCP, 5004), vec![41235]);
+        assert_eq!(parse_listening_inodes(PROC_NET_TCP, 5003), vec![41236]);
+        assert_eq!(
+            parse_listening_inodes(PROC_NET_TCP, 5005),
+            Vec::<u64>::new()
+        );
+    }
+
+    #[test]
+    fn test_parse_listening_inodes_ipv6() {
+        assert_eq!(parse_listening_inodes(PROC_NET_TCP6, 5003), vec![52001]);
+    }
+
+    #[test]
+    fn test_is_sidecar_binary() {
+        assert!(is_sidecar_binary("/opt/magmooty/surreal", "surreal"));
+        assert!(is_sidecar_binary(
+            "/opt/magmooty/surreal-x86_64-unknown-linux-gnu",
+            "surreal"
+        ));
+        assert!(is_sidecar_binary("surreal.exe", "surreal"));
+        assert!(is_sidecar_binary(
+            "/Applications/Magmooty.app/Contents/MacOS/whatsapp-bot",
+            "whatsapp-bot"
+        ));
+    }
+
+    #[test]
+    fn test_is_not_sidecar_binary() {
+        assert!(!is_sidecar_binary("/usr/bin/surrealist", "surreal"));
+        assert!(!is_sidecar_binary("/usr/bin/python3", "surreal"));
+        assert!(!is_sidecar_binary("/opt/surreal/bin/node", "surreal"));
+        assert!(!is_sidecar_binary("", "surreal"));
+    }
+}
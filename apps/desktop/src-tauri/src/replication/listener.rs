@@ -0,0 +1,103 @@
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use rustls::ServerConfig;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio::time::timeout;
+use tokio_rustls::TlsAcceptor;
+
+use super::tls::master_tls_config;
+use super::{local_error, ReplicationError, CONNECT_TIMEOUT, LOG_TARGET};
+use crate::app::GlobalKeyStore;
+use crate::network_discovery::InstanceType;
+use crate::ports::service_ports;
+
+/// TLS config of the last center private key, issuing a certificate takes an RSA signature
+static MASTER_TLS_CONFIG: Lazy<Mutex<Option<(String, Arc<ServerConfig>)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+/// TLS config of the replication listener, `None` unless the local center is a master
+async fn current_tls_config() -> Result<Option<Arc<ServerConfig>>, ReplicationError> {
+    let store = GlobalKeyStore::shared().await.map_err(local_error)?;
+
+    if !matches!(store.instance_type().await, Ok(InstanceType::Master)) {
+        return Ok(None);
+    }
+
+    let Some(private_key) = store.private_key().await.map_err(local_error)? else {
+        return Ok(None);
+    };
+
+    let mut cached = MASTER_TLS_CONFIG.lock().await;
+
+    if let Some((cached_key, config)) = cached.as_ref() {
+        if *cached_key == private_key {
+            return Ok(Some(config.clone()));
+        }
+    }
+
+    let config = Arc::new(master_tls_config(&private_key)?);
+    *cached = Some((private_key, config.clone()));
+
+    Ok(Some(config))
+}
+
+/// Terminate TLS and pass the connection to the local database
+async fn handle_connection(stream: TcpStream, address: SocketAddr) -> Result<(), ReplicationError> {
+    let Some(config) = current_tls_config().await? else {
+        debug!(target: LOG_TARGET, "Dropping replication connection from {}, not a master", address);
+        return Ok(());
+    };
+
+    let mut stream = timeout(CONNECT_TIMEOUT, TlsAcceptor::from(config).accept(stream))
+        .await
+        .map_err(|_| ReplicationError::Failed("TLS handshake timed out".to_string()))?
+        .map_err(local_error)?;
+
+    let mut database = TcpStream::connect(SocketAddrV4::new(
+        Ipv4Addr::LOCALHOST,
+        service_ports().database,
+    ))
+    .await
+    .map_err(local_error)?;
+
+    debug!(target: LOG_TARGET, "Replication connection from {}", address);
+    tokio::io::copy_bidirectional(&mut stream, &mut database)
+        .await
+        .map_err(local_error)?;
+
+    Ok(())
+}
+
+/// Serve the local database to paired slaves over TLS with a certificate issued with the center
+/// key, the database itself only listens on the loopback interface
+pub async fn start_replication_listener() {
+    let port = service_ports().replication;
+
+    info!(target: LOG_TARGET, "Binding replication listener to 0.0.0.0:{}", port);
+    let listener = match TcpListener::bind(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, port)).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to bind replication listener: {:?}", err);
+            return;
+        }
+    };
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, address)) => {
+                tokio::spawn(async move {
+                    if let Err(err) = handle_connection(stream, address).await {
+                        warn!(target: LOG_TARGET, "Replication connection from {} failed: {}", address, err);
+                    }
+                });
+            }
+            Err(err) => {
+                warn!(target: LOG_TARGET, "Failed to accept replication connection: {:?}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,846 @@
+use std::collections::{HashMap, HashSet};
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use surrealdb::engine::any::Any;
+use surrealdb::opt::auth::Database;
+use surrealdb::opt::Config;
+use surrealdb::sql::{self, Datetime, Thing};
+use surrealdb::Surreal;
+use tauri::Window;
+use tokio::time::{sleep, timeout, Duration};
+
+use crate::app::{connect_as_root, GlobalKey, GlobalKeyStore};
+use crate::network_discovery::{discover_network, InstanceType};
+use crate::ports::DEFAULT_REPLICATION_PORT;
+use crate::sync::{apply_change, has_newer_local_change, RetryPolicy, SYNC_RETENTION};
+
+mod listener;
+mod test_replication;
+mod test_tls;
+mod tls;
+
+pub use listener::*;
+
+static LOG_TARGET: &str = "Replication";
+
+/// Time between two successful replication runs
+static REPLICATION_INTERVAL: Duration = Duration::from_secs(5);
+
+static CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Number of master changes pulled at once
+static PULL_LIMIT: i64 = 500;
+
+/// Number of queued local changes forwarded in one run
+static FORWARD_LIMIT: i64 = 500;
+
+/// Number of records copied at once during a snapshot
+static SNAPSHOT_PAGE_SIZE: i64 = 500;
+
+/// Definitions replicated from `INFO FOR DB`, in the order they are applied. Users are left out,
+/// the replica keeps its own root user.
+static DATABASE_DEFINITIONS: [&str; 6] = [
+    "analyzers",
+    "functions",
+    "params",
+    "scopes",
+    "tokens",
+    "tables",
+];
+
+/// Definitions replicated from `INFO FOR TABLE`, applied once every table is defined
+static TABLE_DEFINITIONS: [&str; 3] = ["fields", "indexes", "events"];
+
+/// Position in the master `sync` table, changes are ordered by `created_at` then `id`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ReplicationCursor {
+    pub created_at: Datetime,
+    /// `None` right after a snapshot, every change made since `created_at` is pulled
+    pub id: Option<Thing>,
+}
+
+/// Row of the master `sync` table, kept as SurrealDB values so datetimes and records in the
+/// content are written to the replica as they are
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplicatedChange {
+    pub id: Thing,
+    pub record_id: Thing,
+    pub event: String,
+    pub content: sql::Value,
+    pub created_at: Datetime,
+}
+
+impl ReplicatedChange {
+    pub fn from_value(value: sql::Value) -> Option<Self> {
+        let sql::Value::Object(mut row) = value else {
+            return None;
+        };
+
+        let (
+            Some(sql::Value::Thing(id)),
+            Some(sql::Value::Thing(record_id)),
+            Some(sql::Value::Strand(event)),
+            Some(sql::Value::Datetime(created_at)),
+        ) = (
+            row.remove("id"),
+            row.remove("record_id"),
+            row.remove("event"),
+            row.remove("created_at"),
+        )
+        else {
+            return None;
+        };
+
+        Some(Self {
+            id,
+            record_id,
+            event: event.0,
+            content: row.remove("content").unwrap_or_default(),
+            created_at,
+        })
+    }
+}
+
+/// Unforwarded row of the local `sync` table
+#[derive(Deserialize, Debug, Clone)]
+pub struct QueuedWrite {
+    pub id: Thing,
+    pub record_id: Thing,
+}
+
+/// Queued writes of one record, forwarded as the current state of the record
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForwardedWrite {
+    pub record_id: Thing,
+    pub ids: Vec<Thing>,
+}
+
+/// Reason a replication run stopped early
+#[derive(Debug)]
+pub enum ReplicationError {
+    /// The master could not be reached
+    Offline(String),
+    Failed(String),
+}
+
+impl std::fmt::Display for ReplicationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplicationError::Offline(error) | ReplicationError::Failed(error) => {
+                f.write_str(error)
+            }
+        }
+    }
+}
+
+/// Errors of queries sent to the master, connection errors mean the master is offline
+fn master_error(error: surrealdb::Error) -> ReplicationError {
+    match error {
+        surrealdb::Error::Api(_) => ReplicationError::Offline(error.to_string()),
+        _ => ReplicationError::Failed(error.to_string()),
+    }
+}
+
+fn local_error(error: impl std::fmt::Debug) -> ReplicationError {
+    ReplicationError::Failed(format!("{:?}", error))
+}
+
+/// Group queued writes by record, ordered by the last write of every record
+///
+/// Writes have to be ordered by `created_at`.
+pub fn group_queued_writes(queued_writes: Vec<QueuedWrite>) -> Vec<ForwardedWrite> {
+    // Index of the last write of every record along with the grouped writes
+    let mut writes: Vec<(usize, ForwardedWrite)> = Vec::new();
+    let mut indexes: HashMap<String, usize> = HashMap::new();
+
+    for (position, queued_write) in queued_writes.into_iter().enumerate() {
+        match indexes.get(&queued_write.record_id.to_string()) {
+            Some(&index) => {
+                let (last, write) = &mut writes[index];
+                *last = position;
+                write.ids.push(queued_write.id);
+            }
+            None => {
+                indexes.insert(queued_write.record_id.to_string(), writes.len());
+                writes.push((
+                    position,
+                    ForwardedWrite {
+                        record_id: queued_write.record_id,
+                        ids: vec![queued_write.id],
+                    },
+                ));
+            }
+        }
+    }
+
+    writes.sort_by_key(|(last, _)| *last);
+    writes.into_iter().map(|(_, write)| write).collect()
+}
+
+/// Definition statements of `categories` in an `INFO FOR ...` result
+pub fn definitions(info: &sql::Value, categories: &[&str]) -> Vec<String> {
+    let sql::Value::Object(info) = info else {
+        return Vec::new();
+    };
+
+    categories
+        .iter()
+        .filter_map(|category| match info.get(*category) {
+            Some(sql::Value::Object(definitions)) => Some(definitions),
+            _ => None,
+        })
+        .flat_map(|definitions| definitions.values())
+        .filter_map(|definition| match definition {
+            sql::Value::Strand(definition) => Some(definition.0.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Replica records the master no longer has, `master_ids` are the master records of the same
+/// range of ids
+pub fn deleted_records(local_ids: Vec<Thing>, master_ids: &[Thing]) -> Vec<Thing> {
+    let master_ids: HashSet<String> = master_ids.iter().map(|id| id.to_string()).collect();
+
+    local_ids
+        .into_iter()
+        .filter(|id| !master_ids.contains(&id.to_string()))
+        .collect()
+}
+
+/// Names of the tables in an `INFO FOR DB` result, names that would need escaping are skipped
+pub fn table_names(info: &sql::Value) -> Vec<String> {
+    let sql::Value::Object(info) = info else {
+        return Vec::new();
+    };
+
+    match info.get("tables") {
+        Some(sql::Value::Object(tables)) => tables
+            .keys()
+            .filter(|table| {
+                !table.is_empty()
+                    && table
+                        .chars()
+                        .all(|char| char.is_ascii_alphanumeric() || char == '_')
+            })
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Where the master database runs, the key it proves itself with and the user issued while
+/// pairing
+struct MasterCredentials {
+    address: String,
+    port: u16,
+    public_key: String,
+    username: String,
+    password: String,
+}
+
+/// Address and database user of the master if the local center is a paired slave
+async fn find_master_credentials() -> Option<MasterCredentials> {
+    let store = match GlobalKeyStore::shared().await {
+        Ok(store) => store,
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to open global keys: {:?}", err);
+            return None;
+        }
+    };
+
+    if !matches!(store.instance_type().await, Ok(InstanceType::Slave)) {
+        debug!(target: LOG_TARGET, "Local center is not a slave");
+        return None;
+    }
+
+    let address = store.get(GlobalKey::MasterAddress).await.ok().flatten()?;
+    let port = store
+        .get(GlobalKey::MasterReplicationPort)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|port| port.parse().ok())
+        .unwrap_or(DEFAULT_REPLICATION_PORT);
+    let public_key = store.public_key().await.ok().flatten()?;
+    let username = store
+        .get(GlobalKey::DatabaseUsername)
+        .await
+        .ok()
+        .flatten()?;
+    let password = store
+        .get(GlobalKey::DatabasePassword)
+        .await
+        .ok()
+        .flatten()?;
+
+    Some(MasterCredentials {
+        address,
+        port,
+        public_key,
+        username,
+        password,
+    })
+}
+
+/// Connect to the master database with the user issued while pairing
+///
+/// The connection only goes through when the master proves it holds the center private key, the
+/// credentials and the replicated data never reach anyone else.
+async fn connect_to_master(
+    credentials: &MasterCredentials,
+) -> Result<Surreal<Any>, ReplicationError> {
+    let surreal: Surreal<Any> = Surreal::init();
+    let url = format!("wss://{}:{}/rpc", credentials.address, credentials.port);
+    let config = Config::new().rustls(tls::slave_tls_config(&credentials.public_key)?);
+
+    debug!(target: LOG_TARGET, "Connecting to the master at {}", url);
+    timeout(CONNECT_TIMEOUT, surreal.connect((url, config)))
+        .await
+        .map_err(|_| ReplicationError::Offline("Connection timed out".to_string()))?
+        .map_err(|err| ReplicationError::Offline(err.to_string()))?;
+
+    surreal
+        .signin(Database {
+            namespace: "local",
+            database: "local",
+            username: &credentials.username,
+            password: &credentials.password,
+        })
+        .await
+        .map_err(master_error)?;
+
+    surreal
+        .use_ns("local")
+        .use_db("local")
+        .await
+        .map_err(master_error)?;
+
+    Ok(surreal)
+}
+
+/// Run `statement` on `record_ids` of the replica and drop the sync rows the `*_syncer` events
+/// create for them, replicated writes are never forwarded back to the master
+async fn apply_replicated(
+    local: &Surreal<Any>,
+    statement: &str,
+    bindings: impl Serialize,
+    record_ids: &[Thing],
+) -> Result<(), surrealdb::Error> {
+    local
+        .query(format!(
+            "BEGIN TRANSACTION;
+            LET $applied_at = time::now();
+            {};
+            DELETE sync WHERE record_id IN $replicated_ids AND created_at >= $applied_at;
+            COMMIT TRANSACTION;",
+            statement
+        ))
+        .bind(bindings)
+        .bind(("replicated_ids", record_ids))
+        .await
+        .and_then(|response| response.check())?;
+
+    Ok(())
+}
+
+/// Whether the master changed the record after the cursor, changes the replica hasn't pulled yet
+///
+/// Both sides of the comparison come from the master `sync` table, the clocks of the replica and
+/// the master are never compared. Without a cursor every master change is unseen.
+async fn has_unseen_master_change(
+    master: &Surreal<Any>,
+    record_id: &Thing,
+    cursor: Option<&ReplicationCursor>,
+) -> Result<bool, ReplicationError> {
+    let master_change: Option<Thing> = master
+        .query("SELECT VALUE id FROM sync WHERE record_id = $record_id AND (created_at > $created_at OR (created_at = $created_at AND id > $id)) LIMIT 1")
+        .bind(("record_id", record_id))
+        .bind(("created_at", cursor.map(|cursor| &cursor.created_at)))
+        .bind(("id", cursor.and_then(|cursor| cursor.id.as_ref())))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(master_error)?;
+
+    Ok(master_change.is_some())
+}
+
+/// Send writes made on the replica while the master was away to the master
+///
+/// A record is forwarded as its current local state. Records the master changed since the
+/// replica last pulled are left alone, the master version is pulled right after.
+async fn forward_queued_writes(
+    local: &Surreal<Any>,
+    master: &Surreal<Any>,
+    window: &Window,
+    cursor: Option<&ReplicationCursor>,
+) -> Result<(), ReplicationError> {
+    let queued_writes: Vec<QueuedWrite> = local
+        .query("SELECT id, record_id, created_at FROM sync WHERE pushed = false ORDER BY created_at LIMIT $limit")
+        .bind(("limit", FORWARD_LIMIT))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(local_error)?;
+
+    if queued_writes.is_empty() {
+        return Ok(());
+    }
+
+    let writes = group_queued_writes(queued_writes);
+    info!(target: LOG_TARGET, "Forwarding {} queued writes to the master", writes.len());
+
+    let mut forwarded = 0;
+
+    for write in writes {
+        match has_unseen_master_change(master, &write.record_id, cursor).await? {
+            true => {
+                debug!(target: LOG_TARGET, "Skipping {}, the master has a newer change", write.record_id);
+            }
+            false => {
+                let record: sql::Value = local
+                    .query("SELECT * FROM ONLY $record_id")
+                    .bind(("record_id", &write.record_id))
+                    .await
+                    .and_then(|mut response| response.take(0))
+                    .map_err(local_error)?;
+
+                let statement = match record.is_none_or_null() {
+                    true => "DELETE $record_id",
+                    false => "UPDATE $record_id CONTENT $content",
+                };
+
+                master
+                    .query(statement)
+                    .bind(("record_id", &write.record_id))
+                    .bind(("content", record))
+                    .await
+                    .and_then(|response| response.check())
+                    .map_err(master_error)?;
+
+                forwarded += 1;
+            }
+        }
+
+        local
+            .query("UPDATE $ids SET pushed = true")
+            .bind(("ids", &write.ids))
+            .await
+            .and_then(|response| response.check())
+            .map_err(local_error)?;
+    }
+
+    info!(target: LOG_TARGET, "Forwarded {} writes", forwarded);
+    window
+        .emit("replication_forwarded", forwarded)
+        .unwrap_or_default();
+
+    Ok(())
+}
+
+/// Tables of a database and the statements defining its schema
+pub async fn read_schema(
+    surreal: &Surreal<Any>,
+) -> Result<(Vec<String>, Vec<String>), surrealdb::Error> {
+    let database_info: sql::Value = surreal
+        .query("INFO FOR DB")
+        .await
+        .and_then(|mut response| response.take(0))?;
+
+    let tables = table_names(&database_info);
+    let mut statements = definitions(&database_info, &DATABASE_DEFINITIONS);
+
+    for table in &tables {
+        let table_info: sql::Value = surreal
+            .query(format!("INFO FOR TABLE {}", table))
+            .await
+            .and_then(|mut response| response.take(0))?;
+
+        statements.extend(definitions(&table_info, &TABLE_DEFINITIONS));
+    }
+
+    Ok((tables, statements))
+}
+
+/// Define the master schema on the replica, returns the replicated tables
+async fn replicate_schema(
+    local: &Surreal<Any>,
+    master: &Surreal<Any>,
+) -> Result<Vec<String>, ReplicationError> {
+    let (tables, statements) = read_schema(master).await.map_err(master_error)?;
+
+    debug!(target: LOG_TARGET, "Applying {} schema definitions", statements.len());
+    local
+        .query(statements.join(";\n"))
+        .await
+        .and_then(|response| response.check())
+        .map_err(local_error)?;
+
+    Ok(tables)
+}
+
+/// Copy the whole master database to the replica, returns the cursor to pull changes from
+async fn replicate_snapshot(
+    local: &Surreal<Any>,
+    master: &Surreal<Any>,
+    window: &Window,
+) -> Result<ReplicationCursor, ReplicationError> {
+    info!(target: LOG_TARGET, "Replicating a snapshot of the master");
+    window
+        .emit("replication_snapshot_started", "")
+        .unwrap_or_default();
+
+    // Taken first so changes made during the snapshot are pulled again afterwards
+    let started_at: Option<Datetime> = master
+        .query("RETURN time::now()")
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(master_error)?;
+
+    let started_at = started_at
+        .ok_or_else(|| ReplicationError::Failed("Master returned no time".to_string()))?;
+
+    let tables = replicate_schema(local, master).await?;
+
+    for table in tables.iter().filter(|table| table.as_str() != "sync") {
+        let mut replicated = 0;
+        // Pages follow the ids instead of an offset, records written meanwhile don't shift them
+        let mut last_id: Option<Thing> = None;
+
+        loop {
+            let records: sql::Value = master
+                .query("SELECT * FROM type::table($table) WHERE id > $last_id ORDER BY id LIMIT $limit")
+                .bind(("table", table))
+                .bind(("last_id", &last_id))
+                .bind(("limit", SNAPSHOT_PAGE_SIZE))
+                .await
+                .and_then(|mut response| response.take(0))
+                .map_err(master_error)?;
+
+            let records = match records {
+                sql::Value::Array(records) => records,
+                _ => sql::Array::new(),
+            };
+
+            let is_last_page = (records.len() as i64) < SNAPSHOT_PAGE_SIZE;
+            let master_ids: Vec<Thing> = records
+                .iter()
+                .filter_map(|record| match record {
+                    sql::Value::Object(record) => match record.get("id") {
+                        Some(sql::Value::Thing(id)) => Some(id.clone()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect();
+
+            // Replica records of the same range of ids, the last page covers the rest of the table
+            let until = match is_last_page {
+                true => None,
+                false => master_ids.last().cloned(),
+            };
+
+            let local_ids: Vec<Thing> = local
+                .query("SELECT VALUE id FROM type::table($table) WHERE id > $last_id AND ($until = NONE OR id <= $until)")
+                .bind(("table", table))
+                .bind(("last_id", &last_id))
+                .bind(("until", &until))
+                .await
+                .and_then(|mut response| response.take(0))
+                .map_err(local_error)?;
+
+            // Records deleted on the master while the replica was out of date
+            let deleted = deleted_records(local_ids, &master_ids);
+            if !deleted.is_empty() {
+                apply_replicated(local, "DELETE $ids", ("ids", &deleted), &deleted)
+                    .await
+                    .map_err(local_error)?;
+            }
+
+            replicated += master_ids.len();
+
+            apply_replicated(
+                local,
+                "FOR $record IN $records { UPDATE $record.id CONTENT $record; }",
+                ("records", records),
+                &master_ids,
+            )
+            .await
+            .map_err(local_error)?;
+
+            if is_last_page {
+                break;
+            }
+
+            last_id = until;
+        }
+
+        debug!(target: LOG_TARGET, "Replicated {} records of {}", replicated, table);
+    }
+
+    Ok(ReplicationCursor {
+        created_at: started_at,
+        id: None,
+    })
+}
+
+async fn load_cursor(
+    store: &GlobalKeyStore,
+) -> Result<Option<ReplicationCursor>, ReplicationError> {
+    match store
+        .get(GlobalKey::ReplicationCursor)
+        .await
+        .map_err(local_error)?
+    {
+        None => Ok(None),
+        Some(cursor) => serde_json::from_str(&cursor).map(Some).map_err(local_error),
+    }
+}
+
+async fn store_cursor(
+    store: &GlobalKeyStore,
+    cursor: &ReplicationCursor,
+) -> Result<(), ReplicationError> {
+    let cursor = serde_json::to_string(cursor).map_err(local_error)?;
+
+    store
+        .set(GlobalKey::ReplicationCursor, cursor)
+        .await
+        .map_err(local_error)
+}
+
+/// Whether changes after the cursor may have been pruned from the master `sync` table
+async fn is_cursor_stale(
+    master: &Surreal<Any>,
+    cursor: &ReplicationCursor,
+) -> Result<bool, ReplicationError> {
+    let stale: Option<bool> = master
+        .query("RETURN $created_at < time::now() - $retention")
+        .bind(("created_at", &cursor.created_at))
+        .bind(("retention", sql::Duration::from(SYNC_RETENTION)))
+        .await
+        .and_then(|mut response| response.take(0))
+        .map_err(master_error)?;
+
+    Ok(stale.unwrap_or(true))
+}
+
+/// Apply the changes made on the master since the cursor to the replica
+async fn pull_master_changes(
+    local: &Surreal<Any>,
+    master: &Surreal<Any>,
+    store: &GlobalKeyStore,
+    window: &Window,
+    mut cursor: ReplicationCursor,
+) -> Result<(), ReplicationError> {
+    let mut applied = 0;
+
+    loop {
+        let rows: sql::Value = master
+            .query("SELECT * FROM sync WHERE created_at > $created_at OR (created_at = $created_at AND id > $id) ORDER BY created_at, id LIMIT $limit")
+            .bind(("created_at", &cursor.created_at))
+            .bind(("id", &cursor.id))
+            .bind(("limit", PULL_LIMIT))
+            .await
+            .and_then(|mut response| response.take(0))
+            .map_err(master_error)?;
+
+        let changes: Vec<ReplicatedChange> = match rows {
+            sql::Value::Array(rows) => rows
+                .into_iter()
+                .filter_map(ReplicatedChange::from_value)
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let count = changes.len() as i64;
+
+        for change in changes {
+            if has_newer_local_change(local, &change.record_id, &change.created_at)
+                .await
+                .map_err(local_error)?
+            {
+                debug!(target: LOG_TARGET, "Skipping {}, a newer local write will be forwarded", change.record_id);
+            } else {
+                apply_change(local, &change.record_id, &change.event, change.content)
+                    .await
+                    .map_err(local_error)?;
+                applied += 1;
+            }
+
+            // Advance change by change so a failure resumes from the failed change
+            cursor = ReplicationCursor {
+                created_at: change.created_at,
+                id: Some(change.id),
+            };
+            store_cursor(store, &cursor).await?;
+        }
+
+        if count < PULL_LIMIT {
+            break;
+        }
+    }
+
+    if applied > 0 {
+        info!(target: LOG_TARGET, "Applied {} changes from the master", applied);
+        window
+            .emit("replication_pulled", applied)
+            .unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+/// Forward queued writes to the master then bring the replica up to date
+async fn replicate(
+    local: &Surreal<Any>,
+    master: &Surreal<Any>,
+    window: &Window,
+) -> Result<(), ReplicationError> {
+    let store = GlobalKeyStore::shared().await.map_err(local_error)?;
+    let cursor = load_cursor(&store).await?;
+
+    // Forwarded first so pulled changes never overwrite writes the master hasn't seen
+    forward_queued_writes(local, master, window, cursor.as_ref()).await?;
+
+    let cursor = match cursor {
+        Some(cursor) if !is_cursor_stale(master, &cursor).await? => cursor,
+        _ => {
+            let cursor = replicate_snapshot(local, master, window).await?;
+            store_cursor(&store, &cursor).await?;
+            cursor
+        }
+    };
+
+    pull_master_changes(local, master, &store, window, cursor).await?;
+
+    if let Err(err) = local
+        .query("DELETE sync WHERE pushed = true AND created_at < time::now() - $retention")
+        .bind(("retention", sql::Duration::from(SYNC_RETENTION)))
+        .await
+        .and_then(|response| response.check())
+    {
+        // Pruning only saves space, replication can go on
+        warn!(target: LOG_TARGET, "Error pruning forwarded writes: {:?}", err);
+    }
+
+    Ok(())
+}
+
+/// Emit `replication_offline` / `replication_online` when reachability of the master changes
+fn set_offline(window: &Window, offline: &mut bool, is_offline: bool) {
+    if *offline == is_offline {
+        return;
+    }
+
+    *offline = is_offline;
+
+    match is_offline {
+        true => {
+            warn!(target: LOG_TARGET, "Master is unreachable, serving the local replica");
+            window.emit("replication_offline", "").unwrap_or_default();
+        }
+        false => {
+            info!(target: LOG_TARGET, "Master is reachable again");
+            window.emit("replication_online", "").unwrap_or_default();
+        }
+    }
+}
+
+/// Keeps the local database of a slave replicated from the master
+///
+/// The app always reads and writes the local database, so a slave keeps working while the master
+/// is away. Writes made meanwhile stay in the local `sync` table until they are forwarded.
+pub struct Replicator {
+    retry_policy: RetryPolicy,
+}
+
+impl Replicator {
+    pub fn new() -> Self {
+        Self {
+            retry_policy: RetryPolicy {
+                base_delay: Duration::from_secs(2),
+                max_delay: Duration::from_secs(60),
+                ..RetryPolicy::default()
+            },
+        }
+    }
+
+    pub async fn start_replicating(&self, window: Window) {
+        debug!(target: LOG_TARGET, "Connecting to SurrealDB");
+        let local = connect_as_root()
+            .await
+            .expect("Failed to connect to SurrealDB");
+
+        let retry_policy = self.retry_policy.clone();
+
+        debug!(target: LOG_TARGET, "Spawning replication task");
+        tokio::spawn(async move {
+            let mut master: Option<Surreal<Any>> = None;
+            let mut delay = REPLICATION_INTERVAL;
+            let mut failures = 0;
+            let mut offline = false;
+
+            loop {
+                sleep(delay).await;
+
+                let Some(credentials) = find_master_credentials().await else {
+                    master = None;
+                    delay = REPLICATION_INTERVAL;
+                    continue;
+                };
+
+                let result = match master.take() {
+                    Some(connection) => Ok(connection),
+                    None => connect_to_master(&credentials).await,
+                };
+
+                let result = match result {
+                    Ok(connection) => replicate(&local, &connection, &window)
+                        .await
+                        .map(|_| connection),
+                    Err(error) => Err(error),
+                };
+
+                delay = match result {
+                    Ok(connection) => {
+                        master = Some(connection);
+                        failures = 0;
+                        set_offline(&window, &mut offline, false);
+                        REPLICATION_INTERVAL
+                    }
+                    Err(error) => {
+                        // The connection is dropped, the next run reconnects in case it broke
+                        failures += 1;
+                        set_offline(
+                            &window,
+                            &mut offline,
+                            matches!(error, ReplicationError::Offline(_)),
+                        );
+
+                        // The master may have moved to another port, discovery stores the
+                        // port it advertises before the next run
+                        if matches!(error, ReplicationError::Offline(_)) {
+                            if let Err(err) = discover_network().await {
+                                warn!(target: LOG_TARGET, "Failed to look for the master: {:?}", err);
+                            }
+                        }
+
+                        let delay = retry_policy.delay(failures);
+                        warn!(
+                            target: LOG_TARGET,
+                            "Replication failed {} time(s) in a row ({}), retrying in {:?}",
+                            failures,
+                            error,
+                            delay
+                        );
+                        window
+                            .emit("replication_failed", error.to_string())
+                            .unwrap_or_default();
+                        delay
+                    }
+                };
+            }
+        });
+    }
+}
@@ -0,0 +1,184 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use surrealdb::sql::{self, Datetime, Thing};
+
+    use crate::replication::{
+        definitions, deleted_records, group_queued_writes, table_names, QueuedWrite,
+        ReplicatedChange, ReplicationCursor,
+    };
+
+    fn datetime(value: &str) -> Datetime {
+        Datetime::try_from(value).unwrap()
+    }
+
+    fn object(entries: Vec<(&str, sql::Value)>) -> sql::Value {
+        entries
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect::<BTreeMap<String, sql::Value>>()
+            .into()
+    }
+
+    fn queued_write(id: &str, record_id: &str) -> QueuedWrite {
+        QueuedWrite {
+            id: Thing::from(("sync", id)),
+            record_id: Thing::from(("student", record_id)),
+        }
+    }
+
+    #[test]
+    fn test_replicated_change_from_value() {
+        let content = object(vec![
+            ("name", sql::Value::from("Ahmed")),
+            ("enrolled_at", datetime("2024-01-01T10:00:00Z").into()),
+        ]);
+
+        let row = object(vec![
+            ("id", Thing::from(("sync", "a")).into()),
+            ("record_id", Thing::from(("student", "ahmed")).into()),
+            ("event", sql::Value::from("UPDATE")),
+            ("content", content.clone()),
+            ("created_at", datetime("2024-01-01T10:00:01Z").into()),
+            ("pushed", sql::Value::from(false)),
+        ]);
+
+        let change = ReplicatedChange::from_value(row).unwrap();
+        assert_eq!(change.id, Thing::from(("sync", "a")));
+        assert_eq!(change.record_id, Thing::from(("student", "ahmed")));
+        assert_eq!(change.event, "UPDATE");
+        assert_eq!(change.content, content);
+        assert_eq!(change.created_at, datetime("2024-01-01T10:00:01Z"));
+    }
+
+    #[test]
+    fn test_replicated_change_without_content() {
+        let row = object(vec![
+            ("id", Thing::from(("sync", "a")).into()),
+            ("record_id", Thing::from(("student", "ahmed")).into()),
+            ("event", sql::Value::from("DELETE")),
+            ("created_at", datetime("2024-01-01T10:00:01Z").into()),
+        ]);
+
+        let change = ReplicatedChange::from_value(row).unwrap();
+        assert_eq!(change.content, sql::Value::None);
+    }
+
+    #[test]
+    fn test_invalid_replicated_change() {
+        let row = object(vec![
+            ("id", Thing::from(("sync", "a")).into()),
+            ("record_id", sql::Value::from("student:ahmed")),
+            ("event", sql::Value::from("UPDATE")),
+            ("created_at", datetime("2024-01-01T10:00:01Z").into()),
+        ]);
+
+        assert_eq!(ReplicatedChange::from_value(row), None);
+        assert_eq!(ReplicatedChange::from_value(sql::Value::None), None);
+    }
+
+    #[test]
+    fn test_group_queued_writes() {
+        let writes = group_queued_writes(vec![
+            queued_write("a", "ahmed"),
+            queued_write("b", "mona"),
+            queued_write("c", "ahmed"),
+        ]);
+
+        assert_eq!(writes.len(), 2);
+
+        // Ordered by the last write of every record
+        assert_eq!(writes[0].record_id, Thing::from(("student", "mona")));
+        assert_eq!(writes[0].ids, vec![Thing::from(("sync", "b"))]);
+
+        assert_eq!(writes[1].record_id, Thing::from(("student", "ahmed")));
+        assert_eq!(
+            writes[1].ids,
+            vec![Thing::from(("sync", "a")), Thing::from(("sync", "c"))]
+        );
+    }
+
+    #[test]
+    fn test_definitions_follow_categories() {
+        let info = object(vec![
+            (
+                "tables",
+                object(vec![(
+                    "student",
+                    sql::Value::from("DEFINE TABLE student SCHEMAFULL"),
+                )]),
+            ),
+            (
+                "functions",
+                object(vec![(
+                    "age",
+                    sql::Value::from("DEFINE FUNCTION fn::age() { RETURN 1; }"),
+                )]),
+            ),
+            (
+                "users",
+                object(vec![(
+                    "device_a",
+                    sql::Value::from("DEFINE USER device_a ON DATABASE"),
+                )]),
+            ),
+        ]);
+
+        assert_eq!(
+            definitions(&info, &["functions", "tables"]),
+            vec![
+                "DEFINE FUNCTION fn::age() { RETURN 1; }".to_string(),
+                "DEFINE TABLE student SCHEMAFULL".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deleted_records() {
+        let deleted = deleted_records(
+            vec![
+                Thing::from(("student", "ahmed")),
+                Thing::from(("student", "mona")),
+                Thing::from(("student", "omar")),
+            ],
+            &[
+                Thing::from(("student", "ahmed")),
+                Thing::from(("student", "omar")),
+            ],
+        );
+
+        assert_eq!(deleted, vec![Thing::from(("student", "mona"))]);
+    }
+
+    #[test]
+    fn test_table_names_skip_names_needing_escaping() {
+        let info = object(vec![(
+            "tables",
+            object(vec![
+                ("student", sql::Value::from("DEFINE TABLE student")),
+                ("sync", sql::Value::from("DEFINE TABLE sync")),
+                ("bad name", sql::Value::from("DEFINE TABLE `bad name`")),
+            ]),
+        )]);
+
+        assert_eq!(
+            table_names(&info),
+            vec!["student".to_string(), "sync".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = ReplicationCursor {
+            created_at: datetime("2024-01-01T10:00:00Z"),
+            id: Some(Thing::from(("sync", "a"))),
+        };
+
+        let json = serde_json::to_string(&cursor).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ReplicationCursor>(&json).unwrap(),
+            cursor
+        );
+    }
+}
@@ -0,0 +1,66 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rustls::ServerName;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+    use crate::app::{generate_key_pair, KeyPair};
+    use crate::replication::tls::{master_tls_config, slave_tls_config};
+
+    /// Whether a slave trusting `public_key` reads what a master with `private_key` sends
+    async fn handshake(private_key: &str, public_key: &str) -> bool {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(master_tls_config(private_key).unwrap()));
+
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            if let Ok(mut stream) = acceptor.accept(stream).await {
+                stream.write_all(b"ok").await.unwrap_or_default();
+                stream.flush().await.unwrap_or_default();
+            }
+        });
+
+        let connector = TlsConnector::from(Arc::new(slave_tls_config(public_key).unwrap()));
+        let stream = TcpStream::connect(address).await.unwrap();
+
+        // Slaves connect by address, the master certificate doesn't name it
+        match connector
+            .connect(ServerName::IpAddress(address.ip()), stream)
+            .await
+        {
+            Ok(mut stream) => {
+                let mut reply = [0; 2];
+                stream.read_exact(&mut reply).await.is_ok() && &reply == b"ok"
+            }
+            Err(_) => false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slave_trusts_master_of_center() {
+        let KeyPair {
+            private_key,
+            public_key,
+        } = generate_key_pair().await;
+
+        assert!(handshake(&private_key, &public_key).await);
+    }
+
+    #[tokio::test]
+    async fn test_slave_refuses_other_center() {
+        let center = generate_key_pair().await;
+        let other_center = generate_key_pair().await;
+
+        assert!(!handshake(&other_center.private_key, &center.public_key).await);
+    }
+
+    #[test]
+    fn test_invalid_center_key() {
+        assert!(master_tls_config("not a key").is_err());
+        assert!(slave_tls_config("not a key").is_err());
+    }
+}
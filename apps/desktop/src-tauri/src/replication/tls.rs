@@ -0,0 +1,148 @@
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use base64::Engine;
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, KeyPair,
+    PKCS_RSA_SHA256,
+};
+use rsa::pkcs1::{DecodeRsaPrivateKey, DecodeRsaPublicKey};
+use rsa::pkcs8::{EncodePrivateKey, EncodePublicKey};
+use rsa::{RsaPrivateKey, RsaPublicKey};
+use rustls::client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier};
+use rustls::{ClientConfig, OwnedTrustAnchor, PrivateKey, RootCertStore, ServerConfig, ServerName};
+
+use super::{local_error, ReplicationError};
+
+/// Common name of the certificate made from the center key, master certificates are issued
+/// under it
+static CENTER_COMMON_NAME: &str = "Magmooty center";
+
+/// Name in master certificates, slaves connect by address so every master uses the same name
+static MASTER_SERVER_NAME: &str = "master.magmooty";
+
+/// DER element of `tag` around `content`, only short contents are encoded here
+fn der_element(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut element = vec![tag, content.len() as u8];
+    element.extend(content);
+    element
+}
+
+/// Content of a DER element without its tag and length
+fn der_content(element: &[u8]) -> Result<&[u8], ReplicationError> {
+    let header_len = match element.get(1) {
+        Some(len) if len & 0x80 == 0 => 2,
+        Some(len) => 2 + (len & 0x7f) as usize,
+        None => return Err(ReplicationError::Failed("Empty DER element".to_string())),
+    };
+
+    element
+        .get(header_len..)
+        .ok_or_else(|| ReplicationError::Failed("Truncated DER element".to_string()))
+}
+
+/// Subject of the center certificate without its outer sequence, as rcgen encodes it
+fn center_subject() -> Vec<u8> {
+    // Object identifier of the common name attribute
+    let mut attribute = der_element(0x06, &[0x55, 0x04, 0x03]);
+    attribute.extend(der_element(0x0c, CENTER_COMMON_NAME.as_bytes()));
+
+    der_element(0x31, &der_element(0x30, &attribute))
+}
+
+/// TLS config of the master replication listener with the center private key (base64 PKCS#1
+/// DER), the certificate is issued for a key made for this run
+pub fn master_tls_config(private_key: &str) -> Result<ServerConfig, ReplicationError> {
+    let private_key = base64::prelude::BASE64_STANDARD
+        .decode(private_key)
+        .map_err(local_error)?;
+    let private_key = RsaPrivateKey::from_pkcs1_der(&private_key)
+        .map_err(local_error)?
+        .to_pkcs8_der()
+        .map_err(local_error)?;
+
+    let mut center_params = CertificateParams::new(Vec::new());
+    center_params.alg = &PKCS_RSA_SHA256;
+    center_params.key_pair = Some(KeyPair::from_der(private_key.as_bytes()).map_err(local_error)?);
+    center_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    center_params.distinguished_name = DistinguishedName::new();
+    center_params
+        .distinguished_name
+        .push(DnType::CommonName, CENTER_COMMON_NAME);
+    let center_certificate = Certificate::from_params(center_params).map_err(local_error)?;
+
+    let master_certificate =
+        Certificate::from_params(CertificateParams::new(vec![MASTER_SERVER_NAME.to_string()]))
+            .map_err(local_error)?;
+    let master_certificate_der = master_certificate
+        .serialize_der_with_signer(&center_certificate)
+        .map_err(local_error)?;
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![rustls::Certificate(master_certificate_der)],
+            PrivateKey(master_certificate.serialize_private_key_der()),
+        )
+        .map_err(local_error)
+}
+
+/// Accepts certificates issued with the center key, whatever address the slave connected to
+struct CenterCertificateVerifier {
+    verifier: WebPkiVerifier,
+}
+
+impl ServerCertVerifier for CenterCertificateVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &rustls::Certificate,
+        intermediates: &[rustls::Certificate],
+        _server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let server_name = ServerName::try_from(MASTER_SERVER_NAME)
+            .map_err(|_| rustls::Error::General("Invalid master name".to_string()))?;
+
+        self.verifier.verify_server_cert(
+            end_entity,
+            intermediates,
+            &server_name,
+            scts,
+            ocsp_response,
+            now,
+        )
+    }
+}
+
+/// TLS config of a slave, only masters holding the private key of the center public key
+/// (base64 PKCS#1 DER) are trusted
+pub fn slave_tls_config(public_key: &str) -> Result<ClientConfig, ReplicationError> {
+    let public_key = base64::prelude::BASE64_STANDARD
+        .decode(public_key)
+        .map_err(local_error)?;
+    let public_key = RsaPublicKey::from_pkcs1_der(&public_key)
+        .map_err(local_error)?
+        .to_public_key_der()
+        .map_err(local_error)?;
+
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(std::iter::once(
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            center_subject(),
+            der_content(public_key.as_bytes())?,
+            None::<Vec<u8>>,
+        ),
+    ));
+
+    let verifier = CenterCertificateVerifier {
+        verifier: WebPkiVerifier::new(roots, None),
+    };
+
+    Ok(ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth())
+}
@@ -0,0 +1,40 @@
+use std::net::SocketAddr;
+
+use tokio::net::TcpStream;
+use tokio::time::{timeout, Duration};
+
+static HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How a supervised sidecar is checked to be up and serving
+#[derive(Debug, Clone)]
+pub enum HealthCheck {
+    /// GET request answered with a success status
+    Http(String),
+    /// Port accepting connections
+    Tcp(SocketAddr),
+}
+
+impl HealthCheck {
+    pub async fn check(&self) -> bool {
+        match self {
+            HealthCheck::Http(url) => {
+                let client = match reqwest::Client::builder()
+                    .timeout(HEALTH_CHECK_TIMEOUT)
+                    .build()
+                {
+                    Ok(client) => client,
+                    Err(_) => return false,
+                };
+
+                match client.get(url).send().await {
+                    Ok(response) => response.status().is_success(),
+                    Err(_) => false,
+                }
+            }
+            HealthCheck::Tcp(address) => matches!(
+                timeout(HEALTH_CHECK_TIMEOUT, TcpStream::connect(address)).await,
+                Ok(Ok(_))
+            ),
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use tauri::api::process::{Command, CommandEvent};
+use tauri::Window;
+use tokio::sync::{broadcast, oneshot};
+use tokio::time::{sleep, Duration, Instant};
+
+use crate::process_killer::{remove_pid_file, write_pid_file};
+use crate::sync::RetryPolicy;
+use crate::SIDECARS;
+
+mod health;
+mod test_sidecar;
+
+pub use health::*;
+
+static LOG_TARGET: &str = "Sidecar supervisor";
+
+/// How long a sidecar has to pass its first health check
+static STARTUP_TIMEOUT: Duration = Duration::from_secs(30);
+
+static STARTUP_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+static HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Health checks failed in a row before a running sidecar is restarted
+static MAX_FAILED_HEALTH_CHECKS: u32 = 3;
+
+/// Failed starts in a row before the first start is reported as failed, the supervisor keeps
+/// retrying in the background
+static STARTUP_ATTEMPTS: u32 = 3;
+
+/// Set when the app is closing so terminated sidecars are not restarted
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+/// Last status of every supervised sidecar, by sidecar name
+static STATUSES: Lazy<std::sync::Mutex<HashMap<String, SidecarStatus>>> =
+    Lazy::new(|| std::sync::Mutex::new(HashMap::new()));
+
+static STATUS_CHANGES: Lazy<broadcast::Sender<SidecarStatusChange>> =
+    Lazy::new(|| broadcast::channel(16).0);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type, PartialEq)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SidecarStatus {
+    Starting,
+    Running,
+    /// Still running but failing health checks
+    Unhealthy {
+        failed_checks: u32,
+    },
+    /// Waiting `delay_secs` before starting again after `attempt` failures in a row
+    Restarting {
+        attempt: u32,
+        delay_secs: u32,
+    },
+    Stopped,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Type)]
+pub struct SidecarStatusChange {
+    pub sidecar: String,
+    pub status: SidecarStatus,
+}
+
+#[derive(Debug)]
+pub enum SidecarError {
+    /// The sidecar never passed its health check in `STARTUP_ATTEMPTS` attempts
+    StartFailed,
+}
+
+pub struct SidecarConfig {
+    /// Name of the sidecar binary
+    pub name: &'static str,
+    /// Name used in logs
+    pub label: &'static str,
+    pub args: Vec<String>,
+    /// Added to the environment of the app, keeps secrets out of the process arguments
+    pub envs: HashMap<String, String>,
+    pub health_check: HealthCheck,
+}
+
+fn set_status(sidecar: &str, status: SidecarStatus) {
+    debug!(target: LOG_TARGET, "{} is {:?}", sidecar, status);
+
+    if let Ok(mut statuses) = STATUSES.lock() {
+        statuses.insert(sidecar.to_string(), status.clone());
+    }
+
+    STATUS_CHANGES
+        .send(SidecarStatusChange {
+            sidecar: sidecar.to_string(),
+            status,
+        })
+        .unwrap_or_default();
+}
+
+pub fn sidecar_statuses() -> HashMap<String, SidecarStatus> {
+    STATUSES
+        .lock()
+        .map(|statuses| statuses.clone())
+        .unwrap_or_default()
+}
+
+/// Stop restarting sidecars, called before they are killed on exit
+pub fn stop_supervising() {
+    STOPPING.store(true, Ordering::SeqCst);
+}
+
+/// Forward sidecar status changes to the window as `sidecar_status` events
+pub async fn emit_sidecar_statuses(window: Window) {
+    let mut changes = STATUS_CHANGES.subscribe();
+
+    loop {
+        match changes.recv().await {
+            Ok(change) => window.emit("sidecar_status", change).unwrap_or_default(),
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        }
+    }
+}
+
+/// Print the sidecar output and report when it terminates
+async fn forward_logs(
+    label: &'static str,
+    mut events: tauri::async_runtime::Receiver<CommandEvent>,
+    terminated: oneshot::Sender<()>,
+) {
+    while let Some(event) = events.recv().await {
+        match event {
+            CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
+                print!("{}", line);
+            }
+            CommandEvent::Error(error) => {
+                error!(target: LOG_TARGET, "{} sidecar error: {}", label, error);
+            }
+            CommandEvent::Terminated(payload) => {
+                warn!(target: LOG_TARGET, "{} sidecar terminated with code {:?}", label, payload.code);
+                break;
+            }
+            _ => {
+                warn!(target: LOG_TARGET, "Unhandled {} sidecar event: {:?}", label, event);
+            }
+        }
+    }
+
+    terminated.send(()).unwrap_or_default();
+}
+
+/// Keeps a sidecar running, restarting it with backoff when it terminates or stops answering
+/// its health check
+pub struct SidecarSupervisor {
+    config: SidecarConfig,
+    retry_policy: RetryPolicy,
+}
+
+impl SidecarSupervisor {
+    pub fn new(config: SidecarConfig) -> Self {
+        Self {
+            config,
+            retry_policy: RetryPolicy {
+                base_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(60),
+                ..RetryPolicy::default()
+            },
+        }
+    }
+
+    /// Start supervising the sidecar, resolves once it is healthy or failed to start
+    /// `STARTUP_ATTEMPTS` times in a row
+    pub async fn start(self) -> Result<(), SidecarError> {
+        let name = self.config.name;
+        let mut changes = STATUS_CHANGES.subscribe();
+
+        tokio::spawn(self.supervise());
+
+        loop {
+            let status = match changes.recv().await {
+                Ok(change) if change.sidecar == name => change.status,
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(_)) => match sidecar_statuses().get(name) {
+                    Some(status) => status.clone(),
+                    None => continue,
+                },
+                Err(broadcast::error::RecvError::Closed) => return Err(SidecarError::StartFailed),
+            };
+
+            match status {
+                SidecarStatus::Running => return Ok(()),
+                SidecarStatus::Restarting { attempt, .. } if attempt >= STARTUP_ATTEMPTS => {
+                    return Err(SidecarError::StartFailed)
+                }
+                SidecarStatus::Stopped => return Err(SidecarError::StartFailed),
+                _ => {}
+            }
+        }
+    }
+
+    async fn supervise(self) {
+        let name = self.config.name;
+        let mut failures = 0;
+
+        loop {
+            set_status(name, SidecarStatus::Starting);
+
+            if self.run().await {
+                failures = 0;
+            }
+
+            if STOPPING.load(Ordering::SeqCst) {
+                set_status(name, SidecarStatus::Stopped);
+                return;
+            }
+
+            failures += 1;
+            let delay = self.retry_policy.delay(failures);
+
+            warn!(
+                target: LOG_TARGET,
+                "{} sidecar stopped {} time(s) in a row, restarting in {:?}",
+                self.config.label,
+                failures,
+                delay
+            );
+            set_status(
+                name,
+                SidecarStatus::Restarting {
+                    attempt: failures,
+                    delay_secs: delay.as_secs() as u32,
+                },
+            );
+
+            sleep(delay).await;
+        }
+    }
+
+    /// Run the sidecar until it terminates or fails its health checks, returns whether it
+    /// became healthy
+    async fn run(&self) -> bool {
+        let SidecarConfig {
+            name,
+            label,
+            args,
+            envs,
+            health_check,
+        } = &self.config;
+
+        let spawned = Command::new_sidecar(*name)
+            .and_then(|command| command.args(args).envs(envs.clone()).spawn());
+
+        let (events, child) = match spawned {
+            Ok(spawned) => spawned,
+            Err(err) => {
+                error!(target: LOG_TARGET, "Failed to run {} sidecar: {:?}", label, err);
+                return false;
+            }
+        };
+
+        info!(target: LOG_TARGET, "Started {} sidecar with PID {}", label, child.pid());
+        write_pid_file(name, child.pid());
+
+        if let Ok(mut sidecars) = SIDECARS.lock() {
+            sidecars.insert(*name, child);
+        }
+
+        let (terminated_tx, mut terminated) = oneshot::channel();
+        tokio::spawn(forward_logs(*label, events, terminated_tx));
+
+        debug!(target: LOG_TARGET, "Waiting for {} sidecar to pass its health check", label);
+        let started_at = Instant::now();
+        let healthy = loop {
+            tokio::select! {
+                _ = &mut terminated => break false,
+                _ = sleep(STARTUP_POLL_INTERVAL) => {
+                    if health_check.check().await {
+                        break true;
+                    }
+
+                    if started_at.elapsed() > STARTUP_TIMEOUT {
+                        error!(target: LOG_TARGET, "{} sidecar did not become healthy in {:?}", label, STARTUP_TIMEOUT);
+                        break false;
+                    }
+                }
+            }
+        };
+
+        if healthy {
+            info!(target: LOG_TARGET, "{} sidecar is healthy", label);
+            set_status(name, SidecarStatus::Running);
+
+            let mut failed_checks = 0;
+
+            loop {
+                tokio::select! {
+                    _ = &mut terminated => break,
+                    _ = sleep(HEALTH_CHECK_INTERVAL) => {
+                        if health_check.check().await {
+                            if failed_checks > 0 {
+                                info!(target: LOG_TARGET, "{} sidecar is healthy again", label);
+                                set_status(name, SidecarStatus::Running);
+                            }
+
+                            failed_checks = 0;
+                            continue;
+                        }
+
+                        failed_checks += 1;
+                        warn!(target: LOG_TARGET, "{} sidecar failed {} health check(s) in a row", label, failed_checks);
+                        set_status(name, SidecarStatus::Unhealthy { failed_checks });
+
+                        if failed_checks >= MAX_FAILED_HEALTH_CHECKS {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Killed in case it is still running but stuck
+        let child = SIDECARS
+            .lock()
+            .ok()
+            .and_then(|mut sidecars| sidecars.remove(name));
+        if let Some(child) = child {
+            child.kill().unwrap_or_default();
+        }
+
+        remove_pid_file(name);
+
+        healthy
+    }
+}
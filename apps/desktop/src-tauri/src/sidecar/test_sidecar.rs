@@ -0,0 +1,69 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use crate::sidecar::{HealthCheck, SidecarStatus};
+
+    /// Answer every request with `status_line` and an empty body
+    async fn serve(status_line: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buffer = [0; 1024];
+                stream.read(&mut buffer).await.unwrap_or_default();
+                stream
+                    .write_all(
+                        format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status_line).as_bytes(),
+                    )
+                    .await
+                    .unwrap_or_default();
+            }
+        });
+
+        format!("http://{}/health", address)
+    }
+
+    #[tokio::test]
+    async fn test_http_health_check() {
+        let url = serve("200 OK").await;
+        assert!(HealthCheck::Http(url).check().await);
+    }
+
+    #[tokio::test]
+    async fn test_http_health_check_error_status() {
+        let url = serve("503 Service Unavailable").await;
+        assert!(!HealthCheck::Http(url).check().await);
+    }
+
+    #[tokio::test]
+    async fn test_tcp_health_check() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        assert!(HealthCheck::Tcp(address).check().await);
+
+        drop(listener);
+        assert!(!HealthCheck::Tcp(address).check().await);
+    }
+
+    #[test]
+    fn test_status_serialization() {
+        assert_eq!(
+            serde_json::to_value(SidecarStatus::Restarting {
+                attempt: 2,
+                delay_secs: 4
+            })
+            .unwrap(),
+            json!({ "state": "restarting", "attempt": 2, "delay_secs": 4 })
+        );
+
+        assert_eq!(
+            serde_json::to_value(SidecarStatus::Running).unwrap(),
+            json!({ "state": "running" })
+        );
+    }
+}
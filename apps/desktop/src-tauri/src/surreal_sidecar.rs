@@ -1,118 +1,50 @@
-use std::sync::Arc;
+use std::collections::HashMap;
 
-use crate::{app, SIDECARS};
-use log::{debug, error, info, warn};
-use tauri::api::process::{Command, CommandEvent};
-use tokio::sync::{mpsc::Receiver, oneshot::Sender, Mutex};
+use log::{debug, error, info};
 
-static LOG_TARGET: &str = "Surreal sidecar";
-
-async fn listen_for_start_log(
-    mut tx: Option<Sender<Result<(), ()>>>,
-    logs_rx: Arc<Mutex<Receiver<CommandEvent>>>,
-) {
-    let mut logs_rx = logs_rx.lock().await;
-
-    while let Some(event) = logs_rx.recv().await {
-        match event {
-            CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
-                print!("{}", line);
+use crate::app;
+use crate::ports::service_ports;
+use crate::sidecar::{HealthCheck, SidecarConfig, SidecarSupervisor};
 
-                // Compare every line until the server is started
-                if line.contains("Started web server") {
-                    if let Some(tx) = tx.take() {
-                        tx.send(Ok(())).unwrap();
+static LOG_TARGET: &str = "Surreal sidecar";
 
-                        // Break the loop to avoid expensive operations, we'll start the listener again with less logic
-                        return;
-                    }
-                } else if line.contains("ERROR") {
-                    if let Some(tx) = tx.take() {
-                        tx.send(Err(())).unwrap();
+pub async fn run_surreal_sidecar() {
+    let credentials = app::root_database_credentials()
+        .await
+        .expect("Failed to load root database credentials");
+
+    let port = service_ports().database;
+
+    let supervisor = SidecarSupervisor::new(SidecarConfig {
+        name: "surreal",
+        label: "SurrealDB",
+        args: vec![
+            "start".to_string(),
+            "--log".to_string(),
+            "info".to_string(),
+            // Without it every connection is root, whatever the credentials
+            "--auth".to_string(),
+            "--bind".to_string(),
+            // Slaves go through the TLS endpoint of the replication listener
+            format!("127.0.0.1:{}", port),
+            "file:rocksdb".to_string(),
+        ],
+        // Arguments can be read by any user through the process list
+        envs: HashMap::from([
+            ("SURREAL_USER".to_string(), credentials.username),
+            ("SURREAL_PASS".to_string(), credentials.password),
+        ]),
+        health_check: HealthCheck::Http(format!("http://127.0.0.1:{}/health", port)),
+    });
 
-                        // Break the loop to avoid expensive operations, we'll start the listener again with less logic
-                        return;
-                    }
-                }
-            }
-            CommandEvent::Error(error) => {
-                error!("{}", error);
-            }
-            CommandEvent::Terminated(_) => {
-                error!("SurrealDB sidecar terminated");
-            }
-            _ => {
-                warn!("Unhandled SurrealDB sidecar event: {:?}", event);
-            }
-        }
+    debug!(target: LOG_TARGET, "Waiting for SurrealDB sidecar to start");
+    if let Err(error) = supervisor.start().await {
+        panic!("Failed to start SurrealDB sidecar: {:?}", error);
     }
-}
 
-async fn listen_for_log(logs_rx: Arc<Mutex<Receiver<CommandEvent>>>) {
-    info!(target: LOG_TARGET, "Listening for SurrealDB sidecar logs");
-    let mut logs_rx = logs_rx.lock().await;
+    info!(target: LOG_TARGET, "SurrealDB sidecar started successfully");
 
-    while let Some(event) = logs_rx.recv().await {
-        match event {
-            CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
-                print!("{}", line);
-            }
-            CommandEvent::Error(error) => {
-                error!("{}", error);
-            }
-            CommandEvent::Terminated(_) => {
-                panic!("SurrealDB sidecar terminated");
-            }
-            _ => {
-                warn!("Unhandled SurrealDB sidecar event: {:?}", event);
-            }
-        }
+    if let Err(error) = app::migrate_legacy_root_user().await {
+        error!(target: LOG_TARGET, "Failed to replace the legacy root user: {:?}", error);
     }
 }
-
-pub async fn run_surreal_sidecar() {
-    let credentials = app::get_root_database_credentials();
-
-    let (logs_rx, process) = Command::new_sidecar("surreal")
-        .expect("Failed to run SurrealDB sidecar")
-        .args([
-            "start",
-            "--log",
-            "info",
-            "--user",
-            credentials.username.as_str(),
-            "--pass",
-            credentials.password.as_str(),
-            "--bind",
-            "0.0.0.0:5004",
-            "file:rocksdb",
-        ])
-        .spawn()
-        .expect("Failed to run SurrealDB sidecar");
-
-    SIDECARS.lock().unwrap().push(process);
-
-    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), ()>>();
-
-    let tx = Some(tx);
-    let logs_rx = Arc::new(Mutex::new(logs_rx));
-
-    let start_log_logs_rx = logs_rx.clone();
-
-    tokio::spawn(async move {
-        listen_for_start_log(tx, start_log_logs_rx).await;
-    });
-
-    debug!(target: LOG_TARGET, "Waiting for SurrealDB sidecar to start");
-    match rx.await {
-        Ok(Ok(())) => {
-            info!(target: LOG_TARGET, "SurrealDB sidecar started successfully");
-            tokio::spawn(async move {
-                listen_for_log(logs_rx.clone()).await;
-            });
-        }
-        Ok(Err(())) | Err(_) => {
-            panic!("Failed to start SurrealDB sidecar");
-        }
-    };
-}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use log::debug;
+use surrealdb::sql::Thing;
+
+use super::SyncEvent;
+
+static LOG_TARGET: &str = "Sync compaction";
+
+/// Unpushed events left to upload once the events of every record are coalesced
+#[derive(Debug, Default)]
+pub struct CompactedSyncEvents {
+    pub events: Vec<SyncEvent>,
+    /// IDs of rows of records created and deleted before being pushed, central never needs them
+    pub cancelled: Vec<Thing>,
+}
+
+/// Coalesce unpushed events of the same record into one event per record
+///
+/// Events have to be ordered by `created_at`. The compacted event carries the content of the last
+/// change and takes its place in the queue, the IDs of the folded rows are kept in `compacted` so
+/// they are marked as pushed along with it.
+///
+/// - CREATE followed by UPDATEs becomes a CREATE
+/// - CREATE followed by a DELETE is dropped, central never received the record
+/// - anything else followed by a DELETE becomes a DELETE
+/// - repeated UPDATEs become the last UPDATE
+pub fn compact_sync_events(sync_events: Vec<SyncEvent>) -> CompactedSyncEvents {
+    let total = sync_events.len();
+
+    // Index of the last event of every record, the compacted event is placed there
+    let mut last_index: HashMap<String, usize> = HashMap::new();
+    for (index, sync_event) in sync_events.iter().enumerate() {
+        last_index.insert(sync_event.record_id.to_string(), index);
+    }
+
+    let mut pending: HashMap<String, SyncEvent> = HashMap::new();
+    let mut compacted = CompactedSyncEvents {
+        events: Vec::with_capacity(last_index.len()),
+        cancelled: Vec::new(),
+    };
+
+    for (index, sync_event) in sync_events.into_iter().enumerate() {
+        let record_id = sync_event.record_id.to_string();
+
+        let sync_event = match pending.remove(&record_id) {
+            None => sync_event,
+            Some(previous) => match merge(previous, sync_event, &mut compacted.cancelled) {
+                Some(sync_event) => sync_event,
+                None => continue,
+            },
+        };
+
+        if last_index.get(&record_id) == Some(&index) {
+            compacted.events.push(sync_event);
+        } else {
+            pending.insert(record_id, sync_event);
+        }
+    }
+
+    debug!(
+        target: LOG_TARGET,
+        "Compacted {} events into {}, {} cancel out",
+        total,
+        compacted.events.len(),
+        compacted.cancelled.len()
+    );
+
+    compacted
+}
+
+/// Fold `next` into `previous`, `None` when the two cancel out and their IDs went to `cancelled`
+fn merge(previous: SyncEvent, next: SyncEvent, cancelled: &mut Vec<Thing>) -> Option<SyncEvent> {
+    let mut compacted = previous.compacted;
+    compacted.push(previous.id);
+
+    let event = match (previous.event.as_str(), next.event.as_str()) {
+        ("CREATE", "DELETE") => {
+            cancelled.extend(compacted);
+            cancelled.push(next.id);
+            return None;
+        }
+        (_, "DELETE") => "DELETE",
+        ("CREATE", _) => "CREATE",
+        (_, event) => event,
+    };
+
+    Some(SyncEvent {
+        event: event.to_string(),
+        compacted,
+        ..next
+    })
+}
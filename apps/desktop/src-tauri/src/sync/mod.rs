@@ -1,196 +1,462 @@
 use log::{debug, error, info, warn};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use surrealdb::sql::{Datetime, Thing};
+use surrealdb::engine::any::Any;
+use surrealdb::sql::{self, Datetime, Thing};
 use surrealdb::Surreal;
 use tauri::Window;
+use tokio::sync::Notify;
 use tokio::time::{sleep, Duration};
 
-use crate::app::{get_global_key, GlobalKey};
+use crate::app::{
+    connect_as_root, finish_sync_run, interrupt_sync_runs, start_sync_run, GlobalKeyStore,
+};
 use crate::central::{CentralAPI, CheckSyncAvailabilityError, SyncUploadChunkError};
+use crate::network_discovery::InstanceType;
+
+mod compaction;
+mod pull;
+mod retry;
+
+mod test_compaction;
+
+pub use compaction::*;
+pub use pull::*;
+pub use retry::*;
 
 static LOG_TARGET: &str = "Sync";
 
+/// Time between two successful sync runs
+static SYNC_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Number of unpushed changes compacted and uploaded in one run
+static SYNC_BATCH_SIZE: usize = 1000;
+
+/// Uncompressed size a chunk is kept under, a single bigger change is still uploaded on its own
+static SYNC_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Number of changes uploaded in one request at most
+static SYNC_CHUNK_MAX_EVENTS: usize = 500;
+
+/// How long pushed changes are kept, they are still used to detect conflicts with pulled changes
+pub static SYNC_RETENTION: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Wakes the syncer up before its next scheduled run
+static SYNC_NOW: Lazy<Notify> = Lazy::new(Notify::new);
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct SyncEvent {
+    /// ID of the `sync` row, only used locally
+    #[serde(skip_serializing)]
+    id: Thing,
     record_id: Thing,
     event: String,
     content: Value,
     created_at: Datetime,
+    /// IDs of older `sync` rows of the same record folded into this one
+    #[serde(skip)]
+    compacted: Vec<Thing>,
+}
+
+/// Reason a sync run stopped early, details are emitted to the window as they happen
+#[derive(Debug)]
+pub enum SyncRunError {
+    /// Central could not be reached
+    Offline(String),
+    Failed(String),
+}
+
+impl std::fmt::Display for SyncRunError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncRunError::Offline(error) | SyncRunError::Failed(error) => f.write_str(error),
+        }
+    }
+}
+
+/// Changes exchanged with central during a sync run
+#[derive(Debug, Default)]
+pub struct SyncRunReport {
+    pub pushed: u32,
+    pub pulled: u32,
+}
+
+/// Run the syncer now instead of waiting for the next scheduled run
+pub fn request_sync() {
+    SYNC_NOW.notify_one();
+}
+
+/// Emit `sync_offline` / `sync_online` when reachability of central changes
+fn set_offline(window: &Window, offline: &mut bool, is_offline: bool) {
+    if *offline == is_offline {
+        return;
+    }
+
+    *offline = is_offline;
+
+    match is_offline {
+        true => {
+            warn!(target: LOG_TARGET, "Central is unreachable");
+            window.emit("sync_offline", "").unwrap_or_default();
+        }
+        false => {
+            info!(target: LOG_TARGET, "Central is reachable again");
+            window.emit("sync_online", "").unwrap_or_default();
+        }
+    }
+}
+
+/// Split events into chunks of about `SYNC_CHUNK_BYTES` of JSON each, keeping their order
+fn chunk_by_size(sync_events: &[SyncEvent]) -> Vec<&[SyncEvent]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut size = 0;
+
+    for (index, sync_event) in sync_events.iter().enumerate() {
+        let event_size = serde_json::to_vec(sync_event)
+            .map(|json| json.len())
+            .unwrap_or_default();
+
+        if index > start
+            && (size + event_size > SYNC_CHUNK_BYTES || index - start >= SYNC_CHUNK_MAX_EVENTS)
+        {
+            chunks.push(&sync_events[start..index]);
+            start = index;
+            size = 0;
+        }
+
+        size += event_size;
+    }
+
+    if start < sync_events.len() {
+        chunks.push(&sync_events[start..]);
+    }
+
+    chunks
 }
 
-pub struct Syncer {}
+/// Center ID, private key and installation ID if the local center is a master set up for syncing
+async fn find_sync_credentials() -> Option<(String, String, String)> {
+    let store = match GlobalKeyStore::shared().await {
+        Ok(store) => store,
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to open global keys: {:?}", err);
+            return None;
+        }
+    };
+
+    // Check if local center is a master
+    match store.instance_type().await {
+        Ok(InstanceType::Master) => {
+            debug!(target: LOG_TARGET, "Local center is a master");
+        }
+        _ => {
+            debug!(target: LOG_TARGET, "Local center is not a master");
+            return None;
+        }
+    };
+
+    // Find center id
+    debug!(target: LOG_TARGET, "Finding center id");
+    let center_id = match store.center_id().await.ok().flatten() {
+        None => {
+            debug!(target: LOG_TARGET, "No center id set");
+            return None;
+        }
+        Some(center_id) => {
+            debug!(target: LOG_TARGET, "Found center id {}", &center_id);
+            center_id
+        }
+    };
+
+    // Find private key
+    debug!(target: LOG_TARGET, "Finding private key");
+    let private_key = match store.private_key().await.ok().flatten() {
+        None => {
+            debug!(target: LOG_TARGET, "No private key set");
+            return None;
+        }
+        Some(private_key) => private_key,
+    };
+
+    let installation_id = match store.installation_id().await {
+        Ok(installation_id) => installation_id,
+        Err(err) => {
+            error!(target: LOG_TARGET, "Failed to read installation id: {:?}", err);
+            return None;
+        }
+    };
+
+    Some((center_id, private_key, installation_id))
+}
+
+/// Push local changes to central then pull the changes made by other installations
+async fn sync(
+    surreal: &Surreal<Any>,
+    window: &Window,
+    center_id: &String,
+    private_key: &String,
+    installation_id: &String,
+    report: &mut SyncRunReport,
+) -> Result<(), SyncRunError> {
+    let central = CentralAPI::shared().await.map_err(|error| {
+        error!(target: LOG_TARGET, "Invalid central API settings: {:?}", error);
+        SyncRunError::Failed(format!("{:?}", error))
+    })?;
+
+    // Check if sync is available
+    match central
+        .check_sync_availability(center_id, private_key)
+        .await
+    {
+        Ok(_) => {
+            debug!(target: LOG_TARGET, "Sync is available");
+            window.emit("sync_available", "").unwrap_or_default();
+        }
+        Err(error) => {
+            warn!(target: LOG_TARGET, "Sync is not available: {:?}", error);
+            let offline = matches!(error, CheckSyncAvailabilityError::NetworkError);
+            let error = serde_json::to_string(&error).unwrap_or(
+                serde_json::to_string(&CheckSyncAvailabilityError::UnknownError).unwrap(),
+            );
+
+            window
+                .emit("sync_unavailable", error.clone())
+                .unwrap_or_default();
+
+            return Err(match offline {
+                true => SyncRunError::Offline(error),
+                false => SyncRunError::Failed(error),
+            });
+        }
+    };
+
+    debug!(target: LOG_TARGET, "Checking if there are changes to push");
+    window
+        .emit("sync_collecting_changes", "")
+        .unwrap_or_default();
+
+    let sync_events = match surreal
+        .query("SELECT * FROM sync WHERE pushed = false ORDER BY created_at LIMIT $limit")
+        .bind(("limit", SYNC_BATCH_SIZE))
+        .await
+    {
+        Ok(mut response) => match response.take::<Vec<SyncEvent>>(0) {
+            Ok(sync_events) => {
+                info!(target: LOG_TARGET, "Found {} changes to push", sync_events.len());
+                sync_events
+            }
+            Err(err) => {
+                error!(target: LOG_TARGET, "Error parsing changes: {:?}", err);
+                window
+                    .emit("sync_collecting_changes_failed", err.to_string())
+                    .unwrap_or_default();
+                return Err(SyncRunError::Failed(err.to_string()));
+            }
+        },
+        Err(err) => {
+            error!(target: LOG_TARGET, "Error querying changes: {:?}", err);
+            window
+                .emit("sync_collecting_changes_failed", err.to_string())
+                .unwrap_or_default();
+            return Err(SyncRunError::Failed(err.to_string()));
+        }
+    };
+
+    let CompactedSyncEvents {
+        events: sync_events,
+        cancelled,
+    } = compact_sync_events(sync_events);
+
+    // Records created and deleted since the last push have nothing to upload
+    if !cancelled.is_empty() {
+        if let Err(err) = surreal
+            .query("UPDATE $ids SET pushed = true")
+            .bind(("ids", cancelled))
+            .await
+            .and_then(|response| response.check())
+        {
+            error!(target: LOG_TARGET, "Error marking cancelled changes as pushed: {:?}", err);
+            return Err(SyncRunError::Failed(err.to_string()));
+        }
+    }
+
+    window
+        .emit("sync_start", sync_events.len())
+        .unwrap_or_default();
+
+    debug!(target: LOG_TARGET, "Uploading chunks of data");
+    let mut uploaded = 0;
+
+    // Chunks are uploaded in order, the run stops at the first failure so central never
+    // receives a change before the ones made earlier
+    for chunk in chunk_by_size(&sync_events) {
+        if let Err(error) = central
+            .sync_upload_chunk(chunk, private_key, center_id, installation_id)
+            .await
+        {
+            debug!(target: LOG_TARGET, "Sync failed with error: {:?}", error);
+            let offline = matches!(error, SyncUploadChunkError::NetworkError);
+            let error = serde_json::to_string(&error)
+                .unwrap_or(serde_json::to_string(&SyncUploadChunkError::UnknownError).unwrap());
+
+            window
+                .emit("sync_upload_chunk_failed", error.clone())
+                .unwrap_or_default();
+
+            return Err(match offline {
+                true => SyncRunError::Offline(error),
+                false => SyncRunError::Failed(error),
+            });
+        }
+
+        uploaded += chunk.len();
+        debug!(target: LOG_TARGET, "Chunk uploaded");
+        window.emit("sync_progress", uploaded).unwrap_or_default();
+
+        debug!(target: LOG_TARGET, "Marking sync events as uploaded");
+        let ids: Vec<&Thing> = chunk
+            .iter()
+            .flat_map(|sync_event| sync_event.compacted.iter().chain([&sync_event.id]))
+            .collect();
+
+        // Mark the uploaded sync rows themselves, newer changes to the same records made during
+        // the upload stay unpushed
+        if let Err(err) = surreal
+            .query("UPDATE $ids SET pushed = true")
+            .bind(("ids", ids))
+            .await
+            .and_then(|response| response.check())
+        {
+            error!(target: LOG_TARGET, "Error marking sync events as uploaded: {:?}", err);
+            window
+                .emit("sync_upload_chunk_failed", err.to_string())
+                .unwrap_or_default();
+            return Err(SyncRunError::Failed(err.to_string()));
+        }
+
+        report.pushed += chunk.len() as u32;
+        debug!(target: LOG_TARGET, "{} sync events marked as uploaded", chunk.len());
+    }
+
+    debug!(target: LOG_TARGET, "Pruning pushed changes");
+    if let Err(err) = surreal
+        .query("DELETE sync WHERE pushed = true AND created_at < time::now() - $retention")
+        .bind(("retention", sql::Duration::from(SYNC_RETENTION)))
+        .await
+        .and_then(|response| response.check())
+    {
+        // Pruning only saves space, syncing can go on
+        warn!(target: LOG_TARGET, "Error pruning pushed changes: {:?}", err);
+    }
+
+    debug!(target: LOG_TARGET, "Pulling changes from other installations");
+    pull_changes(
+        surreal,
+        window,
+        &central,
+        center_id,
+        private_key,
+        installation_id,
+        report,
+    )
+    .await
+}
+
+pub struct Syncer {
+    retry_policy: RetryPolicy,
+}
 
 impl Syncer {
     pub fn new() -> Self {
-        Self {}
+        Self {
+            retry_policy: RetryPolicy::default(),
+        }
     }
 
     pub async fn start_syncing(&self, window: Window) {
         debug!(target: LOG_TARGET, "Connecting to SurrealDB");
-        let surreal: Surreal<surrealdb::engine::any::Any> = Surreal::init();
+        let surreal = connect_as_root()
+            .await
+            .expect("Failed to connect to SurrealDB");
+
+        let retry_policy = self.retry_policy.clone();
 
-        // Connect to local SurrealDB instance
-        surreal.connect("ws://127.0.0.1:5004/rpc").await.unwrap();
-        surreal.use_ns("local").use_db("local").await.unwrap();
+        // Runs still marked as running were cut short by the app closing
+        interrupt_sync_runs().await;
 
         debug!(target: LOG_TARGET, "Spawning syncing task");
         tokio::spawn(async move {
-            loop {
-                // Run every minute
-                sleep(Duration::from_secs(60)).await;
-
-                debug!(target: LOG_TARGET, "Syncing started");
+            let mut delay = SYNC_INTERVAL;
+            let mut failures = 0;
+            let mut offline = false;
 
-                // Check if local center is a master
-                match get_global_key(GlobalKey::InstanceType)
-                    .await
-                    .unwrap_or_default()
-                    .as_str()
-                {
-                    "master" => {
-                        debug!(target: LOG_TARGET, "Local center is a master");
-                    }
-                    _ => {
-                        debug!(target: LOG_TARGET, "Local center is not a master");
-                        continue;
+            loop {
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = SYNC_NOW.notified() => {
+                        debug!(target: LOG_TARGET, "Sync requested");
                     }
-                };
+                }
 
-                // Find center id
-                debug!(target: LOG_TARGET, "Finding center id");
-                let center_id = match get_global_key(GlobalKey::CenterId).await {
+                let (center_id, private_key, installation_id) = match find_sync_credentials().await
+                {
+                    Some(credentials) => credentials,
                     None => {
-                        debug!(target: LOG_TARGET, "No center id set");
+                        delay = SYNC_INTERVAL;
                         continue;
                     }
-                    Some(center_id) => {
-                        debug!(target: LOG_TARGET, "Found center id {}", &center_id);
-                        center_id
-                    }
                 };
 
-                // Find private key
-                debug!(target: LOG_TARGET, "Finding private key");
-                let private_key = match get_global_key(GlobalKey::PrivateKey).await {
-                    None => {
-                        debug!(target: LOG_TARGET, "No private key set");
-                        continue;
-                    }
-                    Some(private_key) => private_key,
-                };
+                debug!(target: LOG_TARGET, "Syncing started");
+                let sync_run = start_sync_run().await;
+                let mut report = SyncRunReport::default();
+
+                let result = sync(
+                    &surreal,
+                    &window,
+                    &center_id,
+                    &private_key,
+                    &installation_id,
+                    &mut report,
+                )
+                .await;
+
+                if let Some(sync_run) = sync_run {
+                    finish_sync_run(sync_run, &report, result.as_ref().err()).await;
+                }
 
-                // Check if sync is available
-                match CentralAPI::check_sync_availability(&center_id, &private_key).await {
+                delay = match result {
                     Ok(_) => {
-                        debug!(target: LOG_TARGET, "Sync is available");
-                        window.emit("sync_available", "").unwrap_or_default();
+                        failures = 0;
+                        set_offline(&window, &mut offline, false);
+                        SYNC_INTERVAL
                     }
                     Err(error) => {
-                        warn!(target: LOG_TARGET, "Sync is not available: {:?}", error);
-                        window
-                            .emit(
-                                "sync_unavailable",
-                                serde_json::to_string(&error).unwrap_or(
-                                    serde_json::to_string(
-                                        &CheckSyncAvailabilityError::UnknownError,
-                                    )
-                                    .unwrap(),
-                                ),
-                            )
-                            .unwrap();
-                        continue;
-                    }
-                };
-
-                debug!(target: LOG_TARGET, "Checking if there are changes to push");
-                window
-                    .emit("sync_collecting_changes", "")
-                    .unwrap_or_default();
+                        failures += 1;
+                        set_offline(
+                            &window,
+                            &mut offline,
+                            matches!(error, SyncRunError::Offline(_)),
+                        );
 
-                let sync_events = match surreal
-                    .query("SELECT * FROM sync WHERE pushed = false LIMIT 100")
-                    .await
-                {
-                    Ok(mut response) => match response.take::<Vec<SyncEvent>>(0) {
-                        Ok(sync_events) => {
-                            info!(target: LOG_TARGET, "Found {} changes to push", sync_events.len());
-                            sync_events
-                        }
-                        Err(err) => {
-                            error!(target: LOG_TARGET, "Error parsing changes: {:?}", err);
-                            window
-                                .emit("sync_collecting_changes_failed", err.to_string())
-                                .unwrap_or_default();
-                            continue;
-                        }
-                    },
-                    Err(err) => {
-                        error!(target: LOG_TARGET, "Error querying changes: {:?}", err);
-                        window
-                            .emit("sync_collecting_changes_failed", err.to_string())
-                            .unwrap_or_default();
-                        continue;
+                        let delay = retry_policy.delay(failures);
+                        warn!(
+                            target: LOG_TARGET,
+                            "Sync failed {} time(s) in a row ({}), retrying in {:?}",
+                            failures,
+                            error,
+                            delay
+                        );
+                        window.emit("sync_retry", failures).unwrap_or_default();
+                        delay
                     }
                 };
 
                 window
-                    .emit("sync_start", sync_events.len())
+                    .emit("sync_sleep", delay.as_secs())
                     .unwrap_or_default();
-
-                debug!(target: LOG_TARGET, "Uploading chunks of data");
-                let mut uploaded = 0;
-
-                for chunk in sync_events.chunks(100) {
-                    uploaded += chunk.len();
-
-                    match CentralAPI::sync_upload_chunk(chunk, &private_key, &center_id).await {
-                        Ok(_) => {
-                            debug!(target: LOG_TARGET, "Chunk uploaded");
-                            window.emit("sync_progress", uploaded).unwrap_or_default();
-
-                            debug!(target: LOG_TARGET, "Marking sync events as uploaded");
-                            for sync_event in chunk {
-                                match surreal
-                                    .query(
-                                        format!(
-                                            "UPDATE sync SET pushed = true WHERE record_id = '{}'",
-                                            sync_event.record_id
-                                        )
-                                        .as_str(),
-                                    )
-                                    .await
-                                {
-                                    Ok(_) => {
-                                        debug!(target: LOG_TARGET, "Sync event marked as uploaded");
-                                    }
-                                    Err(err) => {
-                                        error!(target: LOG_TARGET, "Error marking sync event as uploaded: {:?}", err);
-                                        window
-                                            .emit("sync_upload_chunk_failed", err.to_string())
-                                            .unwrap_or_default();
-                                        continue;
-                                    }
-                                }
-                            }
-                        }
-                        Err(error) => {
-                            debug!(target: LOG_TARGET, "Sync failed with error: {:?}", error);
-                            window
-                                .emit(
-                                    "sync_upload_chunk_failed",
-                                    serde_json::to_string(&error).unwrap_or(
-                                        serde_json::to_string(&SyncUploadChunkError::UnknownError)
-                                            .unwrap(),
-                                    ),
-                                )
-                                .unwrap_or_default();
-                            continue;
-                        }
-                    };
-                }
-
-                window.emit("sync_sleep", 60).unwrap_or_default();
             }
         });
     }
@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+
+use log::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use surrealdb::engine::any::Any;
+use surrealdb::sql::{self, Datetime, Thing};
+use surrealdb::Surreal;
+use tauri::Window;
+
+use crate::app::GlobalKeyStore;
+use crate::central::{CentralAPI, SyncPullChangesError};
+
+use super::{SyncRunError, SyncRunReport};
+
+static LOG_TARGET: &str = "Sync pull";
+
+/// Number of changes requested from central at once
+static PULL_LIMIT: i64 = 100;
+
+/// Change applied to the center on central, numbered in the order it was applied
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PulledSyncEvent {
+    pub sequence: i64,
+    pub record_id: Thing,
+    pub event: String,
+    pub content: Value,
+    pub created_at: Datetime,
+}
+
+#[derive(Debug)]
+pub enum ApplyError {
+    UnknownEvent(String),
+    Database(surrealdb::Error),
+}
+
+/// Convert JSON content back to SurrealDB values
+///
+/// Record links are serialized as `{ "tb": ..., "id": { "String": ... } }` objects, they have to be
+/// turned back into records or schemafull `record<...>` fields reject them.
+fn to_surreal_value(value: Value) -> sql::Value {
+    match value {
+        Value::Null => sql::Value::None,
+        Value::Bool(value) => value.into(),
+        Value::Number(number) => match number.as_i64() {
+            Some(number) => number.into(),
+            None => number.as_f64().unwrap_or_default().into(),
+        },
+        Value::String(value) => value.into(),
+        Value::Array(values) => values
+            .into_iter()
+            .map(to_surreal_value)
+            .collect::<Vec<sql::Value>>()
+            .into(),
+        Value::Object(object) => {
+            if object.len() == 2 && object.contains_key("tb") && object.contains_key("id") {
+                if let Ok(thing) = serde_json::from_value::<Thing>(Value::Object(object.clone())) {
+                    return thing.into();
+                }
+            }
+
+            object
+                .into_iter()
+                .map(|(key, value)| (key, to_surreal_value(value)))
+                .collect::<BTreeMap<String, sql::Value>>()
+                .into()
+        }
+    }
+}
+
+/// Whether the record has a local change newer than `created_at` that is still waiting to be pushed
+pub async fn has_newer_local_change(
+    surreal: &Surreal<Any>,
+    record_id: &Thing,
+    created_at: &Datetime,
+) -> Result<bool, surrealdb::Error> {
+    let local_change: Option<Datetime> = surreal
+        .query("SELECT VALUE created_at FROM sync WHERE record_id = $record_id AND pushed = false AND created_at > $created_at LIMIT 1")
+        .bind(("record_id", record_id))
+        .bind(("created_at", created_at))
+        .await?
+        .take(0)?;
+
+    Ok(local_change.is_some())
+}
+
+/// Apply a change made elsewhere to the local database
+///
+/// The `*_syncer` events still fire for the write, the sync events they create are marked as
+/// pushed in the same transaction so applied changes are never pushed back. They are kept for the
+/// slaves replicating the local database.
+pub async fn apply_change(
+    surreal: &Surreal<Any>,
+    record_id: &Thing,
+    event: &str,
+    content: sql::Value,
+) -> Result<(), ApplyError> {
+    let statement = match event {
+        "CREATE" | "UPDATE" => "UPDATE $record_id CONTENT $content",
+        "DELETE" => "DELETE $record_id",
+        _ => return Err(ApplyError::UnknownEvent(event.to_string())),
+    };
+
+    surreal
+        .query(format!(
+            "BEGIN TRANSACTION;
+            LET $applied_at = time::now();
+            {};
+            UPDATE sync SET pushed = true WHERE record_id = $record_id AND created_at >= $applied_at;
+            COMMIT TRANSACTION;",
+            statement
+        ))
+        .bind(("record_id", record_id))
+        .bind(("content", content))
+        .await
+        .and_then(|response| response.check())
+        .map_err(ApplyError::Database)?;
+
+    Ok(())
+}
+
+/// Apply a change pulled from central to the local database
+async fn apply_event(surreal: &Surreal<Any>, event: &PulledSyncEvent) -> Result<(), ApplyError> {
+    apply_change(
+        surreal,
+        &event.record_id,
+        &event.event,
+        to_surreal_value(event.content.clone()),
+    )
+    .await
+}
+
+/// Pull the changes made by other installations of the center since the stored cursor
+pub async fn pull_changes(
+    surreal: &Surreal<Any>,
+    window: &Window,
+    central: &CentralAPI,
+    center_id: &String,
+    private_key: &String,
+    installation_id: &String,
+    report: &mut SyncRunReport,
+) -> Result<(), SyncRunError> {
+    let store = GlobalKeyStore::shared()
+        .await
+        .map_err(|err| SyncRunError::Failed(format!("Failed to open global keys: {:?}", err)))?;
+
+    let mut cursor = store
+        .sync_cursor()
+        .await
+        .map_err(|err| SyncRunError::Failed(format!("Failed to read sync cursor: {:?}", err)))?;
+
+    debug!(target: LOG_TARGET, "Pulling changes after {}", cursor);
+    window.emit("sync_pull_started", cursor).unwrap_or_default();
+
+    let mut applied = 0;
+
+    loop {
+        let pulled = match central
+            .sync_pull_changes(cursor, PULL_LIMIT, private_key, center_id, installation_id)
+            .await
+        {
+            Ok(pulled) => pulled,
+            Err(error) => {
+                warn!(target: LOG_TARGET, "Failed to pull changes: {:?}", error);
+                let offline = matches!(error, SyncPullChangesError::NetworkError);
+                let error = serde_json::to_string(&error)
+                    .unwrap_or(serde_json::to_string(&SyncPullChangesError::UnknownError).unwrap());
+
+                window
+                    .emit("sync_pull_failed", error.clone())
+                    .unwrap_or_default();
+
+                return Err(match offline {
+                    true => SyncRunError::Offline(error),
+                    false => SyncRunError::Failed(error),
+                });
+            }
+        };
+
+        info!(target: LOG_TARGET, "Pulled {} changes after {}", pulled.changes.len(), cursor);
+
+        for event in &pulled.changes {
+            match has_newer_local_change(surreal, &event.record_id, &event.created_at).await {
+                Ok(true) => {
+                    debug!(target: LOG_TARGET, "Skipping {}, a newer local change will be pushed", event.record_id);
+                }
+                Ok(false) => {
+                    if let Err(err) = apply_event(surreal, event).await {
+                        error!(target: LOG_TARGET, "Error applying change {}: {:?}", event.sequence, err);
+                        window
+                            .emit("sync_pull_failed", format!("{:?}", err))
+                            .unwrap_or_default();
+                        return Err(SyncRunError::Failed(format!("{:?}", err)));
+                    }
+
+                    applied += 1;
+                    report.pulled += 1;
+                }
+                Err(err) => {
+                    error!(target: LOG_TARGET, "Error checking local changes: {:?}", err);
+                    window
+                        .emit("sync_pull_failed", err.to_string())
+                        .unwrap_or_default();
+                    return Err(SyncRunError::Failed(err.to_string()));
+                }
+            }
+
+            // Advance the cursor change by change so a failure resumes from the failed change
+            cursor = event.sequence;
+
+            if store.set_sync_cursor(cursor).await.is_err() {
+                error!(target: LOG_TARGET, "Failed to store sync cursor {}", cursor);
+                return Err(SyncRunError::Failed(format!(
+                    "Failed to store sync cursor {}",
+                    cursor
+                )));
+            }
+        }
+
+        window
+            .emit("sync_pull_progress", applied)
+            .unwrap_or_default();
+
+        if !pulled.has_more {
+            break;
+        }
+
+        cursor = pulled.cursor;
+    }
+
+    info!(target: LOG_TARGET, "Applied {} pulled changes", applied);
+
+    Ok(())
+}
@@ -0,0 +1,41 @@
+use rand::Rng;
+use tokio::time::Duration;
+
+/// Exponential backoff with jitter between failed sync runs
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Delay after the first failure
+    pub base_delay: Duration,
+    /// Upper bound for the delay before jitter is applied
+    pub max_delay: Duration,
+    pub multiplier: f64,
+    /// Fraction of the delay randomly added or removed, e.g. 0.2 for ±20%
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            base_delay: Duration::from_secs(5),
+            max_delay: Duration::from_secs(15 * 60),
+            multiplier: 2.0,
+            jitter: 0.2,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retrying after `failures` consecutive failed runs
+    pub fn delay(&self, failures: u32) -> Duration {
+        let exponent = failures.saturating_sub(1).min(32) as i32;
+        let delay = (self.base_delay.as_secs_f64() * self.multiplier.powi(exponent))
+            .min(self.max_delay.as_secs_f64());
+
+        let jitter = match self.jitter > 0.0 {
+            true => rand::thread_rng().gen_range(-self.jitter..=self.jitter),
+            false => 0.0,
+        };
+
+        Duration::from_secs_f64((delay * (1.0 + jitter)).max(0.0))
+    }
+}
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use surrealdb::sql::{Datetime, Thing};
+
+    use crate::sync::{compact_sync_events, SyncEvent};
+
+    fn sync_event(id: &str, record_id: &str, event: &str) -> SyncEvent {
+        SyncEvent {
+            id: Thing::from(("sync", id)),
+            record_id: Thing::from(("student", record_id)),
+            event: event.to_string(),
+            content: json!({ "name": id }),
+            created_at: Datetime::default(),
+            compacted: Vec::new(),
+        }
+    }
+
+    fn ids(ids: &[&str]) -> Vec<Thing> {
+        ids.iter().map(|id| Thing::from(("sync", *id))).collect()
+    }
+
+    fn summary(sync_events: &[SyncEvent]) -> Vec<(String, String, String)> {
+        sync_events
+            .iter()
+            .map(|sync_event| {
+                (
+                    sync_event.id.id.to_string(),
+                    sync_event.record_id.id.to_string(),
+                    sync_event.event.clone(),
+                )
+            })
+            .collect()
+    }
+
+    fn expected(events: &[(&str, &str, &str)]) -> Vec<(String, String, String)> {
+        events
+            .iter()
+            .map(|(id, record_id, event)| {
+                (id.to_string(), record_id.to_string(), event.to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_create_and_updates_become_create() {
+        let compacted = compact_sync_events(vec![
+            sync_event("e1", "a", "CREATE"),
+            sync_event("e2", "a", "UPDATE"),
+            sync_event("e3", "a", "UPDATE"),
+        ]);
+
+        assert_eq!(
+            summary(&compacted.events),
+            expected(&[("e3", "a", "CREATE")])
+        );
+        assert_eq!(compacted.events[0].content, json!({ "name": "e3" }));
+        assert_eq!(compacted.events[0].compacted, ids(&["e1", "e2"]));
+        assert!(compacted.cancelled.is_empty());
+    }
+
+    #[test]
+    fn test_updates_become_last_update() {
+        let compacted = compact_sync_events(vec![
+            sync_event("e1", "a", "UPDATE"),
+            sync_event("e2", "a", "UPDATE"),
+        ]);
+
+        assert_eq!(
+            summary(&compacted.events),
+            expected(&[("e2", "a", "UPDATE")])
+        );
+        assert_eq!(compacted.events[0].compacted, ids(&["e1"]));
+    }
+
+    #[test]
+    fn test_update_and_delete_become_delete() {
+        let compacted = compact_sync_events(vec![
+            sync_event("e1", "a", "UPDATE"),
+            sync_event("e2", "a", "DELETE"),
+        ]);
+
+        assert_eq!(
+            summary(&compacted.events),
+            expected(&[("e2", "a", "DELETE")])
+        );
+        assert_eq!(compacted.events[0].compacted, ids(&["e1"]));
+        assert!(compacted.cancelled.is_empty());
+    }
+
+    #[test]
+    fn test_create_and_delete_cancel_out() {
+        let compacted = compact_sync_events(vec![
+            sync_event("e1", "a", "CREATE"),
+            sync_event("e2", "a", "UPDATE"),
+            sync_event("e3", "a", "DELETE"),
+        ]);
+
+        assert!(compacted.events.is_empty());
+        assert_eq!(compacted.cancelled, ids(&["e1", "e2", "e3"]));
+    }
+
+    #[test]
+    fn test_record_created_again_after_cancelling() {
+        let compacted = compact_sync_events(vec![
+            sync_event("e1", "a", "CREATE"),
+            sync_event("e2", "a", "DELETE"),
+            sync_event("e3", "a", "CREATE"),
+            sync_event("e4", "a", "UPDATE"),
+        ]);
+
+        assert_eq!(
+            summary(&compacted.events),
+            expected(&[("e4", "a", "CREATE")])
+        );
+        assert_eq!(compacted.events[0].compacted, ids(&["e3"]));
+        assert_eq!(compacted.cancelled, ids(&["e1", "e2"]));
+    }
+
+    #[test]
+    fn test_compacted_events_keep_order_of_last_change() {
+        let compacted = compact_sync_events(vec![
+            sync_event("e1", "a", "CREATE"),
+            sync_event("e2", "b", "CREATE"),
+            sync_event("e3", "c", "CREATE"),
+            sync_event("e4", "a", "UPDATE"),
+            sync_event("e5", "c", "DELETE"),
+        ]);
+
+        assert_eq!(
+            summary(&compacted.events),
+            expected(&[("e2", "b", "CREATE"), ("e4", "a", "CREATE")])
+        );
+        assert_eq!(compacted.cancelled, ids(&["e3", "e5"]));
+    }
+}
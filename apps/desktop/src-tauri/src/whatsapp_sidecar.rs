@@ -1,104 +1,30 @@
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::net::{Ipv4Addr, SocketAddr};
 
-use crate::SIDECARS;
-use log::{debug, error, info, warn};
-use tauri::api::process::{Command, CommandEvent};
-use tokio::sync::{mpsc::Receiver, oneshot::Sender, Mutex};
+use log::{debug, error, info};
 
-static LOG_TARGET: &str = "WhatsApp Bot sidecar";
-
-async fn listen_for_start_log(
-    mut tx: Option<Sender<Result<(), ()>>>,
-    logs_rx: Arc<Mutex<Receiver<CommandEvent>>>,
-) {
-    let mut logs_rx = logs_rx.lock().await;
-
-    while let Some(event) = logs_rx.recv().await {
-        match event {
-            CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
-                print!("{}", line);
+use crate::ports::service_ports;
+use crate::sidecar::{HealthCheck, SidecarConfig, SidecarSupervisor};
 
-                // Compare every line until the server is started
-                if line.contains("Started web server") {
-                    if let Some(tx) = tx.take() {
-                        tx.send(Ok(())).unwrap();
-
-                        // Break the loop to avoid expensive operations, we'll start the listener again with less logic
-                        return;
-                    }
-                } else if line.contains("ERROR") {
-                    if let Some(tx) = tx.take() {
-                        tx.send(Err(())).unwrap();
-
-                        // Break the loop to avoid expensive operations, we'll start the listener again with less logic
-                        return;
-                    }
-                }
-            }
-            CommandEvent::Error(error) => {
-                error!("{}", error);
-            }
-            CommandEvent::Terminated(_) => {
-                error!("WhatsApp Bot sidecar terminated");
-            }
-            _ => {
-                warn!("Unhandled WhatsApp Bot sidecar event: {:?}", event);
-            }
-        }
-    }
-}
-
-async fn listen_for_log(logs_rx: Arc<Mutex<Receiver<CommandEvent>>>) {
-    info!(target: LOG_TARGET, "Listening for WhatsApp Bot sidecar logs");
-    let mut logs_rx = logs_rx.lock().await;
-
-    while let Some(event) = logs_rx.recv().await {
-        match event {
-            CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
-                print!("{}", line);
-            }
-            CommandEvent::Error(error) => {
-                error!("{}", error);
-            }
-            CommandEvent::Terminated(_) => {
-                panic!("WhatsApp Bot sidecar terminated");
-            }
-            _ => {
-                warn!("Unhandled WhatsApp Bot sidecar event: {:?}", event);
-            }
-        }
-    }
-}
+static LOG_TARGET: &str = "WhatsApp Bot sidecar";
 
 pub async fn run_whatsapp_sidecar() {
-    let (logs_rx, process) = Command::new_sidecar("whatsapp-bot")
-        .expect("Failed to run WhatsApp Bot sidecar")
-        .spawn()
-        .expect("Failed to run WhatsApp Bot sidecar");
-
-    SIDECARS.lock().unwrap().push(process);
-
-    let (tx, rx) = tokio::sync::oneshot::channel::<Result<(), ()>>();
-
-    let tx = Some(tx);
-    let logs_rx = Arc::new(Mutex::new(logs_rx));
-
-    let start_log_logs_rx = logs_rx.clone();
-
-    tokio::spawn(async move {
-        listen_for_start_log(tx, start_log_logs_rx).await;
+    let port = service_ports().whatsapp;
+
+    let supervisor = SidecarSupervisor::new(SidecarConfig {
+        name: "whatsapp-bot",
+        label: "WhatsApp Bot",
+        args: vec!["--port".to_string(), port.to_string()],
+        envs: HashMap::new(),
+        health_check: HealthCheck::Tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, port))),
     });
 
     debug!(target: LOG_TARGET, "Waiting for WhatsApp Bot sidecar to start");
-    match rx.await {
-        Ok(Ok(())) => {
-            info!(target: LOG_TARGET, "WhatsApp Bot sidecar started successfully");
-            tokio::spawn(async move {
-                listen_for_log(logs_rx.clone()).await;
-            });
+    match supervisor.start().await {
+        Ok(_) => info!(target: LOG_TARGET, "WhatsApp Bot sidecar started successfully"),
+        // The app works without WhatsApp, the supervisor keeps trying in the background
+        Err(error) => {
+            error!(target: LOG_TARGET, "Failed to start WhatsApp Bot sidecar: {:?}", error)
         }
-        Ok(Err(())) | Err(_) => {
-            panic!("Failed to start WhatsApp Bot sidecar");
-        }
-    };
+    }
 }
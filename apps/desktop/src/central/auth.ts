@@ -1,10 +1,10 @@
-import { fetch, Body } from '@tauri-apps/api/http';
 import type { CentralClient } from 'central';
 import { StringRecordId, type ScopeAuth } from 'surrealdb.js';
 import type Surreal from 'surrealdb.js';
 import { jwtDecode } from 'jwt-decode';
 import type { MessagingChannel, SurrealDBToken } from 'common';
 import { logger } from '$lib/logger';
+import { sendSigninCode, type SendSigninCodeError } from '$lib/bindings';
 import { isSurrealConnectionError } from 'common/surreal';
 
 export enum SendSigninCodeStatus {
@@ -88,12 +88,21 @@ export class CentralAuthController {
 		channel: MessagingChannel
 	): Promise<SendSigninCodeResponse> {
 		logger.info(LOG_TARGET, `Requesting signin code for ${phoneNumber}`);
-		const response = await fetch(`${this.client.apiBaseUrl}/auth/send_signin_code`, {
-			method: 'POST',
-			body: Body.json({ phone_number: phoneNumber, channel })
-		});
 
-		logger.debug(LOG_TARGET, `Received response: ${response.status}`);
-		return response.data as SendSigninCodeResponse;
+		// Sent from Rust so the request goes to the configured central API
+		return sendSigninCode(phoneNumber, channel)
+			.then(() => ({ status: SendSigninCodeStatus.MessageSent }))
+			.catch((error: SendSigninCodeError) => {
+				logger.debug(LOG_TARGET, `Received error: ${error}`);
+
+				switch (error) {
+					case 'target_not_on_whats_app':
+						return { status: SendSigninCodeStatus.TargetNotOnWhatsApp };
+					case 'whats_app_error':
+						return { status: SendSigninCodeStatus.WhatsAppError };
+					default:
+						throw new Error(error);
+				}
+			});
 	}
 }
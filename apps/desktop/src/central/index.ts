@@ -8,7 +8,6 @@ export enum ConnectError {
 }
 
 export class CentralClient {
-	apiBaseUrl: string;
 	remoteSurrealUrl: string;
 	db: Surreal;
 
@@ -16,12 +15,6 @@ export class CentralClient {
 	center: CentralCenterController;
 
 	constructor(private testing = false) {
-		if (!import.meta.env.VITE_CENTRAL_API_BASE_URL) {
-			throw new Error('VITE_CENTRAL_API_BASE_URL is not set in environment variables');
-		}
-
-		this.apiBaseUrl = import.meta.env.VITE_CENTRAL_API_BASE_URL;
-
 		if (!import.meta.env.VITE_REMOTE_SURREAL_URL) {
 			throw new Error('VITE_REMOTE_SURREAL_URL is not set in environment variables');
 		}
@@ -18,6 +18,10 @@ export function getGlobalKey(key: GlobalKey) {
     return invoke()<string | null>("get_global_key", { key })
 }
 
+export function initializeCenter(centerId: string, centerName: string, privateKey: string, publicKey: string) {
+    return invoke()<null>("initialize_center", { centerId,centerName,privateKey,publicKey })
+}
+
 export function generateKeyPair() {
     return invoke()<KeyPair>("generate_key_pair")
 }
@@ -30,6 +34,10 @@ export function getRootDatabaseCredentials() {
     return invoke()<RootDatabaseCredentials>("get_root_database_credentials")
 }
 
+export function rotateRootDatabaseCredentials() {
+    return invoke()<RootDatabaseCredentials>("rotate_root_database_credentials")
+}
+
 export function openSplashScreen() {
     return invoke()<null>("open_splash_screen")
 }
@@ -42,10 +50,92 @@ export function generateReport(data: Report, filePath: string) {
     return invoke()<null>("generate_report", { data,filePath })
 }
 
+export function syncNow() {
+    return invoke()<null>("sync_now")
+}
+
+export function getSyncStatus() {
+    return invoke()<SyncStatus>("get_sync_status")
+}
+
+export function listSyncHistory(limit: number | null) {
+    return invoke()<SyncRun[]>("list_sync_history", { limit })
+}
+
+export function getCentralApiUrl() {
+    return invoke()<string>("get_central_api_url")
+}
+
+export function setCentralApiUrl(url: string | null) {
+    return invoke()<string>("set_central_api_url", { url })
+}
+
+export function sendSigninCode(phoneNumber: string, channel: MessagingChannel) {
+    return invoke()<null>("send_signin_code", { phoneNumber,channel })
+}
+
+export function requestPairing(address: string, port: number | null, deviceName: string) {
+    return invoke()<PairingChallenge>("request_pairing", { address,port,deviceName })
+}
+
+export function confirmPairing(pin: string) {
+    return invoke()<PairedMaster>("confirm_pairing", { pin })
+}
+
+export function rejectPairing(requestId: string) {
+    return invoke()<null>("reject_pairing", { requestId })
+}
+
+export function getSidecarStatuses() {
+    return invoke()<{ [key: string]: SidecarStatus }>("get_sidecar_statuses")
+}
+
+export function getServicePorts() {
+    return invoke()<ServicePorts>("get_service_ports")
+}
+
+export function getBackupSettings() {
+    return invoke()<BackupSettings>("get_backup_settings")
+}
+
+export function setBackupSettings(settings: BackupSettings, password: string | null) {
+    return invoke()<BackupSettings>("set_backup_settings", { settings,password })
+}
+
+export function createBackup(path: string | null, password: string | null) {
+    return invoke()<BackupInfo>("create_backup", { path,password })
+}
+
+export function listBackups() {
+    return invoke()<BackupInfo[]>("list_backups")
+}
+
+export function restoreBackup(path: string, password: string | null) {
+    return invoke()<BackupInfo>("restore_backup", { path,password })
+}
+
 export type KeyPair = { private_key: string; public_key: string }
+export type SyncStatus = { last_run: SyncRun | null; last_successful_run: SyncRun | null }
 export type RootDatabaseCredentials = { username: string; password: string }
-export type NetworkInstanceInfo = { center_name: string; version: string; instance_type: InstanceType; local_center_initialized: boolean; ip_addresses: string[] }
+export type NetworkInstanceInfo = { center_id: string | null; center_name: string; version: string; instance_type: InstanceType; local_center_initialized: boolean; ip_addresses: string[]; public_key: string | null; signed: boolean; known_center: boolean; latency_ms: number | null; ports: ServicePorts }
 export type Report = { Receipt: ReceiptData }
+export type SyncRun = { id: number; started_at: string; finished_at: string | null; status: SyncRunStatus; pushed: number; pulled: number; error: string | null }
+export type BackupSettings = { enabled: boolean; directory: string; interval_hours: number; retention: number }
+export type BackupInfo = { path: string; center_id: string; center_name: string; created_at: string; automatic: boolean }
+export type BackupError = "not_initialized" | "center_mismatch" | "not_allowed_on_slave" | "no_password" | "weak_password" | "invalid_settings" | "wrong_password" | "invalid_backup" | "unsupported_version" | "encryption_error" | "file_error" | "database_error" | "storage_error"
+export type ServicePorts = { whatsapp: number; database: number; discovery: number; pairing: number; replication: number }
 export type InstanceType = "master" | "slave" | "uninitialized"
 export type ReceiptData = { student_name: string; item_name: string; item_price: number; seller_name: string }
-export type GlobalKey = "center_id" | "center_name" | "instance_type" | "private_key" | "public_key"
+export type SyncRunStatus = "running" | "succeeded" | "offline" | "failed" | "interrupted"
+export type CentralApiConfigError = "invalid_url" | "unsupported_scheme" | "insecure_url" | "invalid_certificate" | "client_build_error" | "storage_error"
+export type MessagingChannel = "whatsapp" | "telegram"
+export type SendSigninCodeError = "target_not_on_whats_app" | "whats_app_error" | "target_not_on_telegram" | "telegram_error" | "telegram_unavailable" | "invalid_settings" | "network_error" | "response_read_error" | "unknown_error"
+export type GlobalKeyError = "database_error" | "encryption_error" | "decryption_error" | "invalid_value" | "sensitive_key" | "backend_owned_key"
+export type GlobalKey = "center_id" | "center_name" | "instance_type" | "private_key" | "public_key" | "sync_cursor" | "installation_id" | "central_api_url" | "central_api_certificate" | "master_address" | "database_username" | "database_password" | "pairing_certificate" | "root_database_credentials" | "replication_cursor" | "service_ports" | "master_replication_port" | "backup_settings" | "backup_password"
+export type PairingChallenge = { request_id: string; center_name: string }
+export type PairedMaster = { center_id: string; center_name: string; address: string }
+export type PairingRequestInfo = { request_id: string; device_name: string; address: string; pin: string }
+export type PairingError = "not_master" | "already_initialized" | "unreachable" | "protocol_error" | "no_pending_request" | "request_not_found" | "request_expired" | "too_many_attempts" | "invalid_pin" | "database_error" | "certificate_error" | "storage_error"
+export type DatabaseAuthError = "storage_error" | "connection_error" | "signin_error" | "query_error"
+export type SidecarStatus = { state: "starting" } | { state: "running" } | { state: "unhealthy"; failed_checks: number } | { state: "restarting"; attempt: number; delay_secs: number } | { state: "stopped" }
+export type SidecarStatusChange = { sidecar: string; status: SidecarStatus }
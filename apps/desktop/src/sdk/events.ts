@@ -1,3 +1,4 @@
+import type { BackupInfo, PairingRequestInfo, SidecarStatusChange } from '$lib/bindings';
 import { logger } from '$lib/logger';
 import { listen, type Event as TauriEvent, type UnlistenFn } from '@tauri-apps/api/event';
 
@@ -9,7 +10,26 @@ export enum AppEventName {
 	SyncStarted = 'sync_started',
 	SyncProgress = 'sync_progress',
 	SyncSleep = 'sync_sleep',
-	SyncUploadFailed = 'sync_upload_failed'
+	SyncUploadFailed = 'sync_upload_failed',
+	SyncPullStarted = 'sync_pull_started',
+	SyncPullProgress = 'sync_pull_progress',
+	SyncPullFailed = 'sync_pull_failed',
+	SyncRetry = 'sync_retry',
+	SyncOffline = 'sync_offline',
+	SyncOnline = 'sync_online',
+	PairingRequested = 'pairing_requested',
+	PairingCompleted = 'pairing_completed',
+	PairingFailed = 'pairing_failed',
+	ReplicationOffline = 'replication_offline',
+	ReplicationOnline = 'replication_online',
+	ReplicationSnapshotStarted = 'replication_snapshot_started',
+	ReplicationPulled = 'replication_pulled',
+	ReplicationForwarded = 'replication_forwarded',
+	ReplicationFailed = 'replication_failed',
+	SidecarStatus = 'sidecar_status',
+	BackupCompleted = 'backup_completed',
+	BackupFailed = 'backup_failed',
+	BackupRestored = 'backup_restored'
 }
 
 export interface AppEventPayloads {
@@ -19,8 +39,27 @@ export interface AppEventPayloads {
 	[AppEventName.SyncCollectingChangesFailed]: string;
 	[AppEventName.SyncStarted]: number;
 	[AppEventName.SyncProgress]: number;
-	[AppEventName.SyncSleep]: never;
+	[AppEventName.SyncSleep]: number;
 	[AppEventName.SyncUploadFailed]: string;
+	[AppEventName.SyncPullStarted]: number;
+	[AppEventName.SyncPullProgress]: number;
+	[AppEventName.SyncPullFailed]: string;
+	[AppEventName.SyncRetry]: number;
+	[AppEventName.SyncOffline]: never;
+	[AppEventName.SyncOnline]: never;
+	[AppEventName.PairingRequested]: PairingRequestInfo;
+	[AppEventName.PairingCompleted]: string;
+	[AppEventName.PairingFailed]: string;
+	[AppEventName.ReplicationOffline]: never;
+	[AppEventName.ReplicationOnline]: never;
+	[AppEventName.ReplicationSnapshotStarted]: never;
+	[AppEventName.ReplicationPulled]: number;
+	[AppEventName.ReplicationForwarded]: number;
+	[AppEventName.ReplicationFailed]: string;
+	[AppEventName.SidecarStatus]: SidecarStatusChange;
+	[AppEventName.BackupCompleted]: BackupInfo;
+	[AppEventName.BackupFailed]: string;
+	[AppEventName.BackupRestored]: BackupInfo;
 }
 
 export interface AppEvent<T> extends TauriEvent<T> {
@@ -1,7 +1,7 @@
 import { Surreal } from 'surrealdb.js';
 import { StudentsController } from './student';
 import { LocalDatabaseManager } from './manager';
-import { getRootDatabaseCredentials } from '$lib/bindings';
+import { getRootDatabaseCredentials, getServicePorts } from '$lib/bindings';
 import { isSurrealConnectionError } from 'common/surreal';
 import { logger } from '$lib/logger';
 import { LocalAuthController } from './auth';
@@ -55,6 +55,10 @@ export class App {
 	async connect() {
 		if (this.testingUrl) {
 			this.surrealDbUrl = this.testingUrl;
+		} else {
+			// The database falls back to another port when the default one is taken
+			const ports = await getServicePorts();
+			this.surrealDbUrl = `http://127.0.0.1:${ports.database}/rpc`;
 		}
 
 		// Connect to Root
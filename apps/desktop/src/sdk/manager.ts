@@ -1,5 +1,5 @@
 import { App } from 'sdk';
-import { getRootDatabaseCredentials, setGlobalKey } from '$lib/bindings';
+import { getRootDatabaseCredentials, initializeCenter } from '$lib/bindings';
 import { logger } from '$lib/logger';
 import type { InfoForRoot } from './common';
 import { type LocalUser, type Scope } from './user';
@@ -34,20 +34,13 @@ export class LocalDatabaseManager {
 		logger.info(LOG_TARGET, `Defining local database`);
 		await this.defineDatabase();
 
-		logger.info(LOG_TARGET, `Setting local global key: center_id`);
-		await setGlobalKey('center_id', parameters.id.toString());
-
-		logger.info(LOG_TARGET, `Setting local global key: center_name`);
-		await setGlobalKey('center_name', parameters.center_name);
-
-		logger.info(LOG_TARGET, `Setting local global key: private_key`);
-		await setGlobalKey('private_key', parameters.private_key);
-
-		logger.info(LOG_TARGET, `Setting local global key: public_key`);
-		await setGlobalKey('public_key', parameters.public_key);
-
-		logger.info(LOG_TARGET, `Setting local global key: public_key`);
-		await setGlobalKey('instance_type', 'master');
+		logger.info(LOG_TARGET, `Storing center keys`);
+		await initializeCenter(
+			parameters.id.toString(),
+			parameters.center_name,
+			parameters.private_key,
+			parameters.public_key
+		);
 
 		logger.error(LOG_TARGET, `Downloading data from the Central API is not implemented yet`);
 	}
@@ -152,6 +145,8 @@ export class LocalDatabaseManager {
 			${SyncEventCreator('group')}
 			${SyncEventCreator('student')}
 			${SyncEventCreator('enrollment')}
+			${SyncEventCreator('attendance')}
+			${SyncEventCreator('payment')}
 		`;
 
 		return { schema, query };
@@ -89,8 +89,30 @@ export function generateDatabaseSchema() {
 			DEFINE FIELD default_group ON TABLE enrollment TYPE record<group>;
 			DEFINE FIELD academic_year ON TABLE enrollment TYPE record<academic_year>;
 			DEFINE FIELD course ON TABLE enrollment TYPE record<academic_year_course>;
+			DEFINE FIELD fees ON TABLE enrollment TYPE option<number>;
 			DEFINE FIELD space ON TABLE enrollment TYPE record<space>;
 
 			DEFINE INDEX enrollment_student_name_index ON enrollment FIELDS _name SEARCH ANALYZER name_analyzer BM25;
+
+			DEFINE TABLE attendance SCHEMAFULL
+				PERMISSIONS
+					FOR SELECT WHERE ${CenterManagerScope} OR ${SpaceMemberScope},
+					FOR CREATE, UPDATE, DELETE WHERE ${CenterManagerScope} OR ${SpaceManagerScope} OR ${CustomScope(LocalUserScope.ManageStudents)};
+			DEFINE FIELD student ON TABLE attendance TYPE record<student>;
+			DEFINE FIELD enrollment ON TABLE attendance TYPE record<enrollment>;
+			DEFINE FIELD group ON TABLE attendance TYPE record<group>;
+			DEFINE FIELD attended_at ON TABLE attendance TYPE datetime;
+			DEFINE FIELD present ON TABLE attendance TYPE bool;
+			DEFINE FIELD space ON TABLE attendance TYPE record<space>;
+
+			DEFINE TABLE payment SCHEMAFULL
+				PERMISSIONS
+					FOR SELECT WHERE ${CenterManagerScope} OR ${SpaceMemberScope},
+					FOR CREATE, UPDATE, DELETE WHERE ${CenterManagerScope} OR ${SpaceManagerScope} OR ${CustomScope(LocalUserScope.ManageStudents)};
+			DEFINE FIELD student ON TABLE payment TYPE record<student>;
+			DEFINE FIELD enrollment ON TABLE payment TYPE record<enrollment>;
+			DEFINE FIELD amount ON TABLE payment TYPE number;
+			DEFINE FIELD paid_at ON TABLE payment TYPE datetime;
+			DEFINE FIELD space ON TABLE payment TYPE record<space>;
 		`;
 }
@@ -6,7 +6,7 @@ import { beforeAll, describe, expect, it, vi } from 'vitest';
 beforeAll(() => {
 	vi.mock('$lib/bindings', () => {
 		return {
-			setGlobalKey: () => {},
+			initializeCenter: () => {},
 			getRootDatabaseCredentials: () => ({ username: 'root', password: 'root' })
 		};
 	});
@@ -6,7 +6,7 @@ import { Grade, Subject } from 'sdk/academic-year-course';
 beforeAll(() => {
 	vi.mock('$lib/bindings', () => {
 		return {
-			setGlobalKey: () => {},
+			initializeCenter: () => {},
 			getRootDatabaseCredentials: () => ({ username: 'root', password: 'root' })
 		};
 	});
@@ -8,7 +8,7 @@ import { Day } from 'sdk/group';
 beforeAll(() => {
 	vi.mock('$lib/bindings', () => {
 		return {
-			setGlobalKey: () => {},
+			initializeCenter: () => {},
 			getRootDatabaseCredentials: () => ({ username: 'root', password: 'root' })
 		};
 	});
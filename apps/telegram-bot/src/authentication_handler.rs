@@ -59,14 +59,7 @@ impl AuthorizationHandler for ConsoleAuthorizationHandler {
     async fn handle_set_tdlib_params(&self) -> () {
         let client = self.get_client();
 
-        client
-            .send(SetTdLibParameters::new(
-                &client,
-                24977003,
-                "6adc83372bceff3460093e1846796d49".to_string(),
-            ))
-            .await
-            .unwrap();
+        client.send(SetTdLibParameters::new(&client)).await.unwrap();
     }
 
     async fn handle_wait_other_device_confirmation(&self, link: String) {
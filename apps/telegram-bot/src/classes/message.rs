@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "@type")]
+pub enum TelegramMessageSender {
+    #[serde(rename = "messageSenderUser")]
+    User { user_id: i64 },
+
+    #[serde(rename = "messageSenderChat")]
+    Chat { chat_id: i64 },
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramMessageText {
+    pub text: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "@type")]
+pub enum TelegramMessageContent {
+    #[serde(rename = "messageText")]
+    Text { text: TelegramMessageText },
+
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TelegramMessage {
+    pub id: i64,
+    pub chat_id: i64,
+    pub sender_id: TelegramMessageSender,
+    #[serde(default)]
+    pub is_outgoing: bool,
+    pub content: TelegramMessageContent,
+}
+
+impl TelegramMessage {
+    /// Text of the message if it is a text message
+    pub fn text(&self) -> Option<&str> {
+        match &self.content {
+            TelegramMessageContent::Text { text } => Some(text.text.as_str()),
+            TelegramMessageContent::Unsupported => None,
+        }
+    }
+}
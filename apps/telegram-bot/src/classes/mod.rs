@@ -1,5 +1,7 @@
 mod user;
 mod chat;
+mod message;
 
 pub use user::*;
-pub use chat::*;
\ No newline at end of file
+pub use chat::*;
+pub use message::*;
\ No newline at end of file
@@ -5,4 +5,6 @@ pub struct TelegramUser {
     pub id: i64,
     pub first_name: String,
     pub last_name: String,
+    #[serde(default)]
+    pub phone_number: String,
 }
@@ -0,0 +1,136 @@
+use log::{debug, error, info};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::functions::{FormattedText, SendMessage};
+use crate::{TelegramClient, TelegramMessage};
+
+static LOG_TARGET: &str = "Telegram Command Router";
+
+/// Command sent to the bot, e.g. `/schedule` or `/balance 2`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BotCommand {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+impl BotCommand {
+    /// Parse `/name@bot_username arg1 arg2`, returns `None` when the text is not a command
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parts = text.split_whitespace();
+        let name = parts.next()?.strip_prefix('/')?;
+        let name = name.split('@').next().unwrap_or_default().to_lowercase();
+
+        if name.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            name,
+            args: parts.map(|arg| arg.to_string()).collect(),
+        })
+    }
+}
+
+/// Everything a handler needs to answer a command
+pub struct CommandContext {
+    pub client: Arc<TelegramClient>,
+    pub command: BotCommand,
+    pub message: TelegramMessage,
+}
+
+#[async_trait::async_trait]
+pub trait CommandHandler: Send + Sync {
+    /// Handle the command and return the reply to send back to the chat, if any
+    async fn handle(&self, context: &CommandContext) -> Option<FormattedText>;
+}
+
+struct RegisteredCommand {
+    description: String,
+    handler: Arc<dyn CommandHandler>,
+}
+
+/// Dispatches incoming text commands to registered handlers
+#[derive(Default)]
+pub struct CommandRouter {
+    commands: HashMap<String, RegisteredCommand>,
+}
+
+impl CommandRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a handler for `/name`, replacing any previous handler of the same command
+    pub fn register(
+        mut self,
+        name: &str,
+        description: &str,
+        handler: impl CommandHandler + 'static,
+    ) -> Self {
+        self.commands.insert(
+            name.trim_start_matches('/').to_lowercase(),
+            RegisteredCommand {
+                description: description.to_string(),
+                handler: Arc::new(handler),
+            },
+        );
+        self
+    }
+
+    /// List of registered commands and their descriptions
+    pub fn help(&self) -> FormattedText {
+        let mut names: Vec<&String> = self.commands.keys().collect();
+        names.sort();
+
+        let lines: Vec<String> = names
+            .into_iter()
+            .map(|name| format!("/{} - {}", name, self.commands[name].description))
+            .collect();
+
+        FormattedText::new(format!("Available commands:\n{}", lines.join("\n")))
+    }
+
+    /// Find the handler for the command in `message` and send its reply back to the chat
+    pub async fn dispatch(&self, client: Arc<TelegramClient>, message: TelegramMessage) {
+        if message.is_outgoing {
+            return;
+        }
+
+        let command = match message.text().and_then(BotCommand::parse) {
+            Some(command) => command,
+            None => return,
+        };
+
+        info!(target: LOG_TARGET, "Received command /{} in chat {}", command.name, message.chat_id);
+
+        let reply = match self.commands.get(&command.name) {
+            Some(registered_command) => {
+                let handler = registered_command.handler.clone();
+                let context = CommandContext {
+                    client: client.clone(),
+                    command,
+                    message,
+                };
+
+                handler
+                    .handle(&context)
+                    .await
+                    .map(|reply| (context.message.chat_id, reply))
+            }
+            None => {
+                debug!(target: LOG_TARGET, "No handler registered for /{}", command.name);
+                Some((message.chat_id, self.help()))
+            }
+        };
+
+        if let Some((chat_id, reply)) = reply {
+            if let Err(e) = client
+                .send(SendMessage::formatted(&client, chat_id, reply))
+                .await
+            {
+                error!(target: LOG_TARGET, "Failed to reply to command: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::DEFAULT_REQUEST_TIMEOUT;
+
+/// Settings used to initialize a [`crate::TelegramClient`] and its tdlib instance
+#[derive(Debug, Clone)]
+pub struct TelegramConfig {
+    pub api_id: i32,
+    pub api_hash: String,
+    pub database_directory: String,
+    pub files_directory: String,
+    pub use_test_dc: bool,
+    pub system_language_code: String,
+    pub device_model: String,
+    pub application_version: String,
+    pub request_timeout: Duration,
+}
+
+impl TelegramConfig {
+    /// Create a configuration with the given credentials obtained from https://my.telegram.org
+    pub fn new(api_id: i32, api_hash: String) -> Self {
+        Self {
+            api_id,
+            api_hash,
+            database_directory: "third_party/td".to_string(),
+            files_directory: "third_party/td_files".to_string(),
+            use_test_dc: false,
+            system_language_code: "en".to_string(),
+            device_model: "Magmooty".to_string(),
+            application_version: "v1.0.0".to_string(),
+            request_timeout: DEFAULT_REQUEST_TIMEOUT,
+        }
+    }
+
+    /// Store the tdlib database and downloaded files under `directory`
+    pub fn data_directory(mut self, directory: impl AsRef<Path>) -> Self {
+        let directory = directory.as_ref();
+
+        self.database_directory = directory.join("td").to_string_lossy().into_owned();
+        self.files_directory = directory.join("td_files").to_string_lossy().into_owned();
+        self
+    }
+
+    pub fn database_directory(mut self, directory: String) -> Self {
+        self.database_directory = directory;
+        self
+    }
+
+    pub fn files_directory(mut self, directory: String) -> Self {
+        self.files_directory = directory;
+        self
+    }
+
+    /// Connect to Telegram test servers instead of production ones
+    pub fn use_test_dc(mut self, use_test_dc: bool) -> Self {
+        self.use_test_dc = use_test_dc;
+        self
+    }
+
+    pub fn system_language_code(mut self, system_language_code: String) -> Self {
+        self.system_language_code = system_language_code;
+        self
+    }
+
+    pub fn device_model(mut self, device_model: String) -> Self {
+        self.device_model = device_model;
+        self
+    }
+
+    pub fn application_version(mut self, application_version: String) -> Self {
+        self.application_version = application_version;
+        self
+    }
+
+    /// Default time to wait for tdlib to answer a request
+    pub fn request_timeout(mut self, request_timeout: Duration) -> Self {
+        self.request_timeout = request_timeout;
+        self
+    }
+}
@@ -0,0 +1,171 @@
+//! In-memory tdlib backend used to test the client without network access.
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::tdlib::{ClientId, TdBackend};
+
+/// Upper bound for a single blocking `receive` call so listener threads never outlive a test runtime
+const RECEIVE_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Event produced by the fake backend in reaction to a request
+pub enum FakeTdEvent {
+    /// Reply to the request, `@extra` and `@client_id` are copied from the request
+    Response(Value),
+    /// Update not tied to any request
+    Update(Value),
+}
+
+type Responder = Box<dyn Fn(&Value) -> Vec<FakeTdEvent> + Send + Sync>;
+
+/// Scripted tdlib backend
+///
+/// Requests are matched by their `@type` against registered responders, requests without
+/// a responder never get a reply. `setLogVerbosityLevel` and `getAuthorizationState` are
+/// answered by default so [`crate::TelegramClient::init_with_backend`] completes immediately.
+pub struct FakeTdBackend {
+    next_client_id: AtomicI32,
+    responders: Mutex<HashMap<String, Responder>>,
+    sent_requests: Mutex<Vec<Value>>,
+    events_tx: Mutex<Sender<String>>,
+    events_rx: Mutex<Receiver<String>>,
+}
+
+impl FakeTdBackend {
+    pub fn new() -> Self {
+        let (events_tx, events_rx) = channel();
+
+        let backend = Self {
+            next_client_id: AtomicI32::new(1),
+            responders: Mutex::new(HashMap::new()),
+            sent_requests: Mutex::new(Vec::new()),
+            events_tx: Mutex::new(events_tx),
+            events_rx: Mutex::new(events_rx),
+        };
+
+        backend.reply_with("setLogVerbosityLevel", json!({ "@type": "ok" }));
+        backend.reply_with(
+            "getAuthorizationState",
+            json!({ "@type": "authorizationStateWaitTdlibParameters" }),
+        );
+
+        backend
+    }
+
+    /// Register a responder for requests of the given `@type`, replacing any previous one
+    pub fn respond_to<F>(&self, td_type: &str, responder: F)
+    where
+        F: Fn(&Value) -> Vec<FakeTdEvent> + Send + Sync + 'static,
+    {
+        self.responders
+            .lock()
+            .unwrap()
+            .insert(td_type.to_string(), Box::new(responder));
+    }
+
+    /// Reply to every request of the given `@type` with the same response
+    pub fn reply_with(&self, td_type: &str, response: Value) {
+        self.respond_to(td_type, move |_| {
+            vec![FakeTdEvent::Response(response.clone())]
+        });
+    }
+
+    /// Stop replying to requests of the given `@type`
+    pub fn ignore(&self, td_type: &str) {
+        self.responders.lock().unwrap().remove(td_type);
+    }
+
+    /// Emit an update as if it was pushed by tdlib
+    pub fn emit_update(&self, mut update: Value) {
+        if update.get("@client_id").is_none() {
+            update["@client_id"] = json!(self.current_client_id());
+        }
+
+        self.push(update);
+    }
+
+    /// Emit an `updateAuthorizationState` update with the given state `@type`
+    pub fn emit_authorization_state(&self, state: &str) {
+        self.emit_update(json!({
+            "@type": "updateAuthorizationState",
+            "authorization_state": { "@type": state },
+        }));
+    }
+
+    /// Emit an `updateConnectionState` update with the given state `@type`
+    pub fn emit_connection_state(&self, state: &str) {
+        self.emit_update(json!({
+            "@type": "updateConnectionState",
+            "state": { "@type": state },
+        }));
+    }
+
+    /// Requests received so far, in the order they were sent
+    pub fn sent_requests(&self) -> Vec<Value> {
+        self.sent_requests.lock().unwrap().clone()
+    }
+
+    /// Requests of the given `@type` received so far
+    pub fn sent_requests_of_type(&self, td_type: &str) -> Vec<Value> {
+        self.sent_requests()
+            .into_iter()
+            .filter(|request| request["@type"] == td_type)
+            .collect()
+    }
+
+    fn current_client_id(&self) -> ClientId {
+        self.next_client_id.load(Ordering::Relaxed) - 1
+    }
+
+    fn push(&self, event: Value) {
+        let _ = self.events_tx.lock().unwrap().send(event.to_string());
+    }
+}
+
+impl Default for FakeTdBackend {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TdBackend for FakeTdBackend {
+    fn create_client_id(&self) -> ClientId {
+        self.next_client_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn send(&self, client_id: ClientId, request: &str) {
+        let request: Value = match serde_json::from_str(request) {
+            Ok(request) => request,
+            Err(_) => return,
+        };
+
+        self.sent_requests.lock().unwrap().push(request.clone());
+
+        let td_type = request["@type"].as_str().unwrap_or_default().to_string();
+
+        let events = match self.responders.lock().unwrap().get(&td_type) {
+            Some(responder) => responder(&request),
+            None => return,
+        };
+
+        for event in events {
+            match event {
+                FakeTdEvent::Response(mut response) => {
+                    response["@extra"] = request["@extra"].clone();
+                    response["@client_id"] = json!(client_id);
+                    self.push(response);
+                }
+                FakeTdEvent::Update(update) => self.emit_update(update),
+            }
+        }
+    }
+
+    fn receive(&self, timeout: f64) -> Option<String> {
+        let timeout = Duration::from_secs_f64(timeout.max(0.0)).min(RECEIVE_POLL_INTERVAL);
+
+        self.events_rx.lock().unwrap().recv_timeout(timeout).ok()
+    }
+}
@@ -20,7 +20,7 @@ impl CheckAuthenticationPassword {
     pub fn new(client: &TelegramClient, password: String) -> Self {
         Self {
             td_type: TdLibType::CheckAuthenticationPassword,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
             password,
         }
@@ -22,7 +22,7 @@ impl CreatePrivateChat {
     pub fn new(client: &TelegramClient, user_id: i64) -> Self {
         Self {
             td_type: TdLibType::CreatePrivateChat,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
             user_id,
             force: false,
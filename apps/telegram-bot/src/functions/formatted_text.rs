@@ -0,0 +1,309 @@
+use crate::requests::TdLibType;
+use serde::Serialize;
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "@type")]
+pub enum TextEntityType {
+    #[serde(rename = "textEntityTypeBold")]
+    Bold,
+
+    #[serde(rename = "textEntityTypeItalic")]
+    Italic,
+
+    #[serde(rename = "textEntityTypeCode")]
+    Code,
+
+    #[serde(rename = "textEntityTypePre")]
+    Pre,
+
+    #[serde(rename = "textEntityTypeTextUrl")]
+    TextUrl { url: String },
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TextEntity {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    /// Offset of the entity in UTF-16 code units
+    pub offset: i32,
+
+    /// Length of the entity in UTF-16 code units
+    pub length: i32,
+
+    #[serde(rename = "type")]
+    pub entity_type: TextEntityType,
+}
+
+impl TextEntity {
+    pub fn new(offset: i32, length: i32, entity_type: TextEntityType) -> Self {
+        Self {
+            td_type: TdLibType::TextEntity,
+            offset,
+            length,
+            entity_type,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FormattedText {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    pub text: String,
+
+    pub entities: Vec<TextEntity>,
+}
+
+impl FormattedText {
+    pub fn new(text: String) -> Self {
+        Self::with_entities(text, Vec::new())
+    }
+
+    pub fn with_entities(text: String, entities: Vec<TextEntity>) -> Self {
+        Self {
+            td_type: TdLibType::FormattedText,
+            text,
+            entities,
+        }
+    }
+
+    /// Parse a Markdown subset into text and entities
+    ///
+    /// Supports `*bold*`, `_italic_`, `` `code` ``, ```` ```pre``` ```` and `[text](url)`,
+    /// any character can be escaped with a backslash. A `*` or `_` without a closing marker
+    /// and a `[` that doesn't start a whole link are kept as text, entities that overlap
+    /// without nesting (`*a _b* c_`) are rejected.
+    pub fn from_markdown(markdown: &str) -> Result<Self, String> {
+        MarkdownParser::new(markdown).parse()
+    }
+}
+
+struct MarkdownParser {
+    chars: Vec<char>,
+    position: usize,
+    text: String,
+    offset: i32,
+    entities: Vec<TextEntity>,
+    open_entities: Vec<(char, i32)>,
+}
+
+impl MarkdownParser {
+    fn new(markdown: &str) -> Self {
+        Self {
+            chars: markdown.chars().collect(),
+            position: 0,
+            text: String::new(),
+            offset: 0,
+            entities: Vec::new(),
+            open_entities: Vec::new(),
+        }
+    }
+
+    fn parse(mut self) -> Result<FormattedText, String> {
+        while let Some(char) = self.next() {
+            match char {
+                '\\' => {
+                    let escaped = self
+                        .next()
+                        .ok_or("Markdown can't end with an escape character")?;
+                    self.push(escaped);
+                }
+                '`' if self.starts_with("``") => {
+                    self.position += 2;
+                    self.parse_code_block("```", TextEntityType::Pre)?;
+                }
+                '`' => self.parse_code_block("`", TextEntityType::Code)?,
+                '*' | '_' => self.toggle_entity(char)?,
+                '[' if self.link_end(self.position).is_some() => self.parse_link()?,
+                _ => self.push(char),
+            }
+        }
+
+        self.entities
+            .sort_by(|a, b| a.offset.cmp(&b.offset).then(b.length.cmp(&a.length)));
+
+        Ok(FormattedText::with_entities(self.text, self.entities))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let char = self.chars.get(self.position).copied();
+        self.position += 1;
+        char
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        self.chars[self.position.min(self.chars.len())..].starts_with(&pattern)
+    }
+
+    fn push(&mut self, char: char) {
+        self.text.push(char);
+        self.offset += char.len_utf16() as i32;
+    }
+
+    fn add_entity(&mut self, start: i32, entity_type: TextEntityType) {
+        if self.offset > start {
+            self.entities
+                .push(TextEntity::new(start, self.offset - start, entity_type));
+        }
+    }
+
+    fn toggle_entity(&mut self, marker: char) -> Result<(), String> {
+        match self
+            .open_entities
+            .iter()
+            .rposition(|(open_marker, _)| *open_marker == marker)
+        {
+            Some(index) if index + 1 < self.open_entities.len() => {
+                let (inner_marker, _) = self.open_entities[index + 1];
+                Err(format!(
+                    "'{}' entity overlaps '{}' entity",
+                    marker, inner_marker
+                ))
+            }
+            Some(index) => {
+                let (_, start) = self.open_entities.remove(index);
+                let entity_type = match marker {
+                    '*' => TextEntityType::Bold,
+                    _ => TextEntityType::Italic,
+                };
+                self.add_entity(start, entity_type);
+                Ok(())
+            }
+            None if self.closes_later(marker) => {
+                self.open_entities.push((marker, self.offset));
+                Ok(())
+            }
+            None => {
+                self.push(marker);
+                Ok(())
+            }
+        }
+    }
+
+    /// Whether `marker` appears again outside escapes, code blocks and links
+    fn closes_later(&self, marker: char) -> bool {
+        let mut position = self.position;
+
+        while let Some(char) = self.chars.get(position) {
+            position += 1;
+
+            match char {
+                '\\' => position += 1,
+                '`' => {
+                    position = self.chars[position.min(self.chars.len())..]
+                        .iter()
+                        .position(|char| *char == '`')
+                        .map_or(self.chars.len(), |end| position + end + 1);
+                }
+                '[' => {
+                    if let Some(end) = self.link_end(position) {
+                        position = end;
+                    }
+                }
+                char if *char == marker => return true,
+                _ => {}
+            }
+        }
+
+        false
+    }
+
+    /// Position after the `)` of a link whose text starts at `position`, `None` when the text
+    /// isn't followed by `](url)`
+    fn link_end(&self, mut position: usize) -> Option<usize> {
+        let mut in_url = false;
+
+        loop {
+            match self.chars.get(position)? {
+                '\\' => position += 1,
+                ']' if !in_url => {
+                    if self.chars.get(position + 1) != Some(&'(') {
+                        return None;
+                    }
+                    in_url = true;
+                    position += 1;
+                }
+                ')' if in_url => return Some(position + 1),
+                _ => {}
+            }
+
+            position += 1;
+        }
+    }
+
+    /// Copy everything up to `terminator` verbatim, code blocks don't contain nested entities
+    fn parse_code_block(
+        &mut self,
+        terminator: &str,
+        entity_type: TextEntityType,
+    ) -> Result<(), String> {
+        let start = self.offset;
+
+        loop {
+            if self.starts_with(terminator) {
+                self.position += terminator.chars().count();
+                break;
+            }
+
+            match self.next() {
+                Some('\\') => {
+                    let escaped = self
+                        .next()
+                        .ok_or("Markdown can't end with an escape character")?;
+                    self.push(escaped);
+                }
+                Some(char) => self.push(char),
+                None => return Err(format!("Unclosed '{}' entity", terminator)),
+            }
+        }
+
+        self.add_entity(start, entity_type);
+
+        Ok(())
+    }
+
+    fn parse_link(&mut self) -> Result<(), String> {
+        let start = self.offset;
+
+        loop {
+            match self.next() {
+                Some('\\') => {
+                    let escaped = self
+                        .next()
+                        .ok_or("Markdown can't end with an escape character")?;
+                    self.push(escaped);
+                }
+                Some(']') => break,
+                Some(char) => self.push(char),
+                None => return Err("Unclosed link text".to_string()),
+            }
+        }
+
+        if self.next() != Some('(') {
+            return Err("Link text must be followed by a URL in parentheses".to_string());
+        }
+
+        let mut url = String::new();
+
+        loop {
+            match self.next() {
+                Some('\\') => {
+                    url.push(
+                        self.next()
+                            .ok_or("Markdown can't end with an escape character")?,
+                    );
+                }
+                Some(')') => break,
+                Some(char) => url.push(char),
+                None => return Err("Unclosed link URL".to_string()),
+            }
+        }
+
+        self.add_entity(start, TextEntityType::TextUrl { url });
+
+        Ok(())
+    }
+}
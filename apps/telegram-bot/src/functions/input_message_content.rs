@@ -0,0 +1,102 @@
+use crate::requests::TdLibType;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::FormattedText;
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InputFileLocal {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    path: String,
+}
+
+impl InputFileLocal {
+    /// Reference a file on the local disk, relative paths are resolved against the working directory
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref();
+        let path = fs::canonicalize(path).unwrap_or(path.to_path_buf());
+
+        Self {
+            td_type: TdLibType::InputFileLocal,
+            path: path.to_string_lossy().into_owned(),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InputMessageText {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    text: FormattedText,
+}
+
+impl InputMessageText {
+    pub fn new(text: FormattedText) -> Self {
+        Self {
+            td_type: TdLibType::InputMessageText,
+            text,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InputMessageDocument {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    document: InputFileLocal,
+
+    disable_content_type_detection: bool,
+
+    caption: Option<FormattedText>,
+}
+
+impl InputMessageDocument {
+    pub fn new(document: InputFileLocal, caption: Option<FormattedText>) -> Self {
+        Self {
+            td_type: TdLibType::InputMessageDocument,
+            document,
+            disable_content_type_detection: false,
+            caption,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+pub struct InputMessagePhoto {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    photo: InputFileLocal,
+
+    width: i32,
+
+    height: i32,
+
+    caption: Option<FormattedText>,
+}
+
+impl InputMessagePhoto {
+    /// Width and height are left as 0 so tdlib reads them from the file
+    pub fn new(photo: InputFileLocal, caption: Option<FormattedText>) -> Self {
+        Self {
+            td_type: TdLibType::InputMessagePhoto,
+            photo,
+            width: 0,
+            height: 0,
+            caption,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum InputMessageContent {
+    Text(InputMessageText),
+    Document(InputMessageDocument),
+    Photo(InputMessagePhoto),
+}
@@ -7,6 +7,14 @@ mod search_user_by_phone_number;
 mod search_contacts;
 mod create_private_chat;
 mod send_message;
+mod formatted_text;
+mod input_message_content;
+mod send_document;
+mod send_photo;
+mod get_user;
+
+mod test_formatted_text;
+mod test_send_message;
 
 pub use get_authorization_state::*;
 pub use set_tdlib_parameters::*;
@@ -16,4 +24,9 @@ pub use check_authentication_password::*;
 pub use search_user_by_phone_number::*;
 pub use search_contacts::*;
 pub use create_private_chat::*;
-pub use send_message::*;
\ No newline at end of file
+pub use send_message::*;
+pub use formatted_text::*;
+pub use input_message_content::*;
+pub use send_document::*;
+pub use send_photo::*;
+pub use get_user::*;
\ No newline at end of file
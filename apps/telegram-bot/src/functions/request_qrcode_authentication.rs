@@ -18,7 +18,7 @@ impl RequestQrCodeAuthentication {
     pub fn new(client: &TelegramClient) -> Self {
         Self {
             td_type: TdLibType::RequestQrCodeAuthentication,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
         }
     }
@@ -22,7 +22,7 @@ impl SearchContacts {
     pub fn new(client: &TelegramClient, query: String) -> Self {
         Self {
             td_type: TdLibType::SearchContacts,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
             query,
             limit: 10
@@ -22,7 +22,7 @@ impl SearchUserByPhoneNumber {
     pub fn new(client: &TelegramClient, phone_number: String) -> Self {
         Self {
             td_type: TdLibType::SearchUserByPhoneNumber,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
             phone_number,
             only_local: false
@@ -0,0 +1,43 @@
+use crate::tdlib::ClientId;
+use crate::{requests::TdLibType, TelegramClient, TelegramRequest};
+use serde::Serialize;
+use std::path::Path;
+
+use super::{FormattedText, InputFileLocal, InputMessageContent, InputMessageDocument};
+
+/// Upload a local file (e.g. a PDF receipt) and send it as a document
+#[derive(Serialize, Debug, Clone, TelegramRequest)]
+pub struct SendDocument {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    #[serde(rename = "@client_id")]
+    client_id: ClientId,
+
+    #[serde(rename = "@extra")]
+    extra: String,
+
+    chat_id: i64,
+
+    input_message_content: InputMessageContent,
+}
+
+impl SendDocument {
+    pub fn new(
+        client: &TelegramClient,
+        chat_id: i64,
+        path: impl AsRef<Path>,
+        caption: Option<FormattedText>,
+    ) -> Self {
+        Self {
+            td_type: TdLibType::SendMessage,
+            client_id: client.client_id(),
+            extra: client.generate_extra_handle(),
+            chat_id,
+            input_message_content: InputMessageContent::Document(InputMessageDocument::new(
+                InputFileLocal::new(path),
+                caption,
+            )),
+        }
+    }
+}
@@ -2,45 +2,7 @@ use crate::tdlib::ClientId;
 use crate::{requests::TdLibType, TelegramClient, TelegramRequest};
 use serde::Serialize;
 
-#[derive(Serialize, Debug, Clone)]
-pub struct FormattedText {
-    #[serde(rename = "@type")]
-    td_type: TdLibType,
-
-    text: String,
-}
-
-impl FormattedText {
-    pub fn new(text: String) -> Self {
-        Self {
-            td_type: TdLibType::FormattedText,
-            text,
-        }
-    }
-}
-
-#[derive(Serialize, Debug, Clone)]
-pub struct InputMessageText {
-    #[serde(rename = "@type")]
-    td_type: TdLibType,
-
-    text: FormattedText,
-}
-
-impl InputMessageText {
-    pub fn new(text: FormattedText) -> Self {
-        Self {
-            td_type: TdLibType::InputMessageText,
-            text,
-        }
-    }
-}
-
-#[derive(Serialize, Debug, Clone)]
-#[serde(untagged)]
-pub enum InputMessageContent {
-    Text(InputMessageText),
-}
+use super::{FormattedText, InputMessageContent, InputMessageText};
 
 #[derive(Serialize, Debug, Clone, TelegramRequest)]
 pub struct SendMessage {
@@ -60,14 +22,28 @@ pub struct SendMessage {
 
 impl SendMessage {
     pub fn new(client: &TelegramClient, chat_id: i64, message: String) -> Self {
+        Self::formatted(client, chat_id, FormattedText::new(message))
+    }
+
+    pub fn formatted(client: &TelegramClient, chat_id: i64, message: FormattedText) -> Self {
+        Self::with_content(
+            client,
+            chat_id,
+            InputMessageContent::Text(InputMessageText::new(message)),
+        )
+    }
+
+    pub fn with_content(
+        client: &TelegramClient,
+        chat_id: i64,
+        input_message_content: InputMessageContent,
+    ) -> Self {
         Self {
             td_type: TdLibType::SendMessage,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
             chat_id,
-            input_message_content: InputMessageContent::Text(InputMessageText::new(
-                FormattedText::new(message),
-            )),
+            input_message_content,
         }
     }
 }
@@ -0,0 +1,43 @@
+use crate::tdlib::ClientId;
+use crate::{requests::TdLibType, TelegramClient, TelegramRequest};
+use serde::Serialize;
+use std::path::Path;
+
+use super::{FormattedText, InputFileLocal, InputMessageContent, InputMessagePhoto};
+
+/// Upload a local image and send it as a photo
+#[derive(Serialize, Debug, Clone, TelegramRequest)]
+pub struct SendPhoto {
+    #[serde(rename = "@type")]
+    td_type: TdLibType,
+
+    #[serde(rename = "@client_id")]
+    client_id: ClientId,
+
+    #[serde(rename = "@extra")]
+    extra: String,
+
+    chat_id: i64,
+
+    input_message_content: InputMessageContent,
+}
+
+impl SendPhoto {
+    pub fn new(
+        client: &TelegramClient,
+        chat_id: i64,
+        path: impl AsRef<Path>,
+        caption: Option<FormattedText>,
+    ) -> Self {
+        Self {
+            td_type: TdLibType::SendMessage,
+            client_id: client.client_id(),
+            extra: client.generate_extra_handle(),
+            chat_id,
+            input_message_content: InputMessageContent::Photo(InputMessagePhoto::new(
+                InputFileLocal::new(path),
+                caption,
+            )),
+        }
+    }
+}
@@ -46,27 +46,30 @@ pub struct SetTdLibParameters {
 }
 
 impl SetTdLibParameters {
-    pub fn new(client: &TelegramClient, api_id: i32, api_hash: String) -> Self {
-        // Create directory third_party if it doesn't exist
-        fs::create_dir_all("third_party").unwrap();
+    pub fn new(client: &TelegramClient) -> Self {
+        let config = &client.config;
+
+        // Create data directories if they don't exist
+        fs::create_dir_all(&config.database_directory).unwrap();
+        fs::create_dir_all(&config.files_directory).unwrap();
 
         Self {
             td_type: TdLibType::SetTdlibParameters,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
-            use_test_dc: false,
-            database_directory: "third_party/td".to_string(),
-            files_directory: "third_party/td_files".to_string(),
+            use_test_dc: config.use_test_dc,
+            database_directory: config.database_directory.clone(),
+            files_directory: config.files_directory.clone(),
             use_file_database: true,
             use_chat_info_database: true,
             use_message_database: false,
             use_secret_chats: false,
-            api_id,
-            api_hash,
-            system_language_code: "en".to_string(),
-            device_model: "Magmooty".to_string(),
+            api_id: config.api_id,
+            api_hash: config.api_hash.clone(),
+            system_language_code: config.system_language_code.clone(),
+            device_model: config.device_model.clone(),
             system_version: String::default(),
-            application_version: "v1.0.0".to_string(),
+            application_version: config.application_version.clone(),
             enable_storage_optimizer: true,
             ignore_file_names: false,
         }
@@ -20,7 +20,7 @@ impl SetLogVerbosityLevel {
     pub fn new(client: &TelegramClient, new_verbosity_level: i32) -> Self {
         Self {
             td_type: TdLibType::SetLogVerbosityLevel,
-            client_id: client.client_id,
+            client_id: client.client_id(),
             extra: client.generate_extra_handle(),
             new_verbosity_level,
         }
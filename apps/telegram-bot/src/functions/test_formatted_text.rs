@@ -0,0 +1,125 @@
+#[cfg(test)]
+mod tests {
+    use crate::functions::{FormattedText, TextEntity, TextEntityType};
+
+    #[test]
+    fn test_plain_text_has_no_entities() {
+        let text = FormattedText::from_markdown("Your signin code is: 1234").unwrap();
+
+        assert_eq!(text.text, "Your signin code is: 1234");
+        assert!(text.entities.is_empty());
+    }
+
+    #[test]
+    fn test_parses_bold_italic_and_code() {
+        let text = FormattedText::from_markdown("*Paid* _in full_: `120 EGP`").unwrap();
+
+        assert_eq!(text.text, "Paid in full: 120 EGP");
+        assert_eq!(
+            text.entities,
+            vec![
+                TextEntity::new(0, 4, TextEntityType::Bold),
+                TextEntity::new(5, 7, TextEntityType::Italic),
+                TextEntity::new(14, 7, TextEntityType::Code),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_nested_entities_and_pre() {
+        let text = FormattedText::from_markdown("*bold _both_*\n```let x = *1*;```").unwrap();
+
+        assert_eq!(text.text, "bold both\nlet x = *1*;");
+        assert_eq!(
+            text.entities,
+            vec![
+                TextEntity::new(0, 9, TextEntityType::Bold),
+                TextEntity::new(5, 4, TextEntityType::Italic),
+                TextEntity::new(10, 12, TextEntityType::Pre),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parses_links() {
+        let text =
+            FormattedText::from_markdown("Open [the app](https://magmooty.com/a\\)b)").unwrap();
+
+        assert_eq!(text.text, "Open the app");
+        assert_eq!(
+            text.entities,
+            vec![TextEntity::new(
+                5,
+                7,
+                TextEntityType::TextUrl {
+                    url: "https://magmooty.com/a)b".to_string()
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn test_offsets_are_utf16() {
+        let text = FormattedText::from_markdown("مرحبا 👋 *أحمد*").unwrap();
+
+        assert_eq!(
+            text.entities,
+            vec![TextEntity::new(9, 4, TextEntityType::Bold)]
+        );
+    }
+
+    #[test]
+    fn test_escaped_markers_are_literal() {
+        let text = FormattedText::from_markdown("2 \\* 3 \\_ 4").unwrap();
+
+        assert_eq!(text.text, "2 * 3 _ 4");
+        assert!(text.entities.is_empty());
+    }
+
+    #[test]
+    fn test_unmatched_markers_are_literal() {
+        let text = FormattedText::from_markdown("*6* = 2 * 3 _ `a*b`").unwrap();
+
+        assert_eq!(text.text, "6 = 2 * 3 _ a*b");
+        assert_eq!(
+            text.entities,
+            vec![
+                TextEntity::new(0, 1, TextEntityType::Bold),
+                TextEntity::new(12, 3, TextEntityType::Code),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unmatched_marker_inside_entity() {
+        let text = FormattedText::from_markdown("_2 * 3_").unwrap();
+
+        assert_eq!(text.text, "2 * 3");
+        assert_eq!(
+            text.entities,
+            vec![TextEntity::new(0, 5, TextEntityType::Italic)]
+        );
+    }
+
+    #[test]
+    fn test_rejects_overlapping_entities() {
+        assert!(FormattedText::from_markdown("*a _b* c_").is_err());
+        assert!(FormattedText::from_markdown("_a *b_ c*").is_err());
+    }
+
+    #[test]
+    fn test_rejects_unclosed_entities() {
+        assert!(FormattedText::from_markdown("`code").is_err());
+    }
+
+    #[test]
+    fn test_brackets_without_links_are_literal() {
+        let text = FormattedText::from_markdown("*Group [A]* [link] [b](https://").unwrap();
+
+        assert_eq!(text.text, "Group [A] [link] [b](https://");
+        assert_eq!(
+            text.entities,
+            vec![TextEntity::new(0, 9, TextEntityType::Bold)]
+        );
+    }
+}
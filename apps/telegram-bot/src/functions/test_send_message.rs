@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::{
+        functions::{FormattedText, SendDocument, SendMessage, SendPhoto},
+        TelegramClient,
+    };
+
+    #[test]
+    fn test_send_formatted_message_serialization() {
+        let client = TelegramClient::for_testing();
+        let message = FormattedText::from_markdown("*Hello*").unwrap();
+
+        let request = serde_json::to_value(SendMessage::formatted(&client, 7, message)).unwrap();
+
+        assert_eq!(request["@type"], "sendMessage");
+        assert_eq!(
+            request["input_message_content"],
+            json!({
+                "@type": "inputMessageText",
+                "text": {
+                    "@type": "formattedText",
+                    "text": "Hello",
+                    "entities": [{
+                        "@type": "textEntity",
+                        "offset": 0,
+                        "length": 5,
+                        "type": { "@type": "textEntityTypeBold" }
+                    }]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_send_document_serialization() {
+        let client = TelegramClient::for_testing();
+        let path = std::env::temp_dir().join("receipt.pdf");
+
+        let request = serde_json::to_value(SendDocument::new(
+            &client,
+            7,
+            &path,
+            Some(FormattedText::new("Receipt".to_string())),
+        ))
+        .unwrap();
+
+        let content = &request["input_message_content"];
+
+        assert_eq!(request["@type"], "sendMessage");
+        assert_eq!(request["chat_id"], 7);
+        assert_eq!(content["@type"], "inputMessageDocument");
+        assert_eq!(content["document"]["@type"], "inputFileLocal");
+        assert_eq!(content["document"]["path"], path.to_string_lossy().as_ref());
+        assert_eq!(content["caption"]["text"], "Receipt");
+    }
+
+    #[test]
+    fn test_send_photo_serialization() {
+        let client = TelegramClient::for_testing();
+        let path = std::env::temp_dir().join("report.png");
+
+        let request = serde_json::to_value(SendPhoto::new(&client, 7, &path, None)).unwrap();
+
+        let content = &request["input_message_content"];
+
+        assert_eq!(request["@type"], "sendMessage");
+        assert_eq!(content["@type"], "inputMessagePhoto");
+        assert_eq!(content["photo"]["path"], path.to_string_lossy().as_ref());
+        assert!(content["caption"].is_null());
+    }
+}
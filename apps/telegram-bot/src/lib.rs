@@ -1,22 +1,41 @@
 mod authentication_handler;
+mod config;
 mod connection_state_handler;
+#[cfg(any(test, feature = "testing"))]
+mod fake_tdlib;
+mod monitor;
 pub mod functions;
 mod requests;
 mod tdlib;
 mod classes;
+mod commands;
+mod test_client;
+mod test_commands;
+mod test_config;
+mod test_fake_tdlib;
+mod test_monitor;
 
 pub use authentication_handler::{AuthorizationHandler, ConsoleAuthorizationHandler};
+pub use commands::{BotCommand, CommandContext, CommandHandler, CommandRouter};
+pub use config::TelegramConfig;
 pub use connection_state_handler::{ConnectionHandler, ConsoleConnectionHandler};
+#[cfg(any(test, feature = "testing"))]
+pub use fake_tdlib::{FakeTdBackend, FakeTdEvent};
+pub use monitor::{
+    TelegramAlertHandler, TelegramAuthorizationStatus, TelegramConnectionStatus, TelegramStatus,
+};
 pub use requests::TdLibType;
+pub use tdlib::{ClientId, TdBackend, TdJsonBackend};
 pub use classes::*;
 
-use log::{debug, error, trace};
+use log::{debug, error, info, trace};
+use monitor::TelegramMonitor;
 use requests::{AuthorizationState, TDLibResponse, TelegramRequest};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-use tdlib::{new_client, receive, send};
-use tokio::sync::{oneshot, Mutex};
+use std::time::Duration;
+use tokio::sync::{oneshot, watch, Mutex};
 use tokio::task::JoinHandle;
 use tokio::time;
 
@@ -25,43 +44,98 @@ extern crate telegram_macros;
 
 static LOG_TARGET: &str = "Telegram";
 
+/// Default time to wait for tdlib to answer a request
+pub const DEFAULT_REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Snapshot of the request counters of a [`TelegramClient`]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TelegramClientMetrics {
+    /// Requests currently waiting for a response from tdlib
+    pub pending_requests: usize,
+    /// Requests sent to tdlib since the client was created
+    pub sent_requests: u64,
+    /// Requests that received a response from tdlib
+    pub completed_requests: u64,
+    /// Requests that gave up waiting for a response from tdlib
+    pub timed_out_requests: u64,
+    /// Handles removed because their caller stopped waiting for a response
+    pub orphaned_requests: u64,
+}
+
+#[derive(Default)]
+struct RequestCounters {
+    sent: AtomicU64,
+    completed: AtomicU64,
+    timed_out: AtomicU64,
+    orphaned: AtomicU64,
+}
+
 pub struct TelegramClient {
+    backend: Arc<dyn TdBackend>,
     request_handles: Arc<Mutex<HashMap<String, oneshot::Sender<TDLibResponse>>>>,
+    request_counter: AtomicU64,
+    request_counters: Arc<RequestCounters>,
+    request_timeout: Duration,
+    command_router: Arc<Mutex<Option<Arc<CommandRouter>>>>,
+    monitor: Arc<TelegramMonitor>,
     listener_task: Option<JoinHandle<()>>,
-    pub client_id: i32,
+    client_id: Arc<AtomicI32>,
+    pub config: TelegramConfig,
     pub version: Option<String>,
     pub commit_hash: Option<String>,
     pub authorization_state: Option<AuthorizationState>,
 }
 
 impl TelegramClient {
-    pub fn for_testing() -> Arc<Self> {
-        Arc::new(TelegramClient {
+    fn new(config: TelegramConfig, backend: Arc<dyn TdBackend>) -> Self {
+        let client_id = backend.create_client_id();
+
+        TelegramClient {
+            backend,
             request_handles: Arc::new(Mutex::new(HashMap::new())),
+            request_counter: AtomicU64::new(0),
+            request_counters: Arc::new(RequestCounters::default()),
+            request_timeout: config.request_timeout,
+            command_router: Arc::new(Mutex::new(None)),
+            monitor: Arc::new(TelegramMonitor::new()),
             listener_task: None,
-            client_id: 1,
+            client_id: Arc::new(AtomicI32::new(client_id)),
+            config,
             version: None,
             commit_hash: None,
             authorization_state: None,
-        })
+        }
+    }
+
+    /// Client backed by a [`FakeTdBackend`] that is not listening for responses
+    #[cfg(any(test, feature = "testing"))]
+    pub fn for_testing() -> Arc<Self> {
+        Arc::new(TelegramClient::new(
+            TelegramConfig::new(0, String::new()),
+            Arc::new(FakeTdBackend::new()),
+        ))
     }
 
-    pub async fn init<H, C>() -> Arc<Self>
+    pub async fn init<H, C>(config: TelegramConfig) -> Arc<Self>
     where
         H: AuthorizationHandler + 'static,
         C: ConnectionHandler + 'static,
     {
-        let client_id = new_client();
-        let mut client = TelegramClient {
-            client_id,
-            request_handles: Arc::new(Mutex::new(HashMap::new())),
-            listener_task: None,
-            version: None,
-            commit_hash: None,
-            authorization_state: None,
-        };
+        Self::init_with_backend::<H, C>(config, Arc::new(TdJsonBackend)).await
+    }
+
+    /// Initialize a client talking to tdlib through the given backend
+    pub async fn init_with_backend<H, C>(
+        config: TelegramConfig,
+        backend: Arc<dyn TdBackend>,
+    ) -> Arc<Self>
+    where
+        H: AuthorizationHandler + 'static,
+        C: ConnectionHandler + 'static,
+    {
+        let mut client = TelegramClient::new(config, backend);
 
-        let (mut auth_rx, mut conn_rx) = client.start_receiving().await;
+        let (mut auth_rx, mut conn_rx, mut message_rx) = client.start_receiving().await;
 
         let client = Arc::new(client);
 
@@ -75,6 +149,8 @@ impl TelegramClient {
 
         let authorization_handler = H::new(client.clone());
         let connection_state_handler = C::new();
+        let auth_client = client.clone();
+        let conn_monitor = client.monitor.clone();
 
         tokio::spawn(async move {
             loop {
@@ -87,6 +163,11 @@ impl TelegramClient {
                 let response = response.unwrap();
                 let authorization_state = response.authorization_state.unwrap();
 
+                auth_client
+                    .monitor
+                    .update_authorization(&authorization_state.state)
+                    .await;
+
                 match authorization_state.state {
                     requests::AuthorizationState::AuthorizationStateWaitTdlibParameters => {
                         authorization_handler.handle_set_tdlib_params().await;
@@ -129,6 +210,7 @@ impl TelegramClient {
                     }
                     requests::AuthorizationState::AuthorizationStateClosed => {
                         authorization_handler.handle_closed().await;
+                        auth_client.restart().await;
                     }
                 }
             }
@@ -145,6 +227,8 @@ impl TelegramClient {
                 let response = response.unwrap();
                 let connection_state = response.state.unwrap();
 
+                conn_monitor.update_connection(&connection_state.state);
+
                 match connection_state.state {
                     requests::ConnectionState::ConnectionStateWaitingForNetwork => {
                         connection_state_handler.handle_waiting_for_network().await;
@@ -165,9 +249,84 @@ impl TelegramClient {
             }
         });
 
+        let command_router = client.command_router.clone();
+        let message_client = client.clone();
+
+        tokio::spawn(async move {
+            while let Some(response) = message_rx.recv().await {
+                let message = response
+                    .data
+                    .get("message")
+                    .map(|message| serde_json::from_value::<TelegramMessage>(message.clone()));
+
+                let message = match message {
+                    Some(Ok(message)) => message,
+                    _ => {
+                        debug!(target: LOG_TARGET, "Failed to parse new message update");
+                        continue;
+                    }
+                };
+
+                let command_router = command_router.lock().await.clone();
+
+                if let Some(command_router) = command_router {
+                    let client = message_client.clone();
+
+                    tokio::spawn(async move {
+                        command_router.dispatch(client, message).await;
+                    });
+                }
+            }
+        });
+
         client
     }
 
+    /// Route incoming text commands (e.g. `/start`) through the given router
+    pub async fn set_command_router(&self, command_router: CommandRouter) {
+        *self.command_router.lock().await = Some(Arc::new(command_router));
+    }
+
+    /// Current authorization and connection state of the client
+    pub fn status(&self) -> TelegramStatus {
+        self.monitor.status()
+    }
+
+    /// Receiver notified every time the authorization or connection state changes
+    pub fn subscribe_status(&self) -> watch::Receiver<TelegramStatus> {
+        self.monitor.subscribe()
+    }
+
+    /// Notify admins through the given handler when the account has to be logged in again
+    pub async fn set_alert_handler(&self, alert_handler: impl TelegramAlertHandler + 'static) {
+        self.monitor.set_alert_handler(Arc::new(alert_handler)).await;
+    }
+
+    /// Identifier of the tdlib client, changes when the client is restarted
+    pub fn client_id(&self) -> ClientId {
+        self.client_id.load(Ordering::Relaxed)
+    }
+
+    /// Replace the tdlib client with a new one, tdlib closes clients that are logged out or revoked
+    ///
+    /// The new client goes through the whole authorization flow again starting from tdlib parameters.
+    pub async fn restart(&self) {
+        let client_id = self.backend.create_client_id();
+
+        info!(target: LOG_TARGET, "Restarting tdlib client {} as {}", self.client_id(), client_id);
+
+        self.client_id.store(client_id, Ordering::Relaxed);
+
+        // Requests sent to the closed client will never be answered
+        self.request_handles.lock().await.clear();
+
+        self.monitor.record_restart();
+
+        let _ = self
+            .send(functions::GetAuthorizationState::new(self))
+            .await;
+    }
+
     /// Start listening for tdlib events and returns a receiver for authentication messages
     /// # Returns
     ///
@@ -175,20 +334,28 @@ impl TelegramClient {
     ///
     /// 1. Listener for authentication messages
     /// 2. Listener for connection state updates
+    /// 3. Listener for new incoming messages
     async fn start_receiving(
         &mut self,
     ) -> (
         tokio::sync::mpsc::Receiver<TDLibResponse>,
         tokio::sync::mpsc::Receiver<TDLibResponse>,
+        tokio::sync::mpsc::Receiver<TDLibResponse>,
     ) {
         let (auth_tx, auth_rx) = tokio::sync::mpsc::channel::<TDLibResponse>(100);
         let (conn_tx, conn_rx) = tokio::sync::mpsc::channel::<TDLibResponse>(100);
+        let (message_tx, message_rx) = tokio::sync::mpsc::channel::<TDLibResponse>(100);
 
+        let backend = self.backend.clone();
         let request_handles_arc = self.request_handles.clone();
+        let request_counters = self.request_counters.clone();
+        let client_id = self.client_id.clone();
 
         let join_handle = tokio::spawn(async move {
             loop {
-                let response = tokio::task::spawn_blocking(move || receive(300.0)).await;
+                let receive_backend = backend.clone();
+                let response =
+                    tokio::task::spawn_blocking(move || receive_backend.receive(300.0)).await;
 
                 if let Err(e) = response {
                     error!("Failed to receive response from tdlib: {}", e.to_string());
@@ -214,6 +381,11 @@ impl TelegramClient {
                 let response = response.unwrap();
 
                 if let None = response.extra.as_ref() {
+                    if response.client_id != client_id.load(Ordering::Relaxed) {
+                        debug!(target: LOG_TARGET, "Ignoring update of a closed client: {}", event);
+                        continue;
+                    }
+
                     match response.td_type {
                         TdLibType::UpdateAuthorizationState => {
                             debug!(target: LOG_TARGET, "Found authorization state update event: {}", event);
@@ -223,6 +395,10 @@ impl TelegramClient {
                             debug!(target: LOG_TARGET, "Found connection state update event: {}", event);
                             conn_tx.send(response).await.unwrap_or_default();
                         }
+                        TdLibType::UpdateNewMessage => {
+                            debug!(target: LOG_TARGET, "Found new message event: {}", event);
+                            message_tx.send(response).await.unwrap_or_default();
+                        }
                         _ => {
                             trace!(target: LOG_TARGET, "No handle found in response {}", event);
                             continue;
@@ -231,6 +407,7 @@ impl TelegramClient {
                 } else if let Some(handle) = response.extra.as_ref() {
                     if let Some(sender) = request_handles_arc.lock().await.remove(handle) {
                         debug!(target: LOG_TARGET, "Found active handle for response: {}", event);
+                        request_counters.completed.fetch_add(1, Ordering::Relaxed);
                         let _ = sender.send(response);
                     } else {
                         debug!(target: LOG_TARGET, "No active handle for response: {}", event);
@@ -241,54 +418,92 @@ impl TelegramClient {
 
         self.listener_task = Some(join_handle);
 
-        (auth_rx, conn_rx)
+        (auth_rx, conn_rx, message_rx)
     }
 
-    /// Generate a new random handle for @extra field in tdlib
+    /// Generate a new unique handle for @extra field in tdlib
     fn generate_extra_handle(&self) -> String {
         format!(
             "{}-{}",
-            self.client_id,
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Cannot get system time")
-                .as_millis()
+            self.client_id(),
+            self.request_counter.fetch_add(1, Ordering::Relaxed)
         )
     }
 
-    /// Send a request to tdlib
+    /// Number of requests currently waiting for a response from tdlib
+    pub async fn pending_requests(&self) -> usize {
+        self.request_handles.lock().await.len()
+    }
+
+    /// Snapshot of the request counters of this client
+    pub async fn metrics(&self) -> TelegramClientMetrics {
+        TelegramClientMetrics {
+            pending_requests: self.pending_requests().await,
+            sent_requests: self.request_counters.sent.load(Ordering::Relaxed),
+            completed_requests: self.request_counters.completed.load(Ordering::Relaxed),
+            timed_out_requests: self.request_counters.timed_out.load(Ordering::Relaxed),
+            orphaned_requests: self.request_counters.orphaned.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Remove handles whose callers stopped waiting for a response (e.g. the calling future was dropped)
+    async fn remove_orphaned_handles(&self) {
+        let mut request_handles = self.request_handles.lock().await;
+        let pending_requests = request_handles.len();
+
+        request_handles.retain(|_, sender| !sender.is_closed());
+
+        let orphaned_requests = pending_requests - request_handles.len();
+
+        if orphaned_requests > 0 {
+            debug!(target: LOG_TARGET, "Removed {} orphaned request handles", orphaned_requests);
+            self.request_counters
+                .orphaned
+                .fetch_add(orphaned_requests as u64, Ordering::Relaxed);
+        }
+    }
+
+    /// Send a request to tdlib and wait for its response using the default timeout
     pub async fn send(&self, request: impl TelegramRequest) -> Result<TDLibResponse, String> {
+        self.send_with_timeout(request, self.request_timeout).await
+    }
+
+    /// Send a request to tdlib and wait up to `timeout` for its response
+    pub async fn send_with_timeout(
+        &self,
+        request: impl TelegramRequest,
+        timeout: Duration,
+    ) -> Result<TDLibResponse, String> {
+        let extra = request.extra();
+
+        let request_json = serde_json::to_string(&request).map_err(|e| {
+            error!("Failed to serialize request to tdlib: {}", e);
+            e.to_string()
+        })?;
+
+        self.remove_orphaned_handles().await;
+
         let (tx, rx) = oneshot::channel();
 
-        debug!(target: LOG_TARGET,
-            "Assigning a request handle for request: {}",
-            request.extra()
-        );
+        debug!(target: LOG_TARGET, "Assigning a request handle for request: {}", extra);
 
-        self.request_handles
-            .lock()
-            .await
-            .insert(request.extra(), tx);
+        self.request_handles.lock().await.insert(extra.clone(), tx);
 
-        match serde_json::to_string(&request) {
-            Ok(request_json) => {
-                debug!(target: LOG_TARGET, "Sending request to tdlib: {}", request.extra());
+        debug!(target: LOG_TARGET, "Sending request to tdlib: {}", extra);
 
-                send(self.client_id, request_json.as_str());
+        self.backend.send(self.client_id(), request_json.as_str());
+        self.request_counters.sent.fetch_add(1, Ordering::Relaxed);
 
-                debug!(target: LOG_TARGET, "Sent request to tdlib: {}", request.extra());
+        debug!(target: LOG_TARGET, "Sent request to tdlib: {}", extra);
 
-                match time::timeout(Duration::from_secs(5), rx).await {
-                    Ok(response) => response.map_err(|err| (err.to_string())),
-                    Err(e) => {
-                        error!("Timed out waiting for response from tdlib: {}", e);
-                        self.request_handles.lock().await.remove(&request.extra());
-                        Err(e.to_string())
-                    }
-                }
-            }
+        match time::timeout(timeout, rx).await {
+            Ok(response) => response.map_err(|err| err.to_string()),
             Err(e) => {
-                error!("Failed to serialize request to tdlib: {}", e);
+                error!("Timed out waiting for response from tdlib: {}", e);
+                self.request_handles.lock().await.remove(&extra);
+                self.request_counters
+                    .timed_out
+                    .fetch_add(1, Ordering::Relaxed);
                 Err(e.to_string())
             }
         }
@@ -0,0 +1,194 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::{watch, Mutex};
+
+use crate::requests::{AuthorizationState, ConnectionState};
+
+static LOG_TARGET: &str = "Telegram Monitor";
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TelegramAuthorizationStatus {
+    /// No authorization update received yet
+    Unknown,
+    WaitingForParameters,
+    /// An admin has to log in again (QR code, password, ...)
+    WaitingForLogin,
+    Ready,
+    LoggingOut,
+    Closing,
+    Closed,
+}
+
+impl From<&AuthorizationState> for TelegramAuthorizationStatus {
+    fn from(state: &AuthorizationState) -> Self {
+        match state {
+            AuthorizationState::AuthorizationStateWaitTdlibParameters => Self::WaitingForParameters,
+            AuthorizationState::AuthorizationStateWaitPhoneNumber
+            | AuthorizationState::AuthorizationStateWaitEmailAddress
+            | AuthorizationState::AuthorizationStateWaitEmailCode
+            | AuthorizationState::AuthorizationStateWaitCode
+            | AuthorizationState::AuthorizationStateWaitOtherDeviceConfirmation
+            | AuthorizationState::AuthorizationStateWaitRegistration
+            | AuthorizationState::AuthorizationStateWaitPassword => Self::WaitingForLogin,
+            AuthorizationState::AuthorizationStateReady => Self::Ready,
+            AuthorizationState::AuthorizationStateLoggingOut => Self::LoggingOut,
+            AuthorizationState::AuthorizationStateClosing => Self::Closing,
+            AuthorizationState::AuthorizationStateClosed => Self::Closed,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TelegramConnectionStatus {
+    /// No connection update received yet
+    Unknown,
+    WaitingForNetwork,
+    ConnectingToProxy,
+    Connecting,
+    Updating,
+    Ready,
+}
+
+impl From<&ConnectionState> for TelegramConnectionStatus {
+    fn from(state: &ConnectionState) -> Self {
+        match state {
+            ConnectionState::ConnectionStateWaitingForNetwork => Self::WaitingForNetwork,
+            ConnectionState::ConnectionStateConnectingToProxy => Self::ConnectingToProxy,
+            ConnectionState::ConnectionStateConnecting => Self::Connecting,
+            ConnectionState::ConnectionStateUpdating => Self::Updating,
+            ConnectionState::ConnectionStateReady => Self::Ready,
+        }
+    }
+}
+
+/// Current authorization and connection state of a [`crate::TelegramClient`]
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct TelegramStatus {
+    pub authorization: TelegramAuthorizationStatus,
+    pub connection: TelegramConnectionStatus,
+    /// Number of times the client was recreated after tdlib closed it
+    pub restarts: u64,
+}
+
+impl Default for TelegramStatus {
+    fn default() -> Self {
+        Self {
+            authorization: TelegramAuthorizationStatus::Unknown,
+            connection: TelegramConnectionStatus::Unknown,
+            restarts: 0,
+        }
+    }
+}
+
+impl TelegramStatus {
+    /// Whether requests that need a logged in account (e.g. sending messages) can succeed
+    pub fn is_authorized(&self) -> bool {
+        self.authorization == TelegramAuthorizationStatus::Ready
+    }
+
+    pub fn is_login_required(&self) -> bool {
+        self.authorization == TelegramAuthorizationStatus::WaitingForLogin
+    }
+}
+
+#[async_trait::async_trait]
+pub trait TelegramAlertHandler: Send + Sync {
+    /// Called once every time the client starts waiting for an admin to log in
+    async fn handle_login_required(&self, status: &TelegramStatus) -> ();
+}
+
+/// Tracks the state of a client and alerts admins when it needs attention
+pub(crate) struct TelegramMonitor {
+    status: watch::Sender<TelegramStatus>,
+    alert_handler: Mutex<Option<Arc<dyn TelegramAlertHandler>>>,
+}
+
+impl TelegramMonitor {
+    pub(crate) fn new() -> Self {
+        Self {
+            status: watch::Sender::new(TelegramStatus::default()),
+            alert_handler: Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn status(&self) -> TelegramStatus {
+        self.status.borrow().clone()
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<TelegramStatus> {
+        self.status.subscribe()
+    }
+
+    /// Set the alert handler, alerts right away if login is already required
+    pub(crate) async fn set_alert_handler(&self, alert_handler: Arc<dyn TelegramAlertHandler>) {
+        *self.alert_handler.lock().await = Some(alert_handler);
+
+        if self.status().is_login_required() {
+            self.alert_login_required().await;
+        }
+    }
+
+    pub(crate) async fn update_authorization(&self, state: &AuthorizationState) {
+        let authorization = TelegramAuthorizationStatus::from(state);
+        let mut login_required = false;
+
+        self.status.send_if_modified(|status| {
+            if status.authorization == authorization {
+                return false;
+            }
+
+            info!(
+                target: LOG_TARGET,
+                "Authorization status changed from {:?} to {:?}", status.authorization, authorization
+            );
+
+            login_required = authorization == TelegramAuthorizationStatus::WaitingForLogin;
+            status.authorization = authorization;
+            true
+        });
+
+        if login_required {
+            self.alert_login_required().await;
+        }
+    }
+
+    pub(crate) fn update_connection(&self, state: &ConnectionState) {
+        let connection = TelegramConnectionStatus::from(state);
+
+        self.status.send_if_modified(|status| {
+            if status.connection == connection {
+                return false;
+            }
+
+            status.connection = connection;
+            true
+        });
+    }
+
+    /// Reset the state after the client was recreated
+    pub(crate) fn record_restart(&self) {
+        self.status.send_modify(|status| {
+            status.authorization = TelegramAuthorizationStatus::Unknown;
+            status.connection = TelegramConnectionStatus::Unknown;
+            status.restarts += 1;
+        });
+    }
+
+    async fn alert_login_required(&self) {
+        warn!(target: LOG_TARGET, "Telegram is waiting for an admin to log in");
+
+        let alert_handler = self.alert_handler.lock().await.clone();
+
+        if let Some(alert_handler) = alert_handler {
+            let status = self.status();
+
+            // Alerts may be slow (e.g. WhatsApp), don't hold up state handling
+            tokio::spawn(async move {
+                alert_handler.handle_login_required(&status).await;
+            });
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use super::tdlib::ClientId;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum TdLibType {
     SendMessage,
@@ -12,7 +12,11 @@ pub enum TdLibType {
     UpdateMessageSendAcknowledged,
     UpdateSavedMessagesTopic,
     InputMessageText,
+    InputMessageDocument,
+    InputMessagePhoto,
+    InputFileLocal,
     FormattedText,
+    TextEntity,
     UpdateAuthorizationState,
     GetAuthorizationState,
     SetLogVerbosityLevel,
@@ -65,6 +69,7 @@ pub enum TdLibType {
     SearchUserByPhoneNumber,
     SearchContacts,
     CreatePrivateChat,
+    GetUser,
     UpdateChatActiveStories,
     UpdateGroupCall,
     UpdateChatTheme,
@@ -146,7 +151,7 @@ pub struct TDLibResponse {
     pub td_type: TdLibType,
 
     #[serde(rename = "@client_id")]
-    client_id: ClientId,
+    pub(crate) client_id: ClientId,
 
     #[serde(rename = "@extra")]
     pub extra: Option<String>,
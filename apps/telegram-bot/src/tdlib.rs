@@ -12,19 +12,36 @@ extern "C" {
     fn td_receive(timeout: c_double) -> *const c_char;
 }
 
-pub fn new_client() -> ClientId {
-    unsafe { td_create_client_id() }
-}
+/// Transport used by [`crate::TelegramClient`] to talk to tdlib
+pub trait TdBackend: Send + Sync {
+    /// Create a new tdlib client instance and return its identifier
+    fn create_client_id(&self) -> ClientId;
+
+    /// Send a JSON serialized request to the client with the given identifier
+    fn send(&self, client_id: ClientId, request: &str);
 
-pub fn send(client_id: ClientId, request: &str) {
-    let cstring = CString::new(request).unwrap();
-    unsafe { td_send(client_id, cstring.as_ptr()) }
+    /// Wait up to `timeout` seconds for the next response or update from tdlib
+    fn receive(&self, timeout: f64) -> Option<String>;
 }
 
-pub fn receive(timeout: f64) -> Option<String> {
-    unsafe {
-        td_receive(timeout)
-            .as_ref()
-            .map(|response| CStr::from_ptr(response).to_string_lossy().into_owned())
+/// Backend linked against the native tdjson library
+pub struct TdJsonBackend;
+
+impl TdBackend for TdJsonBackend {
+    fn create_client_id(&self) -> ClientId {
+        unsafe { td_create_client_id() }
+    }
+
+    fn send(&self, client_id: ClientId, request: &str) {
+        let cstring = CString::new(request).unwrap();
+        unsafe { td_send(client_id, cstring.as_ptr()) }
+    }
+
+    fn receive(&self, timeout: f64) -> Option<String> {
+        unsafe {
+            td_receive(timeout)
+                .as_ref()
+                .map(|response| CStr::from_ptr(response).to_string_lossy().into_owned())
+        }
     }
 }
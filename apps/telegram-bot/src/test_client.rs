@@ -0,0 +1,104 @@
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use crate::{functions::GetAuthorizationState, TelegramClient, TelegramRequest};
+
+    const SHORT_TIMEOUT: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn test_extra_handles_are_unique_under_concurrency() {
+        let client = TelegramClient::for_testing();
+
+        let tasks: Vec<_> = (0..500)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move { GetAuthorizationState::new(&client).extra() })
+            })
+            .collect();
+
+        let mut handles = HashSet::new();
+
+        for task in tasks {
+            handles.insert(task.await.unwrap());
+        }
+
+        assert_eq!(handles.len(), 500);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_timeout_removes_handle_on_timeout() {
+        let client = TelegramClient::for_testing();
+
+        let response = client
+            .send_with_timeout(GetAuthorizationState::new(&client), SHORT_TIMEOUT)
+            .await;
+
+        assert!(response.is_err());
+
+        let metrics = client.metrics().await;
+
+        assert_eq!(metrics.pending_requests, 0);
+        assert_eq!(metrics.sent_requests, 1);
+        assert_eq!(metrics.timed_out_requests, 1);
+        assert_eq!(metrics.completed_requests, 0);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_tracked_independently() {
+        let client = TelegramClient::for_testing();
+
+        let tasks: Vec<_> = (0..300)
+            .map(|_| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    client
+                        .send_with_timeout(GetAuthorizationState::new(&client), SHORT_TIMEOUT)
+                        .await
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            assert!(task.await.unwrap().is_err());
+        }
+
+        let metrics = client.metrics().await;
+
+        assert_eq!(metrics.pending_requests, 0);
+        assert_eq!(metrics.sent_requests, 300);
+        assert_eq!(metrics.timed_out_requests, 300);
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_handles_are_removed() {
+        let client = TelegramClient::for_testing();
+
+        let orphan_client = client.clone();
+        let orphan = tokio::spawn(async move {
+            orphan_client
+                .send_with_timeout(
+                    GetAuthorizationState::new(&orphan_client),
+                    Duration::from_secs(60),
+                )
+                .await
+        });
+
+        while client.pending_requests().await == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        orphan.abort();
+        let _ = orphan.await;
+
+        let _ = client
+            .send_with_timeout(GetAuthorizationState::new(&client), SHORT_TIMEOUT)
+            .await;
+
+        let metrics = client.metrics().await;
+
+        assert_eq!(metrics.pending_requests, 0);
+        assert_eq!(metrics.orphaned_requests, 1);
+    }
+}
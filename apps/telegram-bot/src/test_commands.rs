@@ -0,0 +1,135 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use serde_json::{json, Value};
+
+    use crate::{
+        functions::FormattedText, BotCommand, CommandContext, CommandHandler, CommandRouter,
+        ConsoleAuthorizationHandler, ConsoleConnectionHandler, FakeTdBackend, TelegramClient,
+        TelegramConfig,
+    };
+
+    struct EchoCommandHandler {}
+
+    #[async_trait::async_trait]
+    impl CommandHandler for EchoCommandHandler {
+        async fn handle(&self, context: &CommandContext) -> Option<FormattedText> {
+            Some(FormattedText::new(format!(
+                "{}: {}",
+                context.command.name,
+                context.command.args.join(",")
+            )))
+        }
+    }
+
+    fn new_message_update(text: &str, is_outgoing: bool) -> Value {
+        json!({
+            "@type": "updateNewMessage",
+            "message": {
+                "@type": "message",
+                "id": 1,
+                "chat_id": 55,
+                "sender_id": { "@type": "messageSenderUser", "user_id": 77 },
+                "is_outgoing": is_outgoing,
+                "content": {
+                    "@type": "messageText",
+                    "text": { "@type": "formattedText", "text": text, "entities": [] }
+                }
+            }
+        })
+    }
+
+    async fn setup() -> (Arc<FakeTdBackend>, Arc<TelegramClient>) {
+        let backend = Arc::new(FakeTdBackend::new());
+        backend.reply_with("sendMessage", json!({ "@type": "message" }));
+
+        let client = TelegramClient::init_with_backend::<
+            ConsoleAuthorizationHandler,
+            ConsoleConnectionHandler,
+        >(TelegramConfig::new(0, String::new()), backend.clone())
+        .await;
+
+        client
+            .set_command_router(CommandRouter::new().register(
+                "balance",
+                "Show the remaining balance",
+                EchoCommandHandler {},
+            ))
+            .await;
+
+        (backend, client)
+    }
+
+    async fn wait_for_replies(backend: &FakeTdBackend, count: usize) -> Vec<Value> {
+        for _ in 0..100 {
+            let replies = backend.sent_requests_of_type("sendMessage");
+
+            if replies.len() >= count {
+                return replies;
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        backend.sent_requests_of_type("sendMessage")
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(
+            BotCommand::parse("/Schedule@MagmootyBot  2 math"),
+            Some(BotCommand {
+                name: "schedule".to_string(),
+                args: vec!["2".to_string(), "math".to_string()],
+            })
+        );
+        assert_eq!(BotCommand::parse("hello /start"), None);
+        assert_eq!(BotCommand::parse("/"), None);
+        assert_eq!(BotCommand::parse(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_dispatches_command_and_replies() {
+        let (backend, _client) = setup().await;
+
+        backend.emit_update(new_message_update("/balance 1 2", false));
+
+        let replies = wait_for_replies(&backend, 1).await;
+
+        assert_eq!(replies.len(), 1);
+        assert_eq!(replies[0]["chat_id"], 55);
+        assert_eq!(
+            replies[0]["input_message_content"]["text"]["text"],
+            "balance: 1,2"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_command_replies_with_help() {
+        let (backend, _client) = setup().await;
+
+        backend.emit_update(new_message_update("/unknown", false));
+
+        let replies = wait_for_replies(&backend, 1).await;
+
+        assert_eq!(replies.len(), 1);
+        assert_eq!(
+            replies[0]["input_message_content"]["text"]["text"],
+            "Available commands:\n/balance - Show the remaining balance"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ignores_outgoing_and_plain_messages() {
+        let (backend, _client) = setup().await;
+
+        backend.emit_update(new_message_update("/balance", true));
+        backend.emit_update(new_message_update("Thanks!", false));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        assert!(backend.sent_requests_of_type("sendMessage").is_empty());
+    }
+}
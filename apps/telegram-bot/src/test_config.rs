@@ -0,0 +1,38 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        functions::SetTdLibParameters, ConsoleAuthorizationHandler, ConsoleConnectionHandler,
+        FakeTdBackend, TelegramClient, TelegramConfig,
+    };
+
+    #[tokio::test]
+    async fn test_set_tdlib_parameters_uses_config() {
+        let data_directory = std::env::temp_dir().join("telegram-bot-test-config");
+
+        let config = TelegramConfig::new(42, "hash".to_string())
+            .data_directory(&data_directory)
+            .use_test_dc(true)
+            .application_version("v2.3.4".to_string());
+
+        let client = TelegramClient::init_with_backend::<
+            ConsoleAuthorizationHandler,
+            ConsoleConnectionHandler,
+        >(config, Arc::new(FakeTdBackend::new()))
+        .await;
+
+        let request = serde_json::to_value(SetTdLibParameters::new(&client)).unwrap();
+
+        assert_eq!(request["api_id"], 42);
+        assert_eq!(request["api_hash"], "hash");
+        assert_eq!(request["use_test_dc"], true);
+        assert_eq!(request["application_version"], "v2.3.4");
+        assert_eq!(
+            request["database_directory"],
+            data_directory.join("td").to_string_lossy().as_ref()
+        );
+        assert!(data_directory.join("td").is_dir());
+        assert!(data_directory.join("td_files").is_dir());
+    }
+}
@@ -0,0 +1,206 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::{
+        functions::SearchContacts, AuthorizationHandler, ConnectionHandler,
+        ConsoleAuthorizationHandler, ConsoleConnectionHandler, FakeTdBackend, FakeTdEvent,
+        TdLibType, TelegramClient, TelegramConfig,
+    };
+
+    static RECORDED_STATES: Mutex<Vec<&'static str>> = Mutex::new(Vec::new());
+
+    struct RecordingAuthorizationHandler {
+        client: Arc<TelegramClient>,
+    }
+
+    #[async_trait::async_trait]
+    impl AuthorizationHandler for RecordingAuthorizationHandler {
+        fn new(client: Arc<TelegramClient>) -> Self {
+            Self { client }
+        }
+
+        fn get_client(&self) -> Arc<TelegramClient> {
+            self.client.clone()
+        }
+
+        async fn handle_set_tdlib_params(&self) {
+            RECORDED_STATES.lock().unwrap().push("set_tdlib_params");
+        }
+
+        async fn handle_wait_other_device_confirmation(&self, _: String) {
+            RECORDED_STATES
+                .lock()
+                .unwrap()
+                .push("wait_other_device_confirmation");
+        }
+
+        async fn handle_wait_password(&self, _: Option<String>) {
+            RECORDED_STATES.lock().unwrap().push("wait_password");
+        }
+
+        async fn handle_ready(&self) {
+            RECORDED_STATES.lock().unwrap().push("authorization_ready");
+        }
+
+        async fn handle_closed(&self) {
+            RECORDED_STATES.lock().unwrap().push("closed");
+        }
+    }
+
+    struct RecordingConnectionHandler {}
+
+    #[async_trait::async_trait]
+    impl ConnectionHandler for RecordingConnectionHandler {
+        fn new() -> Self {
+            Self {}
+        }
+
+        async fn handle_waiting_for_network(&self) {
+            RECORDED_STATES.lock().unwrap().push("waiting_for_network");
+        }
+
+        async fn handle_connecting_to_proxy(&self) {}
+
+        async fn handle_connecting(&self) {
+            RECORDED_STATES.lock().unwrap().push("connecting");
+        }
+
+        async fn handle_updating(&self) {}
+
+        async fn handle_ready(&self) {
+            RECORDED_STATES.lock().unwrap().push("connection_ready");
+        }
+    }
+
+    async fn wait_for_recorded_states(count: usize) -> Vec<&'static str> {
+        for _ in 0..200 {
+            let states = RECORDED_STATES.lock().unwrap().clone();
+
+            if states.len() >= count {
+                return states;
+            }
+
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        RECORDED_STATES.lock().unwrap().clone()
+    }
+
+    async fn setup() -> (Arc<FakeTdBackend>, Arc<TelegramClient>) {
+        let backend = Arc::new(FakeTdBackend::new());
+        let client = TelegramClient::init_with_backend::<
+            ConsoleAuthorizationHandler,
+            ConsoleConnectionHandler,
+        >(TelegramConfig::new(0, String::new()), backend.clone())
+        .await;
+
+        (backend, client)
+    }
+
+    #[tokio::test]
+    async fn test_init_queries_authorization_state() {
+        let (backend, _client) = setup().await;
+
+        assert_eq!(
+            backend.sent_requests_of_type("setLogVerbosityLevel").len(),
+            1
+        );
+        assert_eq!(
+            backend.sent_requests_of_type("getAuthorizationState").len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_send_correlates_concurrent_responses() {
+        let (backend, client) = setup().await;
+
+        backend.respond_to("searchContacts", |request| {
+            vec![FakeTdEvent::Response(json!({
+                "@type": "users",
+                "query": request["query"],
+            }))]
+        });
+
+        let tasks: Vec<_> = (0..300)
+            .map(|i| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    let response = client
+                        .send(SearchContacts::new(&client, format!("query-{}", i)))
+                        .await
+                        .unwrap();
+
+                    (i, response)
+                })
+            })
+            .collect();
+
+        for task in tasks {
+            let (i, response) = task.await.unwrap();
+
+            assert!(matches!(response.td_type, TdLibType::Users));
+            assert_eq!(response.data["query"], format!("query-{}", i));
+        }
+
+        let metrics = client.metrics().await;
+
+        assert_eq!(metrics.pending_requests, 0);
+        assert_eq!(metrics.completed_requests, 302);
+    }
+
+    #[tokio::test]
+    async fn test_send_times_out_without_response() {
+        let (backend, client) = setup().await;
+
+        backend.ignore("searchContacts");
+
+        let response = client
+            .send_with_timeout(
+                SearchContacts::new(&client, "query".to_string()),
+                Duration::from_millis(50),
+            )
+            .await;
+
+        assert!(response.is_err());
+        assert_eq!(backend.sent_requests_of_type("searchContacts").len(), 1);
+        assert_eq!(client.pending_requests().await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_updates_are_dispatched_to_handlers() {
+        let backend = Arc::new(FakeTdBackend::new());
+        let _client = TelegramClient::init_with_backend::<
+            RecordingAuthorizationHandler,
+            RecordingConnectionHandler,
+        >(TelegramConfig::new(0, String::new()), backend.clone())
+        .await;
+
+        backend.emit_connection_state("connectionStateConnecting");
+        backend.emit_authorization_state("authorizationStateWaitTdlibParameters");
+        backend.emit_authorization_state("authorizationStateReady");
+        backend.emit_connection_state("connectionStateReady");
+
+        let states = wait_for_recorded_states(4).await;
+
+        let authorization_states: Vec<_> = states
+            .iter()
+            .filter(|state| ["set_tdlib_params", "authorization_ready"].contains(state))
+            .collect();
+
+        let connection_states: Vec<_> = states
+            .iter()
+            .filter(|state| ["connecting", "connection_ready"].contains(state))
+            .collect();
+
+        assert_eq!(
+            authorization_states,
+            vec![&"set_tdlib_params", &"authorization_ready"]
+        );
+        assert_eq!(connection_states, vec![&"connecting", &"connection_ready"]);
+    }
+}
@@ -0,0 +1,147 @@
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use crate::{
+        AuthorizationHandler, ConsoleConnectionHandler, FakeTdBackend, TelegramAlertHandler,
+        TelegramAuthorizationStatus, TelegramClient, TelegramConfig, TelegramConnectionStatus,
+        TelegramStatus,
+    };
+
+    struct SilentAuthorizationHandler {
+        client: Arc<TelegramClient>,
+    }
+
+    #[async_trait::async_trait]
+    impl AuthorizationHandler for SilentAuthorizationHandler {
+        fn new(client: Arc<TelegramClient>) -> Self {
+            Self { client }
+        }
+
+        fn get_client(&self) -> Arc<TelegramClient> {
+            self.client.clone()
+        }
+
+        async fn handle_set_tdlib_params(&self) {}
+
+        async fn handle_wait_other_device_confirmation(&self, _: String) {}
+
+        async fn handle_wait_password(&self, _: Option<String>) {}
+
+        async fn handle_ready(&self) {}
+
+        async fn handle_closed(&self) {}
+    }
+
+    struct RecordingAlertHandler {
+        alerts: Arc<Mutex<Vec<TelegramStatus>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TelegramAlertHandler for RecordingAlertHandler {
+        async fn handle_login_required(&self, status: &TelegramStatus) {
+            self.alerts.lock().unwrap().push(status.clone());
+        }
+    }
+
+    async fn setup() -> (Arc<FakeTdBackend>, Arc<TelegramClient>) {
+        let backend = Arc::new(FakeTdBackend::new());
+        let client = TelegramClient::init_with_backend::<
+            SilentAuthorizationHandler,
+            ConsoleConnectionHandler,
+        >(TelegramConfig::new(0, String::new()), backend.clone())
+        .await;
+
+        (backend, client)
+    }
+
+    async fn wait_for_status<F>(client: &TelegramClient, predicate: F) -> TelegramStatus
+    where
+        F: Fn(&TelegramStatus) -> bool,
+    {
+        let mut status = client.subscribe_status();
+
+        let _ =
+            tokio::time::timeout(Duration::from_secs(2), status.wait_for(|s| predicate(s))).await;
+
+        client.status()
+    }
+
+    #[tokio::test]
+    async fn test_tracks_authorization_and_connection_state() {
+        let (backend, client) = setup().await;
+
+        assert_eq!(client.status(), TelegramStatus::default());
+
+        backend.emit_connection_state("connectionStateConnecting");
+        backend.emit_authorization_state("authorizationStateReady");
+
+        let status = wait_for_status(&client, |status| {
+            status.is_authorized() && status.connection == TelegramConnectionStatus::Connecting
+        })
+        .await;
+
+        assert!(status.is_authorized());
+        assert_eq!(status.connection, TelegramConnectionStatus::Connecting);
+    }
+
+    #[tokio::test]
+    async fn test_restarts_client_when_closed() {
+        let (backend, client) = setup().await;
+        let first_client_id = client.client_id();
+
+        backend.emit_authorization_state("authorizationStateReady");
+        wait_for_status(&client, TelegramStatus::is_authorized).await;
+
+        backend.emit_authorization_state("authorizationStateClosed");
+
+        let status = wait_for_status(&client, |status| status.restarts == 1).await;
+
+        assert_eq!(status.restarts, 1);
+        assert_ne!(client.client_id(), first_client_id);
+        assert!(!status.is_authorized());
+
+        let requests = backend.sent_requests_of_type("getAuthorizationState");
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["@client_id"], client.client_id());
+    }
+
+    #[tokio::test]
+    async fn test_alerts_once_when_login_is_required() {
+        let (backend, client) = setup().await;
+        let alerts = Arc::new(Mutex::new(Vec::new()));
+
+        client
+            .set_alert_handler(RecordingAlertHandler {
+                alerts: alerts.clone(),
+            })
+            .await;
+
+        // tdlib refreshes the QR code link regularly, each refresh is a new update
+        for _ in 0..2 {
+            backend.emit_update(json!({
+                "@type": "updateAuthorizationState",
+                "authorization_state": {
+                    "@type": "authorizationStateWaitOtherDeviceConfirmation",
+                    "link": "tg://login?token=test",
+                },
+            }));
+        }
+        backend.emit_authorization_state("authorizationStateWaitPassword");
+
+        wait_for_status(&client, TelegramStatus::is_login_required).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let alerts = alerts.lock().unwrap().clone();
+
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].authorization,
+            TelegramAuthorizationStatus::WaitingForLogin
+        );
+    }
+}
@@ -1,6 +1,8 @@
 package main
 
 import (
+	"flag"
+	"fmt"
 	"github.com/containers/winquit/pkg/winquit"
 	"github.com/gin-gonic/gin"
 	"net"
@@ -96,6 +98,12 @@ func startConnection(c *gin.Context) {
 }
 
 func main() {
+	// The desktop app picks another port when 5003 is taken
+	port := flag.Int("port", 5003, "port of the HTTP server")
+	flag.Parse()
+
+	address := fmt.Sprintf("0.0.0.0:%d", *port)
+
 	bot, err := whatsapp.New("INFO")
 
 	if err != nil {
@@ -135,20 +143,20 @@ func main() {
 	router.GET("/info", info)
 	router.POST("/send_message", sendMessage)
 	router.POST("/start_connection", startConnection)
-	println("Starting HTTP server on port 5003...")
+	println(fmt.Sprintf("Starting HTTP server on port %d...", *port))
 
-	listener, err := net.Listen("tcp", "0.0.0.0:5003")
+	listener, err := net.Listen("tcp", address)
 
 	if err != nil {
-		println("[ERROR] Failed to start HTTP server on port 5003: %v", err)
+		println(fmt.Sprintf("[ERROR] Failed to start HTTP server on port %d: %v", *port, err))
 		os.Exit(1)
 	} else {
-		println("Started web server on port 5003")
+		println(fmt.Sprintf("Started web server on port %d", *port))
 	}
 
 	err = router.RunListener(listener)
 
 	if err != nil {
-		println("[ERROR] Failed to start HTTP server on port 5003: %v", err)
+		println(fmt.Sprintf("[ERROR] Failed to start HTTP server on port %d: %v", *port, err))
 	}
 }